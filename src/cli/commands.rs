@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use tapo::ApiClient;
+
+use crate::cli::DeviceAction;
+use crate::settings::{Device, DeviceType, Settings};
+use crate::system::discovery::protocol::{self, DISCOVERY_PORT};
+
+const DEFAULT_DISCOVERY_TIMEOUT_S: u64 = 3;
+
+pub fn check_config(config: Option<PathBuf>) -> anyhow::Result<()> {
+    let settings = Settings::new(config)?;
+
+    println!(
+        "Settings are valid: {} device(s), discovery {}",
+        settings.devices.len(),
+        if settings.discovery.is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    Ok(())
+}
+
+pub fn list_devices(config: Option<PathBuf>) -> anyhow::Result<()> {
+    let settings = Settings::new(config)?;
+
+    println!(
+        "{:<24} {:<16} {:<18} {:<12} REFRESH RATE",
+        "NAME", "IP ADDRESS", "MAC", "CREDENTIALS"
+    );
+
+    for device in &settings.devices {
+        println!(
+            "{:<24} {:<16} {:<18} {:<12} {}s",
+            device.name,
+            device.ip_address,
+            device.mac.as_deref().unwrap_or("-"),
+            device.credentials.as_deref().unwrap_or("default"),
+            device
+                .refresh_rate_s
+                .unwrap_or(settings.tapo.refresh_rate_s),
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn device(
+    config: Option<PathBuf>,
+    name: &str,
+    action: DeviceAction,
+) -> anyhow::Result<()> {
+    let settings = Settings::new(config)?;
+    let device = find_device(&settings, name)?;
+
+    let handler = client(&settings, device)?
+        .generic_device(device.ip_address.clone())
+        .await
+        .with_context(|| format!("failed to connect to '{name}'"))?;
+
+    let device_on = match action {
+        DeviceAction::On => true,
+        DeviceAction::Off => false,
+        DeviceAction::Toggle => {
+            let device_info = handler.get_device_info().await?;
+            !device_info
+                .device_on
+                .with_context(|| format!("'{name}' can't be turned on or off"))?
+        }
+        DeviceAction::Info => {
+            let device_info = handler.get_device_info().await?;
+            println!("{}", serde_json::to_string_pretty(&device_info)?);
+            return Ok(());
+        }
+    };
+
+    if device_on {
+        handler.on().await?;
+    } else {
+        handler.off().await?;
+    }
+
+    println!("'{name}' is {}", if device_on { "on" } else { "off" });
+
+    Ok(())
+}
+
+pub async fn usage(config: Option<PathBuf>, name: &str) -> anyhow::Result<()> {
+    let settings = Settings::new(config)?;
+    let device = find_device(&settings, name)?;
+    anyhow::ensure!(
+        device.device_type == DeviceType::Plug,
+        "'{name}' is a {}, only plugs report usage",
+        device.device_type
+    );
+
+    let handler = client(&settings, device)?
+        .p110(device.ip_address.clone())
+        .await
+        .with_context(|| format!("failed to connect to '{name}'"))?;
+
+    let device_usage = handler.get_device_usage().await?;
+    println!("{}", serde_json::to_string_pretty(&device_usage)?);
+
+    Ok(())
+}
+
+pub async fn discover(
+    config: Option<PathBuf>,
+    targets: Vec<String>,
+    timeout_s: Option<u64>,
+) -> anyhow::Result<()> {
+    // the settings are only needed when the targets aren't given on the command line
+    let discovery = if targets.is_empty() {
+        Settings::new(config)?.discovery
+    } else {
+        None
+    };
+
+    let targets = match &discovery {
+        Some(discovery) => discovery.targets.clone(),
+        None => targets,
+    };
+    anyhow::ensure!(
+        !targets.is_empty(),
+        "nothing to probe, pass --target or configure `discovery.targets`"
+    );
+
+    let port = discovery.as_ref().map_or(DISCOVERY_PORT, |d| d.port);
+    let timeout_s = timeout_s
+        .or(discovery.as_ref().map(|d| d.timeout_s))
+        .unwrap_or(DEFAULT_DISCOVERY_TIMEOUT_S);
+
+    let probe = protocol::build_probe()?;
+    let mut devices =
+        protocol::discover(&probe, &targets, port, Duration::from_secs(timeout_s)).await?;
+    devices.sort_by(|a, b| a.ip_address.cmp(&b.ip_address));
+
+    println!(
+        "{:<16} {:<18} {:<12} {:<16} DEVICE ID",
+        "IP ADDRESS", "MAC", "MODEL", "TYPE"
+    );
+
+    for device in devices {
+        println!(
+            "{:<16} {:<18} {:<12} {:<16} {}",
+            device.ip_address,
+            device.mac,
+            device.device_model,
+            device.device_type,
+            device.device_id
+        );
+    }
+
+    Ok(())
+}
+
+fn find_device<'a>(settings: &'a Settings, name: &str) -> anyhow::Result<&'a Device> {
+    settings
+        .devices
+        .iter()
+        .find(|device| device.name == name)
+        .with_context(|| {
+            let names: Vec<_> = settings
+                .devices
+                .iter()
+                .map(|device| device.name.as_str())
+                .collect();
+            format!(
+                "unknown device '{name}', expected one of: {}",
+                names.join(", ")
+            )
+        })
+}
+
+fn client(settings: &Settings, device: &Device) -> anyhow::Result<ApiClient> {
+    let credentials = settings
+        .tapo
+        .credentials(device.credentials.as_deref())
+        .with_context(|| {
+            format!(
+                "unknown credentials profile '{}'",
+                device.credentials.as_deref().unwrap_or_default()
+            )
+        })?;
+
+    Ok(ApiClient::new(credentials.username, credentials.password))
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+pub mod commands;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Reads the usage of Tapo devices and publishes it through MQTT"
+)]
+pub struct Cli {
+    /// Path of the settings file [default: `HAT_CONFIG` or `settings.{yaml,toml,json}`]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the service (default)
+    Run,
+    /// Validate the settings and exit
+    CheckConfig,
+    /// Inspect the configured devices
+    Devices {
+        #[command(subcommand)]
+        command: DevicesCommand,
+    },
+    /// Control or inspect a configured device
+    Device {
+        /// Name of the device, as configured in the settings
+        name: String,
+
+        #[command(subcommand)]
+        action: DeviceAction,
+    },
+    /// Print the time and energy usage of a configured device
+    Usage {
+        /// Name of the device, as configured in the settings
+        name: String,
+    },
+    /// Discover Tapo devices on the LAN
+    Discover {
+        /// Subnet (e.g. `192.168.1.0/24`) or address to probe [default: `discovery.targets`]
+        #[arg(long)]
+        target: Vec<String>,
+
+        /// How long to wait for responses [default: `discovery.timeout_s` or 3]
+        #[arg(long)]
+        timeout_s: Option<u64>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DevicesCommand {
+    /// List the configured devices
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum DeviceAction {
+    /// Turn the device on
+    On,
+    /// Turn the device off
+    Off,
+    /// Turn the device on if it's off and vice versa
+    Toggle,
+    /// Print the device info
+    Info,
+}
@@ -1,3 +1,4 @@
+pub mod cli;
 pub mod settings;
 pub mod system;
 pub mod telemetry;
@@ -1,36 +1,106 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use actix::Actor;
-use tracing::info;
+use clap::Parser;
+use tracing::{info, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
+use home_automation_tapo::cli::{Cli, Command, DevicesCommand, commands};
 use home_automation_tapo::settings::Settings;
 use home_automation_tapo::system::coordinator_actor::CoordinatorActor;
+use home_automation_tapo::system::messages::ShutdownMessage;
 use home_automation_tapo::telemetry::{init_telemetry, shutdown_telemetry};
 
 #[actix_rt::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let settings = Settings::new().expect("failed to read the settings");
+async fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(cli.config).await,
+        Command::CheckConfig => commands::check_config(cli.config),
+        Command::Devices {
+            command: DevicesCommand::List,
+        } => commands::list_devices(cli.config),
+        Command::Device { name, action } => commands::device(cli.config, &name, action).await,
+        Command::Usage { name } => commands::usage(cli.config, &name).await,
+        Command::Discover { target, timeout_s } => {
+            commands::discover(cli.config, target, timeout_s).await
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{e:#}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(config: Option<PathBuf>) -> anyhow::Result<()> {
+    let settings = Settings::new(config)?;
 
-    let tracer_provider = init_telemetry(&settings.telemetry)?;
+    let tracer_provider = init_telemetry(&settings.telemetry)
+        .map_err(|e| anyhow::anyhow!("failed to initialize telemetry: {e}"))?;
 
     info!("Starting home automation tapo system with Actix-RT on Tokio runtime");
 
+    let shutdown_timeout = Duration::from_secs(settings.shutdown.timeout_s);
+
     // Start coordinator actor
     let coordinator =
         CoordinatorActor::new(settings).expect("Failed to create the CoordinatorActor");
-    coordinator.start();
+    let coordinator_addr = coordinator.start();
 
     info!("System started, waiting for shutdown signal...");
 
     // Wait for shutdown signal
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to listen for ctrl+c");
+    shutdown_signal().await;
 
     info!("Received shutdown signal, shutting down application...");
 
+    let span = tracing::info_span!("main::shutdown");
+    let shutdown = coordinator_addr.send(ShutdownMessage {
+        span_context: span.context(),
+    });
+
+    match tokio::time::timeout(shutdown_timeout, shutdown).await {
+        Ok(Ok(())) => info!("All in-flight work has drained"),
+        Ok(Err(e)) => warn!("Failed to shut down the actors: {e}"),
+        Err(_) => warn!(
+            "In-flight work didn't drain within {}s, exiting anyway",
+            shutdown_timeout.as_secs()
+        ),
+    }
+
     // Shutdown telemetry
     if let Some(tracer_provider) = tracer_provider {
-        shutdown_telemetry(tracer_provider)?;
+        shutdown_telemetry(tracer_provider)
+            .map_err(|e| anyhow::anyhow!("failed to shut down telemetry: {e}"))?;
     }
 
     Ok(())
 }
+
+/// Resolves on ctrl+c or, on Unix, on SIGTERM (e.g. `docker stop`).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for ctrl+c");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
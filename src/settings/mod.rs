@@ -0,0 +1,793 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use chrono::{Datelike as _, NaiveDateTime, NaiveTime, Weekday};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use tapo::requests::LightingEffectPreset;
+
+pub use validation::{ValidationError, ValidationErrors};
+
+mod validation;
+
+/// Environment variable holding the path of the settings file.
+pub const CONFIG_PATH_ENV: &str = "HAT_CONFIG";
+
+const ENV_PREFIX: &str = "HAT__";
+const ENV_SEPARATOR: &str = "__";
+const ENV_FILE_SUFFIX: &str = "_FILE";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Telemetry {
+    pub service_name: String,
+    pub service_namespace: String,
+    pub deployment_environment: String,
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Api {
+    pub host: String,
+    pub port: u16,
+    /// Every route but `/health-check` requires credentials when set.
+    pub auth: Option<ApiAuth>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiAuth {
+    /// Sent as `Authorization: Bearer <token>`.
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+    /// Sent with HTTP basic auth.
+    #[serde(default)]
+    pub users: Vec<ApiUser>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiToken {
+    pub token: String,
+    pub scope: ApiScope,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiUser {
+    pub username: String,
+    pub password: String,
+    pub scope: ApiScope,
+}
+
+/// What the credentials give access to, `control` includes `read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    /// The `GET` routes.
+    Read,
+    /// Every route, e.g. switching devices.
+    Control,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tapo {
+    pub username: String,
+    pub password: String,
+    /// Named credential profiles for devices bound to other Tapo accounts.
+    #[serde(default)]
+    pub credentials: HashMap<String, Credentials>,
+    pub refresh_rate_s: u64,
+    /// Upper bound of the random delay before a device's first poll, spreading polls out.
+    #[serde(default)]
+    pub poll_jitter_s: u64,
+}
+
+impl Tapo {
+    /// Returns the credentials of the given profile, or the default ones when no profile is given.
+    /// Returns `None` if the profile doesn't exist.
+    pub fn credentials(&self, profile: Option<&str>) -> Option<Credentials> {
+        match profile {
+            Some(profile) => self.credentials.get(profile).cloned(),
+            None => Some(Credentials {
+                username: self.username.clone(),
+                password: self.password.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mqtt {
+    pub address: String,
+    pub topic_name: String,
+    /// Topic of the retained `online`/`offline` status, defaults to `<topic_name>/status`.
+    pub status_topic_name: Option<String>,
+    /// Topic of the energy breakdowns, defaults to `<topic_name>/energy`.
+    pub energy_topic_name: Option<String>,
+    /// Topic of the alerts, defaults to `<topic_name>/alerts`.
+    pub alert_topic_name: Option<String>,
+    /// Topic of the appliance cycle events, defaults to `<topic_name>/cycles`.
+    pub cycle_topic_name: Option<String>,
+    /// Topic the names of the scenes to activate are read from, defaults to `<topic_name>/scenes`.
+    pub scene_topic_name: Option<String>,
+    /// Topic of the bulb and light strip states, defaults to `<topic_name>/lights`.
+    pub light_topic_name: Option<String>,
+    /// Topic of the power strip outlet states, defaults to `<topic_name>/outlets`.
+    pub outlet_topic_name: Option<String>,
+    /// Parent topic of the hub sensor readings, defaults to `<topic_name>/sensors`.
+    pub sensor_topic_name: Option<String>,
+}
+
+impl Mqtt {
+    pub fn status_topic(&self) -> String {
+        self.status_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/status", self.topic_name))
+    }
+
+    pub fn energy_topic(&self) -> String {
+        self.energy_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/energy", self.topic_name))
+    }
+
+    pub fn alert_topic(&self) -> String {
+        self.alert_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/alerts", self.topic_name))
+    }
+
+    pub fn cycle_topic(&self) -> String {
+        self.cycle_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/cycles", self.topic_name))
+    }
+
+    pub fn scene_topic(&self) -> String {
+        self.scene_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/scenes", self.topic_name))
+    }
+
+    pub fn light_topic(&self) -> String {
+        self.light_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/lights", self.topic_name))
+    }
+
+    pub fn outlet_topic(&self) -> String {
+        self.outlet_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/outlets", self.topic_name))
+    }
+
+    /// Topic of a single sensor, every sensor has its own under the sensor topic.
+    pub fn sensor_topic(&self, device_id: &str) -> String {
+        let sensor_topic_name = self
+            .sensor_topic_name
+            .clone()
+            .unwrap_or_else(|| format!("{}/sensors", self.topic_name));
+
+        format!("{sensor_topic_name}/{device_id}")
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Device {
+    pub ip_address: String,
+    pub name: String,
+    #[serde(default)]
+    pub device_type: DeviceType,
+    pub record_time_usage: bool,
+    /// Overrides `tapo.refresh_rate_s` for this device.
+    pub refresh_rate_s: Option<u64>,
+    /// Name of the `tapo.credentials` profile to use instead of the default credentials.
+    pub credentials: Option<String>,
+    /// When set, the device is verified against it and followed across IP address changes.
+    pub mac: Option<String>,
+    /// When set, the device is verified against it and followed across IP address changes.
+    pub device_id: Option<String>,
+    /// When set, the cycles of the appliance plugged into the device are detected.
+    pub cycle: Option<CycleDetection>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    /// Energy monitoring plug, e.g. P110 or P115.
+    #[default]
+    #[display("plug")]
+    Plug,
+    /// Dimmable bulb, e.g. L510 or L610.
+    #[display("bulb")]
+    Bulb,
+    /// Color bulb, e.g. L530 or L630.
+    #[display("color bulb")]
+    ColorBulb,
+    /// Light strip, e.g. L900.
+    #[display("light strip")]
+    LightStrip,
+    /// Light strip with individually colored segments and lighting effects, e.g. L920 or L930.
+    #[display("RGBIC light strip")]
+    RgbicLightStrip,
+    /// Power strip with individually switchable outlets, e.g. P300 or P304M.
+    #[display("power strip")]
+    PowerStrip,
+    /// Hub relaying the readings of its sensors, e.g. H100.
+    #[display("hub")]
+    Hub,
+}
+
+impl DeviceType {
+    pub fn is_light(self) -> bool {
+        matches!(
+            self,
+            Self::Bulb | Self::ColorBulb | Self::LightStrip | Self::RgbicLightStrip
+        )
+    }
+
+    pub fn supports_color(self) -> bool {
+        matches!(
+            self,
+            Self::ColorBulb | Self::LightStrip | Self::RgbicLightStrip
+        )
+    }
+
+    pub fn supports_lighting_effects(self) -> bool {
+        self == Self::RgbicLightStrip
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CycleDetection {
+    /// A cycle starts once the current power reaches it, in watts.
+    pub start_threshold_w: u64,
+    /// A cycle is idle while the current power is at or below it, in watts.
+    pub idle_threshold_w: u64,
+    /// How long a cycle must be idle before it's finished, so pauses don't end it.
+    pub min_idle_s: u64,
+}
+
+impl Device {
+    pub fn has_identity(&self) -> bool {
+        self.mac.is_some() || self.device_id.is_some()
+    }
+
+    /// Whether the given MAC and device id match the configured identity.
+    /// Devices without a configured identity match anything.
+    pub fn is_identified_by(&self, mac: &str, device_id: &str) -> bool {
+        let mac_matches = self
+            .mac
+            .as_deref()
+            .is_none_or(|expected| normalize_mac(expected) == normalize_mac(mac));
+        let device_id_matches = self
+            .device_id
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(device_id));
+
+        mac_matches && device_id_matches
+    }
+}
+
+/// Normalizes a MAC address to the `AA:BB:CC:DD:EE:FF` form.
+pub fn normalize_mac(mac: &str) -> String {
+    mac.trim().replace('-', ":").to_uppercase()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Discovery {
+    /// Subnets (e.g. `192.168.1.0/24`) or unicast/broadcast addresses to probe
+    pub targets: Vec<String>,
+    #[serde(default = "default_discovery_port")]
+    pub port: u16,
+    pub interval_s: u64,
+    pub timeout_s: u64,
+    pub auto_register: bool,
+}
+
+fn default_discovery_port() -> u16 {
+    crate::system::discovery::protocol::DISCOVERY_PORT
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Energy {
+    /// How often to fetch the hourly, daily and monthly energy breakdowns.
+    pub refresh_rate_s: u64,
+    /// How far back to backfill the intervals missed while the service or the broker was down.
+    #[serde(default = "default_backfill_days")]
+    pub backfill_days: u64,
+}
+
+fn default_backfill_days() -> u64 {
+    7
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Storage {
+    /// Path of the SQLite database, created if it doesn't exist.
+    pub path: String,
+    /// How long every sample is kept before being downsampled to one sample per hour.
+    pub raw_retention_days: u64,
+    /// How long the hourly samples are kept before being downsampled to one sample per day.
+    pub hourly_retention_days: u64,
+    /// How long the daily samples are kept, forever when not set.
+    pub daily_retention_days: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TariffDays {
+    #[default]
+    All,
+    Weekdays,
+    Weekends,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TariffBand {
+    pub price_per_kwh: f64,
+    /// Local time the band starts at, e.g. `07:00`.
+    pub start: NaiveTime,
+    /// Local time the band ends at, exclusive. Bands ending before they start span midnight,
+    /// bands ending when they start last all day.
+    pub end: NaiveTime,
+    /// The days the band applies on, matched against the day the time falls on.
+    #[serde(default)]
+    pub days: TariffDays,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tariff {
+    pub currency: String,
+    /// Price per kWh whenever none of the bands apply, i.e. the flat rate.
+    pub price_per_kwh: f64,
+    /// Time-of-use bands, the first one that applies wins.
+    #[serde(default)]
+    pub bands: Vec<TariffBand>,
+    /// Fixed daily charge, reported separately since it doesn't depend on any device.
+    #[serde(default)]
+    pub standing_charge_per_day: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The current power is above `threshold` watts.
+    PowerAbove,
+    /// The current power is below `threshold` watts.
+    PowerBelow,
+    /// Today's runtime is above `threshold` minutes.
+    RuntimeTodayAbove,
+    /// The device doesn't answer its polls.
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub name: String,
+    /// Name of the device the rule applies to.
+    pub device: String,
+    pub condition: AlertCondition,
+    /// Watts or minutes, depending on the condition. Not used by `unreachable`.
+    pub threshold: Option<u64>,
+    /// How long the condition must hold before the alert fires.
+    #[serde(default)]
+    pub for_s: u64,
+    /// How far past the threshold the value must go back before the alert resolves.
+    #[serde(default)]
+    pub hysteresis: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    /// Extra headers sent along, e.g. for authentication. Never serialized since they're secrets.
+    #[serde(default, skip_serializing)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Alerts {
+    pub rules: Vec<AlertRule>,
+    /// Every alert is posted to each of them, on top of being published to MQTT.
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+}
+
+/// The days a time window applies on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Days {
+    #[default]
+    All,
+    Weekdays,
+    Weekends,
+}
+
+impl Days {
+    pub fn contains(self, weekday: Weekday) -> bool {
+        let weekend = matches!(weekday, Weekday::Sat | Weekday::Sun);
+
+        match self {
+            Days::All => true,
+            Days::Weekdays => !weekend,
+            Days::Weekends => weekend,
+        }
+    }
+}
+
+/// A daily window of local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindow {
+    /// e.g. `07:00`
+    pub start: NaiveTime,
+    /// Exclusive. Windows ending before they start span midnight, windows ending when they start
+    /// last all day.
+    pub end: NaiveTime,
+    /// Matched against the day the time falls on.
+    #[serde(default)]
+    pub days: Days,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        let day_matches = self.days.contains(time.weekday());
+
+        let time = time.time();
+        let time_matches = match self.start.cmp(&self.end) {
+            Ordering::Less => self.start <= time && time < self.end,
+            Ordering::Greater => self.start <= time || time < self.end,
+            Ordering::Equal => true,
+        };
+
+        day_matches && time_matches
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutomationCondition {
+    /// Name of the device the condition looks at.
+    pub device: String,
+    /// Holds while the current power is above it, in watts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power_above: Option<u64>,
+    /// Holds while the current power is below it, in watts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power_below: Option<u64>,
+    /// Holds while the device is on, or off when `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_on: Option<bool>,
+    /// Holds as the event is read from a sensor of the hub, for a single evaluation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<SensorEventKind>,
+    /// Device id or nickname of the sensor the `event` must come from, any sensor of the hub
+    /// otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor: Option<String>,
+    /// How long the condition must hold before it counts.
+    #[serde(default)]
+    pub for_s: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum AutomationAction {
+    Device {
+        name: String,
+        action: DeviceAction,
+    },
+    Mqtt {
+        topic: String,
+        payload: String,
+    },
+    /// Posts the triggered automation as JSON.
+    Webhook(Webhook),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Automation {
+    pub name: String,
+    /// Every one of them must hold for the automation to trigger.
+    pub conditions: Vec<AutomationCondition>,
+    /// Optional, the automation only triggers within it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<TimeWindow>,
+    pub actions: Vec<AutomationAction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Automations {
+    pub rules: Vec<Automation>,
+}
+
+/// Event of the trigger log of a contact or motion sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorEventKind {
+    Open,
+    Close,
+    /// A contact sensor has been open for more than a minute.
+    KeepOpen,
+    Motion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceAction {
+    On,
+    Off,
+    Toggle,
+}
+
+/// Target state of a device in a scene. Brightness and color only apply to bulbs and light strips.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneState {
+    pub action: DeviceAction,
+    /// Between 1 and 100.
+    pub brightness: Option<u8>,
+    /// Between 0 and 360, together with `saturation`.
+    pub hue: Option<u16>,
+    /// Between 1 and 100, together with `hue`.
+    pub saturation: Option<u8>,
+    /// Between 2500 and 6500 kelvin, instead of `hue` and `saturation`.
+    pub color_temperature: Option<u16>,
+}
+
+impl SceneState {
+    pub fn has_light_settings(&self) -> bool {
+        !self.light().is_empty()
+    }
+
+    pub fn light(&self) -> LightSettings {
+        LightSettings {
+            brightness: self.brightness,
+            hue: self.hue,
+            saturation: self.saturation,
+            color_temperature: self.color_temperature,
+            lighting_effect: None,
+        }
+    }
+}
+
+/// Settings of a bulb or light strip, applied in a single request which also turns it on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightSettings {
+    /// Between 1 and 100.
+    pub brightness: Option<u8>,
+    /// Between 0 and 360, together with `saturation`.
+    pub hue: Option<u16>,
+    /// Between 1 and 100, together with `hue`.
+    pub saturation: Option<u8>,
+    /// Between 2500 and 6500 kelvin, instead of `hue` and `saturation`.
+    pub color_temperature: Option<u16>,
+    /// One of the presets of the Tapo app, e.g. `Aurora`. Can't be combined with the others.
+    pub lighting_effect: Option<LightingEffectPreset>,
+}
+
+impl LightSettings {
+    pub fn is_empty(&self) -> bool {
+        self.brightness.is_none()
+            && self.hue.is_none()
+            && self.saturation.is_none()
+            && self.color_temperature.is_none()
+            && self.lighting_effect.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    pub name: String,
+    /// Target states by device name.
+    pub devices: BTreeMap<String, SceneState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SunEvent {
+    Sunrise,
+    Sunset,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    pub name: String,
+    /// Name of the device the schedule controls.
+    pub device: String,
+    pub action: DeviceAction,
+    /// Cron expression in local time, e.g. `0 7 * * MON-FRI`. Either this or `sun` must be set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sun: Option<SunEvent>,
+    /// Minutes after `sun`, negative for before.
+    #[serde(default)]
+    pub offset_min: i64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scheduler {
+    /// Required by the `sun` schedules.
+    pub location: Option<Location>,
+    /// How long the schedules leave a device alone after it's switched through the API.
+    #[serde(default = "default_override_s")]
+    pub override_s: u64,
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+}
+
+fn default_override_s() -> u64 {
+    60 * 60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shutdown {
+    /// How long to wait for in-flight work to drain before exiting anyway.
+    pub timeout_s: u64,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { timeout_s: 10 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub telemetry: Telemetry,
+    pub api: Api,
+    pub tapo: Tapo,
+    pub mqtt: Mqtt,
+    pub discovery: Option<Discovery>,
+    pub energy: Option<Energy>,
+    pub storage: Option<Storage>,
+    pub tariff: Option<Tariff>,
+    pub alerts: Option<Alerts>,
+    pub scheduler: Option<Scheduler>,
+    pub automations: Option<Automations>,
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+    #[serde(default)]
+    pub shutdown: Shutdown,
+    pub devices: Vec<Device>,
+}
+
+impl Settings {
+    /// Loads the settings from `config_path`, `HAT_CONFIG` or `settings.{yaml,toml,json}` in the
+    /// current directory, with `HAT__` environment variable overrides layered on top.
+    pub fn new(config_path: Option<PathBuf>) -> Result<Self, anyhow::Error> {
+        Self::load(config_path, std::env::vars())
+    }
+
+    /// Same as [`Settings::new`], but reads the environment variables from `vars`.
+    ///
+    /// Keys are nested with `__`, e.g. `HAT__TAPO__PASSWORD` overrides `tapo.password`.
+    /// A `_FILE` suffix reads the value from the given file instead, e.g.
+    /// `HAT__TAPO__PASSWORD_FILE=/run/secrets/tapo-password`.
+    pub fn load(
+        config_path: Option<PathBuf>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, anyhow::Error> {
+        let vars: HashMap<String, String> = vars.into_iter().collect();
+
+        let config_path = config_path.or_else(|| vars.get(CONFIG_PATH_ENV).map(PathBuf::from));
+
+        let mut builder = config::Config::builder();
+
+        let source = match config_path {
+            Some(config_path) => {
+                let format = file_format(&config_path)?;
+                builder =
+                    builder.add_source(config::File::from(config_path.clone()).format(format));
+                config_path.display().to_string()
+            }
+            None => {
+                let base_path =
+                    std::env::current_dir().expect("failed to determine the current directory");
+                let config_path = base_path.join("settings");
+                builder = builder.add_source(config::File::from(config_path.clone()));
+                format!("{}.{{yaml,toml,json}}", config_path.display())
+            }
+        };
+
+        let mut overrides = HashMap::new();
+
+        for (key, value) in &vars {
+            let Some(key) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+
+            match key.strip_suffix(ENV_FILE_SUFFIX) {
+                Some(key) => {
+                    let value = std::fs::read_to_string(value).with_context(|| {
+                        format!("failed to read '{value}' referenced by {ENV_PREFIX}{key}{ENV_FILE_SUFFIX}")
+                    })?;
+                    builder = builder.set_override(env_key(key), value.trim_end().to_string())?;
+                }
+                None => {
+                    overrides.insert(key.to_string(), value.clone());
+                }
+            }
+        }
+
+        // plain variables take precedence over their `_FILE` counterparts
+        for (key, value) in overrides {
+            builder = builder.set_override(env_key(&key), value)?;
+        }
+
+        let settings = builder
+            .build()
+            .and_then(|config| config.try_deserialize::<Self>())
+            .with_context(|| format!("failed to read the settings from '{source}'"))?;
+
+        settings
+            .validate()
+            .with_context(|| format!("invalid settings in '{source}'"))?;
+
+        Ok(settings)
+    }
+}
+
+fn file_format(path: &Path) -> Result<config::FileFormat, anyhow::Error> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => Ok(config::FileFormat::Yaml),
+        Some("toml") => Ok(config::FileFormat::Toml),
+        Some("json") => Ok(config::FileFormat::Json),
+        _ => anyhow::bail!(
+            "unsupported settings file '{}', expected a .yaml, .yml, .toml or .json file",
+            path.display()
+        ),
+    }
+}
+
+/// Converts the part of an environment variable after the prefix into a settings key,
+/// e.g. `TAPO__PASSWORD` into `tapo.password`.
+fn env_key(key: &str) -> String {
+    key.to_lowercase().replace(ENV_SEPARATOR, ".")
+}
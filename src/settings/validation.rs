@@ -0,0 +1,749 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use derive_more::Display;
+
+use crate::settings::{
+    AlertCondition, Alerts, ApiAuth, AutomationAction, Automations, Device, DeviceAction,
+    DeviceType, Discovery, LightSettings, Location, Scene, Schedule, Scheduler, Settings, Storage,
+    Tariff, normalize_mac,
+};
+use crate::system::discovery::protocol::resolve_target;
+use crate::system::scheduler::schedule::parse_cron;
+
+const MQTT_SCHEMES: &[&str] = &["tcp", "ssl", "ws", "wss", "mqtt", "mqtts"];
+const OTLP_SCHEMES: &[&str] = &["http", "https"];
+const WEBHOOK_SCHEMES: &[&str] = &["http", "https"];
+
+#[derive(Debug, Display)]
+#[display("{key}: {message}")]
+pub struct ValidationError {
+    pub key: String,
+    pub message: String,
+}
+
+/// Every problem found in the settings, so they can all be fixed in one go.
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationErrors {
+    fn push(&mut self, key: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(ValidationError {
+            key: key.into(),
+            message: message.into(),
+        });
+    }
+
+    fn require_non_empty(&mut self, key: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.push(key, "must not be empty");
+        }
+    }
+
+    fn require_non_zero(&mut self, key: &str, value: u64) {
+        if value == 0 {
+            self.push(key, "must be greater than 0");
+        }
+    }
+
+    fn require_price(&mut self, key: &str, value: f64) {
+        if !value.is_finite() || value < 0.0 {
+            self.push(key, "must be a positive number or 0");
+        }
+    }
+
+    fn require_host(&mut self, key: &str, value: &str) {
+        if !is_valid_host(value) {
+            self.push(
+                key,
+                format!("'{value}' is not a valid IP address or hostname"),
+            );
+        }
+    }
+
+    fn require_url(&mut self, key: &str, value: &str, schemes: &[&str]) {
+        let Some((scheme, rest)) = value.split_once("://") else {
+            self.push(
+                key,
+                format!(
+                    "'{value}' is missing a scheme, expected one of: {}",
+                    schemes.join(", ")
+                ),
+            );
+            return;
+        };
+
+        if !schemes.contains(&scheme.to_lowercase().as_str()) {
+            self.push(
+                key,
+                format!(
+                    "unsupported scheme '{scheme}', expected one of: {}",
+                    schemes.join(", ")
+                ),
+            );
+        }
+
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (host, port) = match authority.strip_prefix('[') {
+            // IPv6 literal, e.g. `[::1]:1883`
+            Some(bracketed) => match bracketed.split_once(']') {
+                Some((host, rest)) => (host, rest.strip_prefix(':')),
+                None => (authority, None),
+            },
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+
+        if let Some(port) = port
+            && port.parse::<u16>().is_err()
+        {
+            self.push(key, format!("'{port}' is not a valid port"));
+        }
+
+        self.require_host(key, host);
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "  - {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl Settings {
+    /// Checks the settings for problems that deserialization can't catch.
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        errors.require_non_empty("telemetry.service_name", &self.telemetry.service_name);
+        errors.require_non_empty(
+            "telemetry.service_namespace",
+            &self.telemetry.service_namespace,
+        );
+        errors.require_non_empty(
+            "telemetry.deployment_environment",
+            &self.telemetry.deployment_environment,
+        );
+        if let Some(otlp_endpoint) = &self.telemetry.otlp_endpoint {
+            errors.require_url("telemetry.otlp_endpoint", otlp_endpoint, OTLP_SCHEMES);
+        }
+
+        errors.require_host("api.host", &self.api.host);
+        if let Some(auth) = &self.api.auth {
+            validate_api_auth(&mut errors, auth);
+        }
+
+        errors.require_non_empty("tapo.username", &self.tapo.username);
+        errors.require_non_empty("tapo.password", &self.tapo.password);
+        errors.require_non_zero("tapo.refresh_rate_s", self.tapo.refresh_rate_s);
+        for (profile, credentials) in &self.tapo.credentials {
+            errors.require_non_empty(
+                &format!("tapo.credentials.{profile}.username"),
+                &credentials.username,
+            );
+            errors.require_non_empty(
+                &format!("tapo.credentials.{profile}.password"),
+                &credentials.password,
+            );
+        }
+
+        errors.require_url("mqtt.address", &self.mqtt.address, MQTT_SCHEMES);
+        errors.require_non_empty("mqtt.topic_name", &self.mqtt.topic_name);
+        if let Some(status_topic_name) = &self.mqtt.status_topic_name {
+            errors.require_non_empty("mqtt.status_topic_name", status_topic_name);
+        }
+        if let Some(energy_topic_name) = &self.mqtt.energy_topic_name {
+            errors.require_non_empty("mqtt.energy_topic_name", energy_topic_name);
+        }
+        if let Some(alert_topic_name) = &self.mqtt.alert_topic_name {
+            errors.require_non_empty("mqtt.alert_topic_name", alert_topic_name);
+        }
+        if let Some(cycle_topic_name) = &self.mqtt.cycle_topic_name {
+            errors.require_non_empty("mqtt.cycle_topic_name", cycle_topic_name);
+        }
+        if let Some(scene_topic_name) = &self.mqtt.scene_topic_name {
+            errors.require_non_empty("mqtt.scene_topic_name", scene_topic_name);
+        }
+        if let Some(light_topic_name) = &self.mqtt.light_topic_name {
+            errors.require_non_empty("mqtt.light_topic_name", light_topic_name);
+        }
+        if let Some(outlet_topic_name) = &self.mqtt.outlet_topic_name {
+            errors.require_non_empty("mqtt.outlet_topic_name", outlet_topic_name);
+        }
+        if let Some(sensor_topic_name) = &self.mqtt.sensor_topic_name {
+            errors.require_non_empty("mqtt.sensor_topic_name", sensor_topic_name);
+        }
+
+        if let Some(discovery) = &self.discovery {
+            validate_discovery(&mut errors, discovery);
+        }
+
+        if let Some(energy) = &self.energy {
+            errors.require_non_zero("energy.refresh_rate_s", energy.refresh_rate_s);
+        }
+
+        if let Some(storage) = &self.storage {
+            validate_storage(&mut errors, storage);
+        }
+
+        if let Some(tariff) = &self.tariff {
+            validate_tariff(&mut errors, tariff);
+        }
+
+        if let Some(alerts) = &self.alerts {
+            validate_alerts(&mut errors, alerts);
+        }
+
+        if let Some(scheduler) = &self.scheduler {
+            validate_scheduler(&mut errors, scheduler);
+        }
+
+        if let Some(automations) = &self.automations {
+            validate_automations(&mut errors, automations);
+        }
+
+        validate_scenes(&mut errors, &self.scenes, &self.devices);
+
+        errors.require_non_zero("shutdown.timeout_s", self.shutdown.timeout_s);
+
+        let mut names = HashMap::new();
+        let mut ip_addresses = HashMap::new();
+
+        for (index, device) in self.devices.iter().enumerate() {
+            validate_device(&mut errors, self, index, device);
+
+            if let Some(first) = names.insert(device.name.as_str(), index) {
+                errors.push(
+                    format!("devices[{index}].name"),
+                    format!("'{}' is already used by devices[{first}]", device.name),
+                );
+            }
+
+            if let Some(first) = ip_addresses.insert(device.ip_address.as_str(), index) {
+                errors.push(
+                    format!("devices[{index}].ip_address"),
+                    format!(
+                        "'{}' is already used by devices[{first}]",
+                        device.ip_address
+                    ),
+                );
+            }
+        }
+
+        if errors.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_discovery(errors: &mut ValidationErrors, discovery: &Discovery) {
+    if discovery.targets.is_empty() {
+        errors.push("discovery.targets", "must contain at least one target");
+    }
+
+    for (index, target) in discovery.targets.iter().enumerate() {
+        if let Err(e) = resolve_target(target) {
+            errors.push(format!("discovery.targets[{index}]"), e.to_string());
+        }
+    }
+
+    errors.require_non_zero("discovery.interval_s", discovery.interval_s);
+    errors.require_non_zero("discovery.timeout_s", discovery.timeout_s);
+}
+
+fn validate_storage(errors: &mut ValidationErrors, storage: &Storage) {
+    errors.require_non_empty("storage.path", &storage.path);
+    errors.require_non_zero("storage.raw_retention_days", storage.raw_retention_days);
+    errors.require_non_zero(
+        "storage.hourly_retention_days",
+        storage.hourly_retention_days,
+    );
+
+    if storage.daily_retention_days == Some(0) {
+        errors.push("storage.daily_retention_days", "must be greater than 0");
+    }
+}
+
+fn validate_tariff(errors: &mut ValidationErrors, tariff: &Tariff) {
+    errors.require_non_empty("tariff.currency", &tariff.currency);
+    errors.require_price("tariff.price_per_kwh", tariff.price_per_kwh);
+    errors.require_price(
+        "tariff.standing_charge_per_day",
+        tariff.standing_charge_per_day,
+    );
+
+    for (index, band) in tariff.bands.iter().enumerate() {
+        errors.require_price(
+            &format!("tariff.bands[{index}].price_per_kwh"),
+            band.price_per_kwh,
+        );
+    }
+}
+
+fn validate_alerts(errors: &mut ValidationErrors, alerts: &Alerts) {
+    let mut names = HashMap::new();
+
+    for (index, rule) in alerts.rules.iter().enumerate() {
+        let key = |field: &str| format!("alerts.rules[{index}].{field}");
+
+        errors.require_non_empty(&key("name"), &rule.name);
+        errors.require_non_empty(&key("device"), &rule.device);
+
+        if rule.condition != AlertCondition::Unreachable && rule.threshold.is_none() {
+            errors.push(key("threshold"), "is required by this condition");
+        }
+
+        if let Some(first) = names.insert(rule.name.as_str(), index) {
+            errors.push(
+                key("name"),
+                format!("'{}' is already used by alerts.rules[{first}]", rule.name),
+            );
+        }
+    }
+
+    for (index, webhook) in alerts.webhooks.iter().enumerate() {
+        errors.require_url(
+            &format!("alerts.webhooks[{index}].url"),
+            &webhook.url,
+            WEBHOOK_SCHEMES,
+        );
+    }
+}
+
+fn validate_automations(errors: &mut ValidationErrors, automations: &Automations) {
+    let mut names = HashMap::new();
+
+    for (index, automation) in automations.rules.iter().enumerate() {
+        let key = |field: &str| format!("automations.rules[{index}].{field}");
+
+        errors.require_non_empty(&key("name"), &automation.name);
+
+        if let Some(first) = names.insert(automation.name.as_str(), index) {
+            errors.push(
+                key("name"),
+                format!(
+                    "'{}' is already used by automations.rules[{first}]",
+                    automation.name
+                ),
+            );
+        }
+
+        if automation.conditions.is_empty() {
+            errors.push(key("conditions"), "must contain at least one condition");
+        }
+
+        for (condition_index, condition) in automation.conditions.iter().enumerate() {
+            let key = |field: &str| key(&format!("conditions[{condition_index}].{field}"));
+
+            errors.require_non_empty(&key("device"), &condition.device);
+
+            let watches_state = condition.power_above.is_some()
+                || condition.power_below.is_some()
+                || condition.device_on.is_some();
+
+            if !watches_state && condition.event.is_none() {
+                errors.push(
+                    key("device"),
+                    "requires at least one of power_above, power_below, device_on or event",
+                );
+            }
+
+            if condition.event.is_some() {
+                if watches_state {
+                    errors.push(
+                        key("event"),
+                        "can't be combined with power_above, power_below or device_on",
+                    );
+                }
+                if condition.for_s > 0 {
+                    errors.push(key("for_s"), "isn't supported by events");
+                }
+            }
+
+            if condition.sensor.is_some() && condition.event.is_none() {
+                errors.push(key("sensor"), "requires event");
+            }
+        }
+
+        if automation.actions.is_empty() {
+            errors.push(key("actions"), "must contain at least one action");
+        }
+
+        for (action_index, action) in automation.actions.iter().enumerate() {
+            let key = |field: &str| key(&format!("actions[{action_index}].{field}"));
+
+            match action {
+                AutomationAction::Device { name, .. } => {
+                    errors.require_non_empty(&key("device.name"), name);
+                }
+                AutomationAction::Mqtt { topic, .. } => {
+                    errors.require_non_empty(&key("mqtt.topic"), topic);
+                }
+                AutomationAction::Webhook(webhook) => {
+                    errors.require_url(&key("webhook.url"), &webhook.url, WEBHOOK_SCHEMES);
+                }
+            }
+        }
+    }
+}
+
+fn validate_api_auth(errors: &mut ValidationErrors, auth: &ApiAuth) {
+    if auth.tokens.is_empty() && auth.users.is_empty() {
+        errors.push("api.auth", "requires at least one token or user");
+    }
+
+    for (index, token) in auth.tokens.iter().enumerate() {
+        errors.require_non_empty(&format!("api.auth.tokens[{index}].token"), &token.token);
+    }
+
+    let mut usernames = HashMap::new();
+
+    for (index, user) in auth.users.iter().enumerate() {
+        let key = |field: &str| format!("api.auth.users[{index}].{field}");
+
+        errors.require_non_empty(&key("username"), &user.username);
+        errors.require_non_empty(&key("password"), &user.password);
+
+        // basic auth separates the username from the password with the first colon
+        if user.username.contains(':') {
+            errors.push(key("username"), "must not contain ':'");
+        }
+
+        if let Some(first) = usernames.insert(user.username.as_str(), index) {
+            errors.push(
+                key("username"),
+                format!(
+                    "'{}' is already used by api.auth.users[{first}]",
+                    user.username
+                ),
+            );
+        }
+    }
+}
+
+fn validate_scenes(errors: &mut ValidationErrors, scenes: &[Scene], devices: &[Device]) {
+    let mut names = HashMap::new();
+
+    for (index, scene) in scenes.iter().enumerate() {
+        let key = |field: &str| format!("scenes[{index}].{field}");
+
+        errors.require_non_empty(&key("name"), &scene.name);
+
+        if let Some(first) = names.insert(scene.name.as_str(), index) {
+            errors.push(
+                key("name"),
+                format!("'{}' is already used by scenes[{first}]", scene.name),
+            );
+        }
+
+        if scene.devices.is_empty() {
+            errors.push(key("devices"), "must contain at least one device");
+        }
+
+        for (device, state) in &scene.devices {
+            let key = |field: &str| key(&format!("devices.{device}.{field}"));
+
+            if state.has_light_settings() && state.action != DeviceAction::On {
+                errors.push(key("action"), "must be on when brightness or color are set");
+            }
+
+            // devices that aren't configured, e.g. discovered ones, are checked when activated
+            let device_type = devices
+                .iter()
+                .find(|configured| &configured.name == device)
+                .map(|configured| configured.device_type);
+
+            validate_light(errors, key, &state.light(), device_type);
+        }
+    }
+}
+
+fn validate_light(
+    errors: &mut ValidationErrors,
+    key: impl Fn(&str) -> String,
+    light: &LightSettings,
+    device_type: Option<DeviceType>,
+) {
+    if let Some(brightness) = light.brightness
+        && !(1..=100).contains(&brightness)
+    {
+        errors.push(key("brightness"), "must be between 1 and 100");
+    }
+
+    match (light.hue, light.saturation) {
+        (Some(hue), Some(saturation)) => {
+            if hue > 360 {
+                errors.push(key("hue"), "must be between 0 and 360");
+            }
+
+            if !(1..=100).contains(&saturation) {
+                errors.push(key("saturation"), "must be between 1 and 100");
+            }
+        }
+        (Some(_), None) => errors.push(key("saturation"), "is required by hue"),
+        (None, Some(_)) => errors.push(key("hue"), "is required by saturation"),
+        (None, None) => {}
+    }
+
+    if let Some(color_temperature) = light.color_temperature {
+        if !(2500..=6500).contains(&color_temperature) {
+            errors.push(key("color_temperature"), "must be between 2500 and 6500");
+        }
+
+        if light.hue.is_some() || light.saturation.is_some() {
+            errors.push(
+                key("color_temperature"),
+                "can't be combined with hue and saturation",
+            );
+        }
+    }
+
+    if light.lighting_effect.is_some()
+        && (light.brightness.is_some()
+            || light.hue.is_some()
+            || light.saturation.is_some()
+            || light.color_temperature.is_some())
+    {
+        errors.push(
+            key("lighting_effect"),
+            "can't be combined with brightness or color",
+        );
+    }
+
+    let Some(device_type) = device_type else {
+        return;
+    };
+
+    let unsupported = [
+        (
+            "brightness",
+            light.brightness.is_some(),
+            device_type.is_light(),
+        ),
+        ("hue", light.hue.is_some(), device_type.supports_color()),
+        (
+            "saturation",
+            light.saturation.is_some(),
+            device_type.supports_color(),
+        ),
+        (
+            "color_temperature",
+            light.color_temperature.is_some(),
+            device_type.supports_color(),
+        ),
+        (
+            "lighting_effect",
+            light.lighting_effect.is_some(),
+            device_type.supports_lighting_effects(),
+        ),
+    ];
+
+    for (field, set, supported) in unsupported {
+        if set && !supported {
+            errors.push(key(field), format!("isn't supported by a {device_type}"));
+        }
+    }
+}
+
+impl LightSettings {
+    /// Checks the settings applied at runtime, against the type of the device.
+    pub fn validate(&self, device_type: DeviceType) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        if self.is_empty() {
+            errors.push(
+                "light",
+                "requires at least one of brightness, hue, saturation, color_temperature or lighting_effect",
+            );
+        }
+
+        validate_light(
+            &mut errors,
+            |field| field.to_string(),
+            self,
+            Some(device_type),
+        );
+
+        if errors.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Schedule {
+    /// Checks a schedule that's added at runtime, against the scheduler's `location`.
+    pub fn validate(&self, location: Option<Location>) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        validate_schedule(&mut errors, |field| field.to_string(), self, location);
+
+        if errors.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_scheduler(errors: &mut ValidationErrors, scheduler: &Scheduler) {
+    if let Some(location) = scheduler.location {
+        if !(-90.0..=90.0).contains(&location.latitude) {
+            errors.push("scheduler.location.latitude", "must be between -90 and 90");
+        }
+
+        if !(-180.0..=180.0).contains(&location.longitude) {
+            errors.push(
+                "scheduler.location.longitude",
+                "must be between -180 and 180",
+            );
+        }
+    }
+
+    let mut names = HashMap::new();
+
+    for (index, schedule) in scheduler.schedules.iter().enumerate() {
+        let key = |field: &str| format!("scheduler.schedules[{index}].{field}");
+
+        validate_schedule(errors, key, schedule, scheduler.location);
+
+        if let Some(first) = names.insert(schedule.name.as_str(), index) {
+            errors.push(
+                key("name"),
+                format!(
+                    "'{}' is already used by scheduler.schedules[{first}]",
+                    schedule.name
+                ),
+            );
+        }
+    }
+}
+
+fn validate_schedule(
+    errors: &mut ValidationErrors,
+    key: impl Fn(&str) -> String,
+    schedule: &Schedule,
+    location: Option<Location>,
+) {
+    errors.require_non_empty(&key("name"), &schedule.name);
+    errors.require_non_empty(&key("device"), &schedule.device);
+
+    match (&schedule.cron, schedule.sun) {
+        (Some(_), Some(_)) => errors.push(key("sun"), "can't be combined with cron"),
+        (None, None) => errors.push(key("cron"), "either cron or sun is required"),
+        (Some(expression), None) => {
+            if let Err(e) = parse_cron(expression) {
+                errors.push(
+                    key("cron"),
+                    format!("'{expression}' is not a valid cron expression: {e}"),
+                );
+            }
+        }
+        (None, Some(_)) => {
+            if location.is_none() {
+                errors.push(key("sun"), "requires scheduler.location");
+            }
+        }
+    }
+
+    if schedule.cron.is_some() && schedule.offset_min != 0 {
+        errors.push(key("offset_min"), "is only supported by sun schedules");
+    }
+}
+
+fn validate_device(
+    errors: &mut ValidationErrors,
+    settings: &Settings,
+    index: usize,
+    device: &Device,
+) {
+    let key = |field: &str| format!("devices[{index}].{field}");
+
+    errors.require_non_empty(&key("name"), &device.name);
+    errors.require_host(&key("ip_address"), &device.ip_address);
+
+    if device.refresh_rate_s == Some(0) {
+        errors.push(key("refresh_rate_s"), "must be greater than 0");
+    }
+
+    if let Some(profile) = &device.credentials
+        && !settings.tapo.credentials.contains_key(profile)
+    {
+        errors.push(
+            key("credentials"),
+            format!("'{profile}' is not defined under tapo.credentials"),
+        );
+    }
+
+    if let Some(mac) = &device.mac
+        && !is_valid_mac(mac)
+    {
+        errors.push(key("mac"), format!("'{mac}' is not a valid MAC address"));
+    }
+
+    if let Some(device_id) = &device.device_id {
+        errors.require_non_empty(&key("device_id"), device_id);
+    }
+
+    if let Some(cycle) = &device.cycle {
+        if device.device_type != DeviceType::Plug {
+            errors.push(
+                key("cycle"),
+                format!("isn't supported by a {}", device.device_type),
+            );
+        }
+
+        errors.require_non_zero(&key("cycle.start_threshold_w"), cycle.start_threshold_w);
+
+        if cycle.idle_threshold_w >= cycle.start_threshold_w {
+            errors.push(
+                key("cycle.idle_threshold_w"),
+                "must be lower than cycle.start_threshold_w",
+            );
+        }
+    }
+}
+
+fn is_valid_host(value: &str) -> bool {
+    if value.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+
+    // hostnames as per RFC 1123
+    !value.is_empty()
+        && value.len() <= 253
+        && value.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        // a name made of digits and dots only is a malformed IP address, not a hostname
+        && !value.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+fn is_valid_mac(value: &str) -> bool {
+    let mac = normalize_mac(value);
+    let octets: Vec<_> = mac.split(':').collect();
+
+    octets.len() == 6
+        && octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, ResponseActFuture,
+    WrapFuture,
+};
+use chrono::Utc;
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, error, info, instrument, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::settings::{Alerts, Webhook};
+use crate::system::alerts::rules::{Alert, Observation, RuleState};
+use crate::system::coordinator_actor::CoordinatorActor;
+use crate::system::messages::{
+    AlertMessage, DeviceUnreachableMessage, DeviceUsageMessage, ShutdownMessage,
+};
+use crate::telemetry::record_error;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Evaluates the alert rules over every poll of the devices and delivers the alerts.
+#[derive(Debug)]
+pub struct AlertActor {
+    coordinator_actor_addr: Addr<CoordinatorActor>,
+    config: Alerts,
+    /// By rule name.
+    states: HashMap<String, RuleState>,
+    client: reqwest::Client,
+    in_flight: TaskTracker,
+}
+
+impl AlertActor {
+    pub fn new(coordinator_actor_addr: Addr<CoordinatorActor>, config: Alerts) -> Self {
+        Self {
+            coordinator_actor_addr,
+            config,
+            states: HashMap::new(),
+            client: reqwest::Client::new(),
+            in_flight: TaskTracker::new(),
+        }
+    }
+
+    fn evaluate(
+        &mut self,
+        ctx: &mut Context<Self>,
+        span: &tracing::Span,
+        device_name: &str,
+        observation: Observation,
+    ) {
+        let now = Utc::now();
+
+        let alerts: Vec<_> = self
+            .config
+            .rules
+            .iter()
+            .filter(|rule| rule.device == device_name)
+            .filter_map(|rule| {
+                self.states
+                    .entry(rule.name.clone())
+                    .or_default()
+                    .evaluate(rule, observation, now)
+            })
+            .collect();
+
+        for alert in alerts {
+            info!(
+                "Alert '{}' of '{}' is {:?}",
+                alert.rule, alert.device_name, alert.state
+            );
+
+            if self.in_flight.is_closed() {
+                warn!("Dropping the alert, the Alert Actor is shutting down");
+                continue;
+            }
+
+            for webhook in &self.config.webhooks {
+                let fut = Self::deliver(self.client.clone(), webhook.clone(), alert.clone());
+                let fut = self
+                    .in_flight
+                    .track_future(fut)
+                    .instrument(span.clone())
+                    .into_actor(self);
+
+                ctx.spawn(fut);
+            }
+
+            let result = self.coordinator_actor_addr.try_send(AlertMessage {
+                span_context: span.context(),
+                alert,
+            });
+
+            if let Err(e) = result {
+                record_error(span, &e);
+            }
+        }
+    }
+
+    async fn deliver(client: reqwest::Client, webhook: Webhook, alert: Alert) {
+        let span = tracing::Span::current();
+
+        let mut request = client
+            .post(&webhook.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&alert);
+
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+
+        let result = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        if let Err(e) = result {
+            error!(
+                "Failed to deliver the alert '{}' to '{}': {e:?}",
+                alert.rule, webhook.url
+            );
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Actor for AlertActor {
+    type Context = Context<Self>;
+
+    #[instrument(name = "AlertActor::stopped", level = "error", skip_all)]
+    fn stopped(&mut self, _: &mut Self::Context) {}
+}
+
+impl Handler<DeviceUsageMessage> for AlertActor {
+    type Result = ();
+
+    #[instrument(
+        name = "AlertActor::Handler<DeviceUsageMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceUsageMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AlertActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+        )
+    )]
+    fn handle(&mut self, message: DeviceUsageMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let observation = Observation::Usage {
+            current_power: message.current_power,
+            runtime_today: message.device_usage.time_usage.today,
+        };
+
+        self.evaluate(ctx, &span, &message.device.name, observation);
+    }
+}
+
+impl Handler<DeviceUnreachableMessage> for AlertActor {
+    type Result = ();
+
+    #[instrument(
+        name = "AlertActor::Handler<DeviceUnreachableMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceUnreachableMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AlertActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: DeviceUnreachableMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.evaluate(ctx, &span, &message.device.name, Observation::Unreachable);
+    }
+}
+
+impl Handler<ShutdownMessage> for AlertActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "AlertActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AlertActor",
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // let the webhooks that are being delivered finish
+        self.in_flight.close();
+        let in_flight = self.in_flight.clone();
+
+        let fut = async move { in_flight.wait().await }
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
@@ -0,0 +1,2 @@
+pub mod alert_actor;
+pub mod rules;
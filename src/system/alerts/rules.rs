@@ -0,0 +1,139 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{AlertCondition, AlertRule};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule: String,
+    pub device_name: String,
+    pub condition: AlertCondition,
+    pub state: AlertState,
+    /// The value that changed the state, not set for `unreachable`.
+    pub value: Option<u64>,
+    pub threshold: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What a poll of a device found out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Observation {
+    Usage {
+        /// Current power in watts (W)
+        current_power: u64,
+        /// Today's runtime in minutes
+        runtime_today: Option<u64>,
+    },
+    Unreachable,
+}
+
+/// Whether an observation triggers a rule, clears it, or neither, i.e. is within the hysteresis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Triggered,
+    Cleared,
+    Unchanged,
+}
+
+/// The state of a rule between observations.
+#[derive(Debug, Clone, Default)]
+pub struct RuleState {
+    /// When the condition started holding, while waiting for `for_s` to pass.
+    triggered_since: Option<DateTime<Utc>>,
+    firing: bool,
+}
+
+impl RuleState {
+    /// Advances the rule with a new observation of its device.
+    /// Returns the alert to send when the rule starts firing or resolves.
+    pub fn evaluate(
+        &mut self,
+        rule: &AlertRule,
+        observation: Observation,
+        now: DateTime<Utc>,
+    ) -> Option<Alert> {
+        let (value, verdict) = verdict(rule, observation)?;
+
+        let state = match (self.firing, verdict) {
+            (false, Verdict::Triggered) => {
+                let triggered_since = *self.triggered_since.get_or_insert(now);
+                let for_s = i64::try_from(rule.for_s).unwrap_or(i64::MAX);
+
+                if now - triggered_since < TimeDelta::seconds(for_s) {
+                    return None;
+                }
+
+                AlertState::Firing
+            }
+            (false, _) => {
+                self.triggered_since = None;
+                return None;
+            }
+            (true, Verdict::Cleared) => AlertState::Resolved,
+            (true, _) => return None,
+        };
+
+        self.firing = state == AlertState::Firing;
+        self.triggered_since = None;
+
+        Some(Alert {
+            rule: rule.name.clone(),
+            device_name: rule.device.clone(),
+            condition: rule.condition,
+            state,
+            value,
+            threshold: rule.threshold,
+            timestamp: now,
+        })
+    }
+}
+
+/// Returns `None` for observations the rule doesn't look at, e.g. failed polls for power rules.
+fn verdict(rule: &AlertRule, observation: Observation) -> Option<(Option<u64>, Verdict)> {
+    let threshold = rule.threshold.unwrap_or_default();
+
+    let above = |value: u64| {
+        let verdict = if value > threshold {
+            Verdict::Triggered
+        } else if value <= threshold.saturating_sub(rule.hysteresis) {
+            Verdict::Cleared
+        } else {
+            Verdict::Unchanged
+        };
+
+        Some((Some(value), verdict))
+    };
+    let below = |value: u64| {
+        let verdict = if value < threshold {
+            Verdict::Triggered
+        } else if value >= threshold.saturating_add(rule.hysteresis) {
+            Verdict::Cleared
+        } else {
+            Verdict::Unchanged
+        };
+
+        Some((Some(value), verdict))
+    };
+
+    match (rule.condition, observation) {
+        (AlertCondition::PowerAbove, Observation::Usage { current_power, .. }) => {
+            above(current_power)
+        }
+        (AlertCondition::PowerBelow, Observation::Usage { current_power, .. }) => {
+            below(current_power)
+        }
+        (AlertCondition::RuntimeTodayAbove, Observation::Usage { runtime_today, .. }) => {
+            above(runtime_today?)
+        }
+        (AlertCondition::Unreachable, Observation::Unreachable) => Some((None, Verdict::Triggered)),
+        (AlertCondition::Unreachable, Observation::Usage { .. }) => Some((None, Verdict::Cleared)),
+        (_, Observation::Unreachable) => None,
+    }
+}
@@ -1,20 +1,38 @@
-use actix::{Actor, AsyncContext, Context, WrapFuture};
-use tracing::debug;
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, ResponseActFuture,
+    WrapFuture,
+};
+use actix_web::dev::ServerHandle;
+use tracing::{Instrument, debug, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
-use crate::settings::{Api, Tapo};
+use crate::settings::{Api, Tapo, Tariff};
 use crate::system::api::web_server::WebServer;
+use crate::system::coordinator_actor::CoordinatorActor;
+use crate::system::messages::ShutdownMessage;
 
 #[derive(Debug)]
 pub struct ApiActor {
     config_api: Api,
     config_tapo: Tapo,
+    config_tariff: Option<Tariff>,
+    coordinator_actor_addr: Addr<CoordinatorActor>,
+    server_handle: Option<ServerHandle>,
 }
 
 impl ApiActor {
-    pub fn new(config_api: Api, config_tapo: Tapo) -> Self {
+    pub fn new(
+        config_api: Api,
+        config_tapo: Tapo,
+        config_tariff: Option<Tariff>,
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+    ) -> Self {
         Self {
             config_api,
             config_tapo,
+            config_tariff,
+            coordinator_actor_addr,
+            server_handle: None,
         }
     }
 }
@@ -27,20 +45,31 @@ impl Actor for ApiActor {
 
         let host = self.config_api.host.clone();
         let port = self.config_api.port;
+        let auth = self.config_api.auth.clone();
 
         let tapo = self.config_tapo.clone();
+        let tariff = self.config_tariff.clone();
+        let coordinator_actor_addr = self.coordinator_actor_addr.clone();
 
         let fut = async move {
-            let web_server = WebServer::new(&host, port, tapo)
+            WebServer::new(&host, port, auth, tapo, tariff, coordinator_actor_addr)
                 .await
-                .expect("failed to create the API");
-
-            web_server
-                .run_until_stopped()
-                .await
-                .expect("failed to start the API");
+                .expect("failed to create the API")
         }
-        .into_actor(self);
+        .into_actor(self)
+        .map(|web_server, actor, ctx| {
+            actor.server_handle = Some(web_server.handle());
+
+            let fut = async move {
+                web_server
+                    .run_until_stopped()
+                    .await
+                    .expect("failed to start the API");
+            }
+            .into_actor(actor);
+
+            ctx.spawn(fut);
+        });
 
         ctx.wait(fut);
     }
@@ -49,3 +78,37 @@ impl Actor for ApiActor {
         debug!("Api Actor stopped.");
     }
 }
+
+impl Handler<ShutdownMessage> for ApiActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "ApiActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "ApiActor",
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let server_handle = self.server_handle.take();
+
+        // stop accepting connections and let the in-flight requests finish
+        let fut = async move {
+            if let Some(server_handle) = server_handle {
+                server_handle.stop(true).await;
+            }
+        }
+        .instrument(span)
+        .into_actor(self)
+        .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
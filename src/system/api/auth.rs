@@ -0,0 +1,113 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::middleware::Next;
+use actix_web::web;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::settings::{ApiAuth, ApiScope};
+use crate::system::api::errors::ApiError;
+
+/// Routes reachable without credentials, for the probes.
+const PUBLIC_PATHS: &[&str] = &["/health-check"];
+
+/// Routes that also take a token from the `Sec-WebSocket-Protocol` header, as browsers can't set
+/// the `Authorization` header on a WebSocket.
+const WEBSOCKET_PATHS: &[&str] = &["/events/ws"];
+
+/// Subprotocol a WebSocket client offers with the token right after it, i.e.
+/// `Sec-WebSocket-Protocol: bearer, <token>`.
+pub const BEARER_PROTOCOL: &str = "bearer";
+
+/// Rejects the requests without credentials, or whose credentials don't give access to the route.
+/// Does nothing unless `api.auth` is set.
+pub async fn authorize(
+    auth: web::Data<Option<ApiAuth>>,
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if let Some(auth) = auth.as_ref()
+        && !PUBLIC_PATHS.contains(&request.path())
+    {
+        let headers = request.headers();
+        let scope = match headers.get(header::AUTHORIZATION) {
+            Some(value) => value.to_str().ok().and_then(|value| scope_of(auth, value)),
+            None if WEBSOCKET_PATHS.contains(&request.path()) => {
+                protocol_token(headers).and_then(|token| token_scope(auth, token))
+            }
+            None => None,
+        }
+        .ok_or_else(|| ApiError::Unauthorized("missing or invalid credentials".to_string()))?;
+
+        let required = match *request.method() {
+            Method::GET | Method::HEAD => ApiScope::Read,
+            _ => ApiScope::Control,
+        };
+
+        if scope < required {
+            return Err(ApiError::Forbidden("the credentials are read-only".to_string()).into());
+        }
+    }
+
+    next.call(request).await
+}
+
+/// Scope of the credentials in an `Authorization` header, `None` when they're unknown.
+fn scope_of(auth: &ApiAuth, authorization: &str) -> Option<ApiScope> {
+    let (scheme, credentials) = authorization.split_once(' ')?;
+    let credentials = credentials.trim();
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        return token_scope(auth, credentials);
+    }
+
+    if scheme.eq_ignore_ascii_case("basic") {
+        let decoded = String::from_utf8(STANDARD.decode(credentials).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+
+        return auth
+            .users
+            .iter()
+            // both compared every time, so whether the user exists doesn't show either
+            .filter(|user| {
+                secure_eq(&user.username, username) & secure_eq(&user.password, password)
+            })
+            .map(|user| user.scope)
+            .max();
+    }
+
+    None
+}
+
+/// Scope of a bearer token, `None` when it's unknown.
+fn token_scope(auth: &ApiAuth, token: &str) -> Option<ApiScope> {
+    auth.tokens
+        .iter()
+        .filter(|known| secure_eq(&known.token, token))
+        .map(|known| known.scope)
+        .max()
+}
+
+/// Token offered right after the [`BEARER_PROTOCOL`] in the `Sec-WebSocket-Protocol` header.
+pub fn protocol_token(headers: &HeaderMap) -> Option<&str> {
+    let mut protocols = headers
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim);
+
+    protocols.find(|protocol| *protocol == BEARER_PROTOCOL)?;
+    protocols.next()
+}
+
+/// Compares in constant time, so the secrets can't be guessed from how long a rejection takes.
+fn secure_eq(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
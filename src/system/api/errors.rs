@@ -1,4 +1,4 @@
-use actix_web::{HttpResponse, error::ResponseError};
+use actix_web::{HttpResponse, error::ResponseError, http::header};
 use derive_more::Display;
 
 #[derive(Debug, Display)]
@@ -8,6 +8,15 @@ pub enum ApiError {
 
     #[display("BadRequest: {}", _0)]
     BadRequest(String),
+
+    #[display("NotFound: {}", _0)]
+    NotFound(String),
+
+    #[display("Unauthorized: {}", _0)]
+    Unauthorized(String),
+
+    #[display("Forbidden: {}", _0)]
+    Forbidden(String),
 }
 
 impl ResponseError for ApiError {
@@ -17,6 +26,14 @@ impl ResponseError for ApiError {
                 HttpResponse::InternalServerError().json("Internal Server Error")
             }
             ApiError::BadRequest(message) => HttpResponse::BadRequest().json(message),
+            ApiError::NotFound(message) => HttpResponse::NotFound().json(message),
+            ApiError::Unauthorized(message) => HttpResponse::Unauthorized()
+                .insert_header((
+                    header::WWW_AUTHENTICATE,
+                    r#"Bearer realm="home-automation-tapo", Basic realm="home-automation-tapo""#,
+                ))
+                .json(message),
+            ApiError::Forbidden(message) => HttpResponse::Forbidden().json(message),
         }
     }
 }
@@ -1,11 +1,38 @@
+use actix::Addr;
 use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, web};
+use actix_web::http::header::{self, CacheControl, CacheDirective, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_ws::Message;
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use tapo::ApiClient;
-use tracing::instrument;
+use serde_json::json;
+use tapo::{ApiClient, GenericDeviceHandler};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{instrument, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
-use crate::settings::Tapo;
+use crate::settings::{Credentials, DeviceAction, LightSettings, Schedule, Tapo, Tariff};
+use crate::system::api::auth;
 use crate::system::api::errors::ApiError;
+use crate::system::api::metrics;
+use crate::system::coordinator_actor::CoordinatorActor;
+use crate::system::cycles::Cycle;
+use crate::system::device_status::{DeviceInfo, LightError, OutletError};
+use crate::system::energy::{EnergyData, EnergyInterval, EnergyRequest};
+use crate::system::events::{DeviceEvent, EventFilter};
+use crate::system::messages::{
+    ActivateSceneMessage, CancelDeviceTimerMessage, DeleteScheduleMessage, DeviceControlledMessage,
+    GetAutomationsMessage, GetDeviceCyclesMessage, GetDeviceHistoryMessage, GetDeviceStatusMessage,
+    GetDevicesMessage, GetDevicesUsageMessage, GetDiscoveredDevicesMessage, GetEnergyDataMessage,
+    GetSchedulesMessage, GetSensorsMessage, ListDevicesMessage, SetDeviceTimerMessage,
+    SetLightMessage, SetOutletMessage, SetRefreshRateMessage, SetScheduleMessage,
+    SubscribeEventsMessage,
+};
+use crate::system::scheduler::schedule::ScheduleError;
+use crate::system::storage::database::{Resolution, Sample};
+use crate::system::tariff::UsageCost;
+use crate::telemetry::record_error;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ApiStatusResponse {
@@ -26,17 +53,136 @@ impl ApiStatusResponse {
 pub struct SetDevicePayload {
     ip_address: String,
     device_on: bool,
+    /// Name of the credentials profile to use, defaults to the one of the configured device.
+    credentials: Option<String>,
 }
 
 #[derive(Deserialize)]
-pub struct GetDevicePayload {
+pub struct DevicePayload {
     ip_address: String,
+    /// Name of the credentials profile to use, defaults to the one of the configured device.
+    credentials: Option<String>,
 }
 
-#[derive(Serialize)]
-pub struct DeviceResponse {
-    ip_address: String,
-    device_on: Option<bool>,
+#[derive(Deserialize)]
+pub struct SetRefreshRatePayload {
+    refresh_rate_s: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshRateResponse {
+    pub name: String,
+    pub refresh_rate_s: u64,
+}
+
+/// Longest timer accepted, a day.
+const MAX_TIMER_S: u64 = 24 * 60 * 60;
+
+#[derive(Deserialize)]
+pub struct SetTimerPayload {
+    duration_s: u64,
+    action: DeviceAction,
+}
+
+#[derive(Deserialize)]
+pub struct SetOutletPayload {
+    action: DeviceAction,
+}
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    /// Comma separated device names, defaults to every device.
+    device: Option<String>,
+    /// Comma separated event types, defaults to every type.
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// Defaults to 24 hours before `to`.
+    from: Option<DateTime<Utc>>,
+    /// Defaults to now.
+    to: Option<DateTime<Utc>>,
+    resolution: Option<Resolution>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryResponse {
+    pub name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub resolution: Resolution,
+    pub samples: Vec<Sample>,
+}
+
+#[derive(Deserialize)]
+pub struct CyclesQuery {
+    /// Defaults to 7 days before `to`.
+    from: Option<DateTime<Utc>>,
+    /// Defaults to now.
+    to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CyclesResponse {
+    pub name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub cycles: Vec<Cycle>,
+}
+
+#[derive(Deserialize)]
+pub struct EnergyQuery {
+    /// Defaults to hourly.
+    interval: Option<EnergyInterval>,
+    /// Defaults to the current day, quarter or year.
+    start: Option<NaiveDate>,
+    /// Hourly breakdowns only, defaults to `start`.
+    end: Option<NaiveDate>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnergyResponse {
+    pub name: String,
+    #[serde(flatten)]
+    pub energy_data: EnergyData,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CostResponse {
+    pub name: String,
+    #[serde(flatten)]
+    pub cost: UsageCost,
+}
+
+/// Picks the credentials for the device at `ip_address`: the requested profile, then the profile of
+/// the configured device with that IP address, then the default credentials.
+async fn resolve_credentials(
+    config: &Tapo,
+    coordinator_actor_addr: &Addr<CoordinatorActor>,
+    ip_address: &str,
+    profile: Option<&str>,
+) -> Result<Credentials, ApiError> {
+    let profile = match profile {
+        Some(profile) => Some(profile.to_string()),
+        None => coordinator_actor_addr
+            .send(GetDevicesMessage {
+                span_context: tracing::Span::current().context(),
+            })
+            .await
+            .map_err(|_| ApiError::InternalServerError)?
+            .into_iter()
+            .find(|device| device.ip_address == ip_address)
+            .and_then(|device| device.credentials),
+    };
+
+    config.credentials(profile.as_deref()).ok_or_else(|| {
+        ApiError::BadRequest(format!(
+            "unknown credentials profile '{}'",
+            profile.unwrap_or_default()
+        ))
+    })
 }
 
 #[instrument(name = "health_check", skip_all)]
@@ -46,30 +192,44 @@ pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().json(body)
 }
 
+/// Connects to the device at `ip_address`, with the credentials picked by [`resolve_credentials`].
+async fn connect(
+    config: &Tapo,
+    coordinator_actor_addr: &Addr<CoordinatorActor>,
+    ip_address: &str,
+    profile: Option<&str>,
+) -> Result<GenericDeviceHandler, ApiError> {
+    let credentials =
+        resolve_credentials(config, coordinator_actor_addr, ip_address, profile).await?;
+
+    let client = ApiClient::new(credentials.username, credentials.password);
+    client
+        .generic_device(ip_address)
+        .await
+        .map_err(|_| ApiError::BadRequest("failed to connect to the device".to_string()))
+}
+
 #[instrument(name = "get_device", skip_all, fields(
     device.ip_address = %device.ip_address,
 ))]
 pub async fn get_device(
     config: web::Data<Tapo>,
-    device: web::Json<GetDevicePayload>,
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    device: web::Json<DevicePayload>,
 ) -> Result<HttpResponse, ApiError> {
-    let client = ApiClient::new(config.username.clone(), config.password.clone());
-    let handler = client
-        .generic_device(device.ip_address.clone())
-        .await
-        .map_err(|_| ApiError::BadRequest("failed to connect to the device".to_string()))?;
+    let handler = connect(
+        &config,
+        &coordinator_actor_addr,
+        &device.ip_address,
+        device.credentials.as_deref(),
+    )
+    .await?;
 
-    let device_info = handler
-        .get_device_info()
+    let device_info = DeviceInfo::read(&handler)
         .await
         .map_err(|_| ApiError::InternalServerError)?;
 
-    let result = DeviceResponse {
-        ip_address: device.ip_address.clone(),
-        device_on: device_info.device_on,
-    };
-
-    Ok(HttpResponse::Ok().json(result))
+    Ok(HttpResponse::Ok().json(device_info))
 }
 
 #[instrument(name = "set_device", skip_all, fields(
@@ -78,30 +238,811 @@ pub async fn get_device(
 ))]
 pub async fn set_device(
     config: web::Data<Tapo>,
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
     device: web::Json<SetDevicePayload>,
 ) -> Result<HttpResponse, ApiError> {
-    let client = ApiClient::new(config.username.clone(), config.password.clone());
-    let handler = client
-        .generic_device(device.ip_address.clone())
+    let handler = connect(
+        &config,
+        &coordinator_actor_addr,
+        &device.ip_address,
+        device.credentials.as_deref(),
+    )
+    .await?;
+
+    switch(&handler, device.device_on).await?;
+    device_controlled(
+        &coordinator_actor_addr,
+        &device.ip_address,
+        device.device_on,
+    );
+
+    let device_info = DeviceInfo::read(&handler)
         .await
-        .map_err(|_| ApiError::BadRequest("failed to connect to the device".to_string()))?;
+        .map_err(|_| ApiError::InternalServerError)?;
 
-    if device.device_on {
-        handler
-            .on()
-            .await
-            .map_err(|_| ApiError::InternalServerError)?
+    Ok(HttpResponse::Ok().json(device_info))
+}
+
+#[instrument(name = "toggle_device", skip_all, fields(
+    device.ip_address = %device.ip_address,
+))]
+pub async fn toggle_device(
+    config: web::Data<Tapo>,
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    device: web::Json<DevicePayload>,
+) -> Result<HttpResponse, ApiError> {
+    let handler = connect(
+        &config,
+        &coordinator_actor_addr,
+        &device.ip_address,
+        device.credentials.as_deref(),
+    )
+    .await?;
+
+    let device_on = DeviceInfo::read(&handler)
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .device_on
+        .ok_or_else(|| ApiError::BadRequest("the device can't be turned on or off".to_string()))?;
+
+    switch(&handler, !device_on).await?;
+    device_controlled(&coordinator_actor_addr, &device.ip_address, !device_on);
+
+    let device_info = DeviceInfo::read(&handler)
+        .await
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(device_info))
+}
+
+async fn switch(handler: &GenericDeviceHandler, device_on: bool) -> Result<(), ApiError> {
+    let result = if device_on {
+        handler.on().await
     } else {
-        handler
-            .off()
-            .await
-            .map_err(|_| ApiError::InternalServerError)?
+        handler.off().await
+    };
+
+    result.map_err(|_| ApiError::InternalServerError)
+}
+
+/// Lets the schedules leave the device alone for a while.
+fn device_controlled(
+    coordinator_actor_addr: &Addr<CoordinatorActor>,
+    ip_address: &str,
+    device_on: bool,
+) {
+    if let Err(e) = coordinator_actor_addr.try_send(DeviceControlledMessage {
+        span_context: tracing::Span::current().context(),
+        ip_address: ip_address.to_string(),
+        device_on,
+    }) {
+        record_error(&tracing::Span::current(), &e);
+    }
+}
+
+#[instrument(name = "get_devices", skip_all)]
+pub async fn get_devices(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+) -> Result<HttpResponse, ApiError> {
+    let devices = coordinator_actor_addr
+        .send(ListDevicesMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(devices))
+}
+
+#[instrument(name = "get_device_status", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn get_device_status(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let status = coordinator_actor_addr
+        .send(GetDeviceStatusMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?
+        .map_err(|_| ApiError::BadRequest("failed to get the device info".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+#[instrument(name = "set_device_timer", skip_all, fields(
+    device.name = %name,
+    device.timer_s = %payload.duration_s,
+))]
+pub async fn set_device_timer(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+    payload: web::Json<SetTimerPayload>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    if !(1..=MAX_TIMER_S).contains(&payload.duration_s) {
+        return Err(ApiError::BadRequest(format!(
+            "duration_s must be between 1 and {MAX_TIMER_S}"
+        )));
+    }
+
+    let timer = coordinator_actor_addr
+        .send(SetDeviceTimerMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            action: payload.action,
+            duration_s: payload.duration_s,
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?;
+
+    Ok(HttpResponse::Ok().json(timer))
+}
+
+#[instrument(name = "cancel_device_timer", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn cancel_device_timer(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let cancelled = coordinator_actor_addr
+        .send(CancelDeviceTimerMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?;
+
+    if !cancelled {
+        return Err(ApiError::NotFound(format!(
+            "device '{name}' has no timer running"
+        )));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[instrument(name = "set_device_light", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn set_device_light(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+    light: web::Json<LightSettings>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let state = coordinator_actor_addr
+        .send(SetLightMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            light: light.into_inner(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?
+        .map_err(|e| match e {
+            LightError::Invalid(_) => ApiError::BadRequest(e.to_string()),
+            LightError::Failed(_) => ApiError::BadRequest("failed to set the light".to_string()),
+        })?;
+
+    Ok(HttpResponse::Ok().json(state))
+}
+
+#[instrument(name = "set_device_outlet", skip_all, fields(
+    device.name = %path.0,
+    device.outlet = %path.1,
+))]
+pub async fn set_device_outlet(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    path: web::Path<(String, u8)>,
+    payload: web::Json<SetOutletPayload>,
+) -> Result<HttpResponse, ApiError> {
+    let (name, position) = path.into_inner();
+
+    let outlet = coordinator_actor_addr
+        .send(SetOutletMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            position,
+            action: payload.action,
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?
+        .map_err(|e| match e {
+            OutletError::NotFound => {
+                ApiError::NotFound(format!("device '{name}' has no outlet {position}"))
+            }
+            OutletError::Failed(_) => {
+                ApiError::BadRequest("failed to switch the outlet".to_string())
+            }
+        })?;
+
+    Ok(HttpResponse::Ok().json(outlet))
+}
+
+#[instrument(name = "get_device_sensors", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn get_device_sensors(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let sensors = coordinator_actor_addr
+        .send(GetSensorsMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("hub '{name}' not found")))?;
+
+    Ok(HttpResponse::Ok().json(sensors))
+}
+
+#[instrument(name = "get_device_sensor", skip_all, fields(
+    device.name = %path.0,
+    sensor.device_id = %path.1,
+))]
+pub async fn get_device_sensor(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (name, device_id) = path.into_inner();
+
+    let sensor = coordinator_actor_addr
+        .send(GetSensorsMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("hub '{name}' not found")))?
+        .into_iter()
+        .find(|sensor| sensor.device_id == device_id)
+        .ok_or_else(|| ApiError::NotFound(format!("hub '{name}' has no sensor '{device_id}'")))?;
+
+    Ok(HttpResponse::Ok().json(sensor))
+}
+
+#[instrument(name = "get_discovery", skip_all)]
+pub async fn get_discovery(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+) -> Result<HttpResponse, ApiError> {
+    let devices = coordinator_actor_addr
+        .send(GetDiscoveredDevicesMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("discovery is not enabled".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(devices))
+}
+
+#[instrument(name = "set_device_refresh_rate", skip_all, fields(
+    device.name = %name,
+    device.refresh_rate_s = %payload.refresh_rate_s,
+))]
+pub async fn set_device_refresh_rate(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+    payload: web::Json<SetRefreshRatePayload>,
+) -> Result<HttpResponse, ApiError> {
+    if payload.refresh_rate_s == 0 {
+        return Err(ApiError::BadRequest(
+            "refresh_rate_s must be greater than 0".to_string(),
+        ));
+    }
+
+    let name = name.into_inner();
+
+    let found = coordinator_actor_addr
+        .send(SetRefreshRateMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            refresh_rate_s: payload.refresh_rate_s,
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    if !found {
+        return Err(ApiError::NotFound(format!("device '{name}' not found")));
     }
 
-    let result = DeviceResponse {
-        ip_address: device.ip_address.clone(),
-        device_on: Some(device.device_on),
+    let result = RefreshRateResponse {
+        name,
+        refresh_rate_s: payload.refresh_rate_s,
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[instrument(name = "get_device_history", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn get_device_history(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+    let query = query.into_inner();
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - TimeDelta::days(1));
+    let resolution = query.resolution.unwrap_or(Resolution::Raw);
+
+    if from >= to {
+        return Err(ApiError::BadRequest("from must be before to".to_string()));
+    }
+
+    let known = coordinator_actor_addr
+        .send(GetDevicesMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .iter()
+        .any(|device| device.name == name);
+
+    if !known {
+        return Err(ApiError::NotFound(format!("device '{name}' not found")));
+    }
+
+    let samples = coordinator_actor_addr
+        .send(GetDeviceHistoryMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            from,
+            to,
+            resolution,
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("storage is not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    let result = HistoryResponse {
+        name,
+        from,
+        to,
+        resolution,
+        samples,
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[instrument(name = "get_device_cycles", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn get_device_cycles(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+    query: web::Query<CyclesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+    let query = query.into_inner();
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - TimeDelta::days(7));
+
+    if from >= to {
+        return Err(ApiError::BadRequest("from must be before to".to_string()));
+    }
+
+    let known = coordinator_actor_addr
+        .send(GetDevicesMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .iter()
+        .any(|device| device.name == name);
+
+    if !known {
+        return Err(ApiError::NotFound(format!("device '{name}' not found")));
+    }
+
+    let cycles = coordinator_actor_addr
+        .send(GetDeviceCyclesMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            from,
+            to,
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("storage is not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    let result = CyclesResponse {
+        name,
+        from,
+        to,
+        cycles,
     };
 
     Ok(HttpResponse::Ok().json(result))
 }
+
+#[instrument(name = "get_device_energy", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn get_device_energy(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+    query: web::Query<EnergyQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+    let query = query.into_inner();
+
+    let request = EnergyRequest::new(
+        query.interval.unwrap_or(EnergyInterval::Hourly),
+        query.start,
+        query.end,
+        Local::now().date_naive(),
+    )
+    .map_err(ApiError::BadRequest)?;
+
+    let energy_data = coordinator_actor_addr
+        .send(GetEnergyDataMessage {
+            span_context: tracing::Span::current().context(),
+            device_name: name.clone(),
+            request,
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?
+        .map_err(|_| {
+            ApiError::BadRequest("failed to get the energy data from the device".to_string())
+        })?;
+
+    let result = EnergyResponse { name, energy_data };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[instrument(name = "get_device_cost", skip_all, fields(
+    device.name = %name,
+))]
+pub async fn get_device_cost(
+    tariff: web::Data<Option<Tariff>>,
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let (_, device_usage) = coordinator_actor_addr
+        .send(GetDevicesUsageMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .into_iter()
+        .find(|(device, _)| device.name == name)
+        .ok_or_else(|| ApiError::NotFound(format!("device '{name}' not found")))?;
+
+    let tariff = tariff
+        .as_ref()
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("tariff is not configured".to_string()))?;
+
+    let device_usage = device_usage.ok_or_else(|| {
+        ApiError::NotFound(format!("no usage has been read from device '{name}' yet"))
+    })?;
+
+    let result = CostResponse {
+        name,
+        cost: UsageCost::new(tariff, &device_usage, Utc::now()),
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[instrument(name = "get_metrics", skip_all)]
+pub async fn get_metrics(
+    tariff: web::Data<Option<Tariff>>,
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+) -> Result<HttpResponse, ApiError> {
+    let devices_usage = coordinator_actor_addr
+        .send(GetDevicesUsageMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    let body = metrics::render(&devices_usage, tariff.as_ref().as_ref(), Utc::now());
+
+    Ok(HttpResponse::Ok()
+        .content_type(metrics::CONTENT_TYPE)
+        .body(body))
+}
+
+#[instrument(name = "get_schedules", skip_all)]
+pub async fn get_schedules(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+) -> Result<HttpResponse, ApiError> {
+    let schedules = coordinator_actor_addr
+        .send(GetSchedulesMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("scheduler is not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(schedules))
+}
+
+#[instrument(name = "get_schedule", skip_all, fields(
+    schedule.name = %name,
+))]
+pub async fn get_schedule(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let schedule = coordinator_actor_addr
+        .send(GetSchedulesMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("scheduler is not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?
+        .into_iter()
+        .find(|status| status.schedule.name == name)
+        .ok_or_else(|| ApiError::NotFound(format!("schedule '{name}' not found")))?;
+
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+#[instrument(name = "set_schedule", skip_all, fields(
+    schedule.name = %schedule.name,
+))]
+pub async fn set_schedule(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    schedule: web::Json<Schedule>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = schedule.into_inner();
+
+    let replaced = coordinator_actor_addr
+        .send(SetScheduleMessage {
+            span_context: tracing::Span::current().context(),
+            schedule: schedule.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("scheduler is not enabled".to_string()))?
+        .map_err(|e| match e {
+            ScheduleError::Invalid(_) => ApiError::BadRequest(e.to_string()),
+            ScheduleError::Failed(_) => ApiError::InternalServerError,
+        })?;
+
+    if replaced {
+        Ok(HttpResponse::Ok().json(schedule))
+    } else {
+        Ok(HttpResponse::Created().json(schedule))
+    }
+}
+
+#[instrument(name = "delete_schedule", skip_all, fields(
+    schedule.name = %name,
+))]
+pub async fn delete_schedule(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let deleted = coordinator_actor_addr
+        .send(DeleteScheduleMessage {
+            span_context: tracing::Span::current().context(),
+            name: name.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("scheduler is not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    if !deleted {
+        return Err(ApiError::NotFound(format!("schedule '{name}' not found")));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[instrument(name = "get_automations", skip_all)]
+pub async fn get_automations(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+) -> Result<HttpResponse, ApiError> {
+    let automations = coordinator_actor_addr
+        .send(GetAutomationsMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("automations are not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(automations))
+}
+
+#[instrument(name = "get_automation", skip_all, fields(
+    automation.name = %name,
+))]
+pub async fn get_automation(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let automation = coordinator_actor_addr
+        .send(GetAutomationsMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound("automations are not enabled".to_string()))?
+        .map_err(|_| ApiError::InternalServerError)?
+        .into_iter()
+        .find(|status| status.automation.name == name)
+        .ok_or_else(|| ApiError::NotFound(format!("automation '{name}' not found")))?;
+
+    Ok(HttpResponse::Ok().json(automation))
+}
+
+#[instrument(name = "activate_scene", skip_all, fields(
+    scene.name = %name,
+))]
+pub async fn activate_scene(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let name = name.into_inner();
+
+    let activation = coordinator_actor_addr
+        .send(ActivateSceneMessage {
+            span_context: tracing::Span::current().context(),
+            scene_name: name.clone(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .ok_or_else(|| ApiError::NotFound(format!("scene '{name}' not found")))?;
+
+    // the report tells which of the devices failed
+    if activation.succeeded() {
+        Ok(HttpResponse::Ok().json(activation))
+    } else {
+        Ok(HttpResponse::BadGateway().json(activation))
+    }
+}
+
+async fn subscribe_events(
+    coordinator_actor_addr: &Addr<CoordinatorActor>,
+    query: &EventsQuery,
+) -> Result<(EventFilter, broadcast::Receiver<DeviceEvent>), ApiError> {
+    let filter = EventFilter::parse(query.device.as_deref(), query.event_type.as_deref())
+        .map_err(ApiError::BadRequest)?;
+
+    let receiver = coordinator_actor_addr
+        .send(SubscribeEventsMessage {
+            span_context: tracing::Span::current().context(),
+        })
+        .await
+        .map_err(|_| ApiError::InternalServerError)?;
+
+    Ok((filter, receiver))
+}
+
+/// Waits for the next event that passes `filter`, `None` once the events are closed.
+async fn next_event(
+    receiver: &mut broadcast::Receiver<DeviceEvent>,
+    filter: &EventFilter,
+) -> Option<DeviceEvent> {
+    loop {
+        match receiver.recv().await {
+            Ok(event) if filter.matches(&event) => return Some(event),
+            Ok(_) => {}
+            // a slow client misses the oldest events rather than holding up the others
+            Err(RecvError::Lagged(missed)) => {
+                warn!("Event stream fell behind, {missed} event(s) missed");
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[instrument(name = "get_events", skip_all)]
+pub async fn get_events(
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    query: web::Query<EventsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let (filter, receiver) = subscribe_events(&coordinator_actor_addr, &query).await?;
+
+    let stream = futures::stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        let event = next_event(&mut receiver, &filter).await?;
+        let frame = format!(
+            "event: {}\ndata: {}\n\n",
+            event.event_type().as_str(),
+            json!(event)
+        );
+
+        Some((
+            Ok::<_, actix_web::Error>(web::Bytes::from(frame)),
+            (receiver, filter),
+        ))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(stream))
+}
+
+#[instrument(name = "get_events_ws", skip_all)]
+pub async fn get_events_ws(
+    request: HttpRequest,
+    body: web::Payload,
+    coordinator_actor_addr: web::Data<Addr<CoordinatorActor>>,
+    query: web::Query<EventsQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (filter, mut receiver) = subscribe_events(&coordinator_actor_addr, &query).await?;
+    let (mut response, mut session, mut messages) = actix_ws::handle(&request, body)?;
+
+    if auth::protocol_token(request.headers()).is_some() {
+        // browsers drop the connection unless one of the offered subprotocols is picked
+        response.headers_mut().insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(auth::BEARER_PROTOCOL),
+        );
+    }
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                event = next_event(&mut receiver, &filter) => {
+                    let Some(event) = event else {
+                        break;
+                    };
+
+                    if session.text(json!(event).to_string()).await.is_err() {
+                        // the client is gone
+                        return;
+                    }
+                }
+                message = messages.next() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
@@ -0,0 +1,152 @@
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Utc};
+use tapo::responses::DeviceUsageEnergyMonitoringResult;
+
+use crate::settings::{Device, Tariff};
+use crate::system::tariff::{PeriodCost, UsageCost};
+
+/// Version 0.0.4 of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+const PERIODS: [&str; 3] = ["today", "past7", "past30"];
+
+/// A metric and its samples, rendered together so every metric is described exactly once.
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    samples: Vec<(Vec<(&'static str, String)>, String)>,
+}
+
+impl Family {
+    fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            samples: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, labels: Vec<(&'static str, String)>, value: impl ToString) {
+        self.samples.push((labels, value.to_string()));
+    }
+
+    fn render(&self, output: &mut String) {
+        if self.samples.is_empty() {
+            return;
+        }
+
+        let _ = writeln!(output, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(output, "# TYPE {} gauge", self.name);
+
+        for (labels, value) in &self.samples {
+            let labels = labels
+                .iter()
+                .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let _ = writeln!(output, "{}{{{labels}}} {value}", self.name);
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Renders the last usage read from every device, and its cost when a tariff is configured.
+pub fn render(
+    devices_usage: &[(Device, Option<DeviceUsageEnergyMonitoringResult>)],
+    tariff: Option<&Tariff>,
+    now: DateTime<Utc>,
+) -> String {
+    let mut power_usage = Family::new(
+        "tapo_power_usage_wh",
+        "Power usage reported by the device, in watt-hour.",
+    );
+    let mut time_usage = Family::new(
+        "tapo_time_usage_minutes",
+        "Time usage reported by the device, in minutes.",
+    );
+    let mut energy_cost = Family::new(
+        "tapo_energy_cost",
+        "Cost of the power usage, assuming it was spread evenly over the period.",
+    );
+    let mut standing_charge = Family::new(
+        "tapo_standing_charge",
+        "Standing charge of the tariff over the period.",
+    );
+
+    for (device, device_usage) in devices_usage {
+        let Some(device_usage) = device_usage else {
+            continue;
+        };
+
+        let labels = |period: &str| {
+            vec![
+                ("device", device.name.clone()),
+                ("period", period.to_string()),
+            ]
+        };
+
+        let power_usage_values = [
+            device_usage.power_usage.today,
+            device_usage.power_usage.past7,
+            device_usage.power_usage.past30,
+        ];
+        for (period, value) in PERIODS.iter().zip(power_usage_values) {
+            if let Some(value) = value {
+                power_usage.push(labels(period), value);
+            }
+        }
+
+        if device.record_time_usage {
+            let time_usage_values = [
+                device_usage.time_usage.today,
+                device_usage.time_usage.past7,
+                device_usage.time_usage.past30,
+            ];
+            for (period, value) in PERIODS.iter().zip(time_usage_values) {
+                if let Some(value) = value {
+                    time_usage.push(labels(period), value);
+                }
+            }
+        }
+
+        if let Some(tariff) = tariff {
+            let cost = UsageCost::new(tariff, device_usage, now);
+            let costs: [&Option<PeriodCost>; 3] = [&cost.today, &cost.past7, &cost.past30];
+
+            for (period, period_cost) in PERIODS.iter().zip(costs) {
+                if let Some(period_cost) = period_cost {
+                    let mut labels = labels(period);
+                    labels.push(("currency", cost.currency.clone()));
+                    energy_cost.push(labels, period_cost.cost);
+                }
+            }
+        }
+    }
+
+    if let Some(tariff) = tariff {
+        for (period, days) in PERIODS.iter().zip([1, 7, 30]) {
+            standing_charge.push(
+                vec![
+                    ("period", period.to_string()),
+                    ("currency", tariff.currency.clone()),
+                ],
+                tariff.standing_charge(days),
+            );
+        }
+    }
+
+    let mut output = String::new();
+    for family in [power_usage, time_usage, energy_cost, standing_charge] {
+        family.render(&mut output);
+    }
+
+    output
+}
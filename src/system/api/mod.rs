@@ -1,4 +1,6 @@
 pub mod api_actor;
+mod auth;
 mod errors;
 pub mod handlers;
+mod metrics;
 pub mod web_server;
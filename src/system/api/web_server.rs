@@ -1,10 +1,22 @@
 use std::net::TcpListener;
 
-use actix_web::{App, HttpServer, dev::Server, web};
+use actix::Addr;
+use actix_web::{
+    App, HttpServer,
+    dev::{Server, ServerHandle},
+    middleware::from_fn,
+    web,
+};
 use anyhow::Context;
 use tracing_actix_web::TracingLogger;
 
-use crate::{settings::Tapo, system::api::handlers};
+use crate::{
+    settings::{ApiAuth, Tapo, Tariff},
+    system::{
+        api::{auth, handlers},
+        coordinator_actor::CoordinatorActor,
+    },
+};
 
 pub struct WebServer {
     port: u16,
@@ -12,22 +24,108 @@ pub struct WebServer {
 }
 
 impl WebServer {
-    pub async fn new(host: &str, port: u16, tapo: Tapo) -> Result<Self, anyhow::Error> {
+    pub async fn new(
+        host: &str,
+        port: u16,
+        auth: Option<ApiAuth>,
+        tapo: Tapo,
+        tariff: Option<Tariff>,
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+    ) -> Result<Self, anyhow::Error> {
         let address = format!("{host}:{port}",);
 
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr()?.port();
 
+        let auth_data = web::Data::new(auth);
         let data = web::Data::new(tapo);
+        let tariff_data = web::Data::new(tariff);
+        let coordinator_data = web::Data::new(coordinator_actor_addr);
 
         let server = HttpServer::new(move || {
             App::new()
+                .wrap(from_fn(auth::authorize))
                 .wrap(TracingLogger::default())
+                .app_data(auth_data.clone())
                 .app_data(data.clone())
+                .app_data(tariff_data.clone())
+                .app_data(coordinator_data.clone())
                 .route("/health-check", web::get().to(handlers::health_check))
+                .route("/metrics", web::get().to(handlers::get_metrics))
                 .route("/device", web::get().to(handlers::get_device))
                 .route("/device", web::post().to(handlers::set_device))
+                .route("/device/toggle", web::post().to(handlers::toggle_device))
+                .route("/devices", web::get().to(handlers::get_devices))
+                .route(
+                    "/devices/{name}",
+                    web::get().to(handlers::get_device_status),
+                )
+                .route(
+                    "/devices/{name}/timer",
+                    web::post().to(handlers::set_device_timer),
+                )
+                .route(
+                    "/devices/{name}/timer",
+                    web::delete().to(handlers::cancel_device_timer),
+                )
+                .route(
+                    "/devices/{name}/light",
+                    web::put().to(handlers::set_device_light),
+                )
+                .route(
+                    "/devices/{name}/outlets/{position}",
+                    web::put().to(handlers::set_device_outlet),
+                )
+                .route(
+                    "/devices/{name}/sensors",
+                    web::get().to(handlers::get_device_sensors),
+                )
+                .route(
+                    "/devices/{name}/sensors/{device_id}",
+                    web::get().to(handlers::get_device_sensor),
+                )
+                .route(
+                    "/devices/{name}/refresh-rate",
+                    web::put().to(handlers::set_device_refresh_rate),
+                )
+                .route(
+                    "/devices/{name}/energy",
+                    web::get().to(handlers::get_device_energy),
+                )
+                .route(
+                    "/devices/{name}/cost",
+                    web::get().to(handlers::get_device_cost),
+                )
+                .route(
+                    "/devices/{name}/history",
+                    web::get().to(handlers::get_device_history),
+                )
+                .route(
+                    "/devices/{name}/cycles",
+                    web::get().to(handlers::get_device_cycles),
+                )
+                .route("/events", web::get().to(handlers::get_events))
+                .route("/events/ws", web::get().to(handlers::get_events_ws))
+                .route("/discovery", web::get().to(handlers::get_discovery))
+                .route("/automations", web::get().to(handlers::get_automations))
+                .route(
+                    "/automations/{name}",
+                    web::get().to(handlers::get_automation),
+                )
+                .route(
+                    "/scenes/{name}/activate",
+                    web::post().to(handlers::activate_scene),
+                )
+                .route("/schedules", web::get().to(handlers::get_schedules))
+                .route("/schedules", web::post().to(handlers::set_schedule))
+                .route("/schedules/{name}", web::get().to(handlers::get_schedule))
+                .route(
+                    "/schedules/{name}",
+                    web::delete().to(handlers::delete_schedule),
+                )
         })
+        // shutdown signals are handled by the coordinator, which stops the server gracefully
+        .disable_signals()
         .listen(listener)
         .context("failed to listen to the API socket")?
         .run();
@@ -39,6 +137,10 @@ impl WebServer {
         Ok(self.server.await?)
     }
 
+    pub fn handle(&self) -> ServerHandle {
+        self.server.handle()
+    }
+
     pub fn port(&self) -> u16 {
         self.port
     }
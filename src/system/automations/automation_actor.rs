@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, MessageResult,
+    ResponseActFuture, WrapFuture,
+};
+use chrono::{Local, Utc};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, error, info, instrument, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::settings::{AutomationAction, Automations, DeviceAction, Webhook};
+use crate::system::automations::rules::{AutomationState, AutomationTriggered, DeviceState};
+use crate::system::coordinator_actor::CoordinatorActor;
+use crate::system::messages::{
+    DeviceUnreachableMessage, DeviceUsageMessage, GetAutomationsMessage, MqttPublishMessage,
+    SensorEventsMessage, SetDevicePowerMessage, ShutdownMessage,
+};
+use crate::system::sensors::SensorEvent;
+use crate::telemetry::record_error;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Evaluates the automations over every poll of the devices and carries out their actions.
+#[derive(Debug)]
+pub struct AutomationActor {
+    coordinator_actor_addr: Addr<CoordinatorActor>,
+    config: Automations,
+    /// By device name, devices that stopped answering are left out.
+    devices: HashMap<String, DeviceState>,
+    /// Sensor events being evaluated, by hub name. Only kept for a single evaluation.
+    events: HashMap<String, Vec<SensorEvent>>,
+    /// By automation name.
+    states: HashMap<String, AutomationState>,
+    client: reqwest::Client,
+    in_flight: TaskTracker,
+}
+
+impl AutomationActor {
+    pub fn new(coordinator_actor_addr: Addr<CoordinatorActor>, config: Automations) -> Self {
+        Self {
+            coordinator_actor_addr,
+            config,
+            devices: HashMap::new(),
+            events: HashMap::new(),
+            states: HashMap::new(),
+            client: reqwest::Client::new(),
+            in_flight: TaskTracker::new(),
+        }
+    }
+
+    /// Evaluates the automations that look at `device_name`.
+    fn evaluate(&mut self, ctx: &mut Context<Self>, span: &tracing::Span, device_name: &str) {
+        let now = Local::now();
+
+        let triggered: Vec<_> = self
+            .config
+            .rules
+            .iter()
+            .filter(|automation| {
+                automation
+                    .conditions
+                    .iter()
+                    .any(|condition| condition.device == device_name)
+            })
+            .filter(|automation| {
+                self.states
+                    .entry(automation.name.clone())
+                    .or_default()
+                    .evaluate(automation, &self.devices, &self.events, &now)
+            })
+            .cloned()
+            .collect();
+
+        for automation in triggered {
+            info!("Automation '{}' triggered", automation.name);
+
+            if self.in_flight.is_closed() {
+                warn!("Skipping the actions, the Automation Actor is shutting down");
+                continue;
+            }
+
+            let event = AutomationTriggered {
+                automation: automation.name.clone(),
+                timestamp: now.with_timezone(&Utc),
+            };
+
+            for action in automation.actions {
+                match action {
+                    AutomationAction::Device { name, action } => {
+                        let fut = Self::switch(
+                            self.coordinator_actor_addr.clone(),
+                            event.clone(),
+                            name,
+                            action,
+                        );
+                        let fut = self
+                            .in_flight
+                            .track_future(fut)
+                            .instrument(span.clone())
+                            .into_actor(self);
+
+                        ctx.spawn(fut);
+                    }
+                    AutomationAction::Mqtt { topic, payload } => {
+                        let result = self.coordinator_actor_addr.try_send(MqttPublishMessage {
+                            span_context: span.context(),
+                            topic,
+                            payload,
+                        });
+
+                        if let Err(e) = result {
+                            record_error(span, &e);
+                        }
+                    }
+                    AutomationAction::Webhook(webhook) => {
+                        let fut = Self::deliver(self.client.clone(), webhook, event.clone());
+                        let fut = self
+                            .in_flight
+                            .track_future(fut)
+                            .instrument(span.clone())
+                            .into_actor(self);
+
+                        ctx.spawn(fut);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn switch(
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        event: AutomationTriggered,
+        device_name: String,
+        action: DeviceAction,
+    ) {
+        let span = tracing::Span::current();
+
+        let result = coordinator_actor_addr
+            .send(SetDevicePowerMessage {
+                span_context: span.context(),
+                device_name: device_name.clone(),
+                action,
+            })
+            .await;
+
+        match result {
+            Ok(Some(Ok(_))) => {}
+            Ok(Some(Err(e))) => {
+                error!(
+                    "Automation '{}' failed to switch '{device_name}': {e:?}",
+                    event.automation
+                );
+                record_error(&span, &*e);
+            }
+            Ok(None) => warn!(
+                "Automation '{}' refers to the unknown device '{device_name}'",
+                event.automation
+            ),
+            Err(e) => record_error(&span, &e),
+        }
+    }
+
+    async fn deliver(client: reqwest::Client, webhook: Webhook, event: AutomationTriggered) {
+        let span = tracing::Span::current();
+
+        let mut request = client
+            .post(&webhook.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&event);
+
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+
+        let result = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        if let Err(e) = result {
+            error!(
+                "Failed to deliver the automation '{}' to '{}': {e:?}",
+                event.automation, webhook.url
+            );
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Actor for AutomationActor {
+    type Context = Context<Self>;
+
+    #[instrument(name = "AutomationActor::stopped", level = "error", skip_all)]
+    fn stopped(&mut self, _: &mut Self::Context) {}
+}
+
+impl Handler<DeviceUsageMessage> for AutomationActor {
+    type Result = ();
+
+    #[instrument(
+        name = "AutomationActor::Handler<DeviceUsageMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceUsageMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AutomationActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: DeviceUsageMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.devices.insert(
+            message.device.name.clone(),
+            DeviceState {
+                current_power: message.current_power,
+                device_on: message.device_on,
+            },
+        );
+
+        self.evaluate(ctx, &span, &message.device.name);
+    }
+}
+
+impl Handler<DeviceUnreachableMessage> for AutomationActor {
+    type Result = ();
+
+    #[instrument(
+        name = "AutomationActor::Handler<DeviceUnreachableMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceUnreachableMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AutomationActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: DeviceUnreachableMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.devices.remove(&message.device.name);
+
+        self.evaluate(ctx, &span, &message.device.name);
+    }
+}
+
+impl Handler<SensorEventsMessage> for AutomationActor {
+    type Result = ();
+
+    #[instrument(
+        name = "AutomationActor::Handler<SensorEventsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SensorEventsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AutomationActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SensorEventsMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.events
+            .insert(message.device.name.clone(), message.events);
+        self.evaluate(ctx, &span, &message.device.name);
+
+        // events are instantaneous, evaluating again once they're gone resets the automations they
+        // triggered so the next events trigger them again
+        self.events.remove(&message.device.name);
+        self.evaluate(ctx, &span, &message.device.name);
+    }
+}
+
+impl Handler<GetAutomationsMessage> for AutomationActor {
+    type Result = MessageResult<GetAutomationsMessage>;
+
+    #[instrument(
+        name = "AutomationActor::Handler<GetAutomationsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetAutomationsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AutomationActor",
+        )
+    )]
+    fn handle(&mut self, message: GetAutomationsMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let automations = self
+            .config
+            .rules
+            .iter()
+            .map(|automation| {
+                self.states
+                    .get(&automation.name)
+                    .cloned()
+                    .unwrap_or_default()
+                    .status(automation)
+            })
+            .collect();
+
+        MessageResult(Some(Ok(automations)))
+    }
+}
+
+impl Handler<ShutdownMessage> for AutomationActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "AutomationActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "AutomationActor",
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // let the actions that are being carried out finish
+        self.in_flight.close();
+        let in_flight = self.in_flight.clone();
+
+        let fut = async move { in_flight.wait().await }
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
@@ -0,0 +1,2 @@
+pub mod automation_actor;
+pub mod rules;
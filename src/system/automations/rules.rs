@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{Automation, AutomationCondition};
+use crate::system::sensors::SensorEvent;
+
+/// The latest state read from a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceState {
+    /// Current power in watts (W)
+    pub current_power: u64,
+    pub device_on: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationStatus {
+    pub automation: Automation,
+    /// Whether the automation has triggered and its conditions still hold.
+    pub active: bool,
+    pub last_triggered: Option<DateTime<Utc>>,
+}
+
+/// Posted to the webhooks of an automation when it triggers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutomationTriggered {
+    pub automation: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl AutomationCondition {
+    /// Conditions never hold for devices that haven't been read or stopped answering.
+    /// Event conditions hold while one of the `events` of the hub matches.
+    fn holds(&self, state: Option<&DeviceState>, events: Option<&Vec<SensorEvent>>) -> bool {
+        if let Some(event) = self.event {
+            return events.is_some_and(|events| {
+                events.iter().any(|sensor_event| {
+                    sensor_event.event == event
+                        && self
+                            .sensor
+                            .as_ref()
+                            .is_none_or(|sensor| sensor_event.is_from(sensor))
+                })
+            });
+        }
+
+        let Some(state) = state else {
+            return false;
+        };
+
+        self.power_above
+            .is_none_or(|power_above| state.current_power > power_above)
+            && self
+                .power_below
+                .is_none_or(|power_below| state.current_power < power_below)
+            && self
+                .device_on
+                .is_none_or(|device_on| state.device_on == device_on)
+    }
+}
+
+/// The state of an automation between evaluations.
+#[derive(Debug, Clone, Default)]
+pub struct AutomationState {
+    /// When every condition started holding, by condition index.
+    held_since: Vec<Option<DateTime<Utc>>>,
+    /// Set once triggered, until one of the conditions stops holding.
+    active: bool,
+    last_triggered: Option<DateTime<Utc>>,
+}
+
+impl AutomationState {
+    /// Advances the automation with the latest state of the devices and the sensor events that
+    /// were just read, both by device name.
+    /// Returns whether it triggers, which it does once every time its conditions start holding.
+    ///
+    /// The window of the automation follows the time zone of `now`.
+    pub fn evaluate<Tz: TimeZone>(
+        &mut self,
+        automation: &Automation,
+        devices: &HashMap<String, DeviceState>,
+        events: &HashMap<String, Vec<SensorEvent>>,
+        now: &DateTime<Tz>,
+    ) -> bool {
+        let utc_now = now.with_timezone(&Utc);
+
+        self.held_since.resize(automation.conditions.len(), None);
+
+        for (condition, held_since) in automation.conditions.iter().zip(&mut self.held_since) {
+            if condition.holds(
+                devices.get(&condition.device),
+                events.get(&condition.device),
+            ) {
+                held_since.get_or_insert(utc_now);
+            } else {
+                *held_since = None;
+            }
+        }
+
+        if self.active {
+            self.active = self.held_since.iter().all(Option::is_some);
+            return false;
+        }
+
+        let ready =
+            automation
+                .conditions
+                .iter()
+                .zip(&self.held_since)
+                .all(|(condition, held_since)| {
+                    let for_s = i64::try_from(condition.for_s).unwrap_or(i64::MAX);
+
+                    held_since
+                        .is_some_and(|held_since| utc_now - held_since >= TimeDelta::seconds(for_s))
+                });
+
+        let in_window = automation
+            .window
+            .is_none_or(|window| window.contains(now.naive_local()));
+
+        if !ready || !in_window {
+            return false;
+        }
+
+        self.active = true;
+        self.last_triggered = Some(utc_now);
+
+        true
+    }
+
+    pub fn status(&self, automation: &Automation) -> AutomationStatus {
+        AutomationStatus {
+            automation: automation.clone(),
+            active: self.active,
+            last_triggered: self.last_triggered,
+        }
+    }
+}
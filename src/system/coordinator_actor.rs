@@ -1,25 +1,86 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use actix::clock::interval;
-use actix::{Actor, Addr, AsyncContext, Context, Handler, WrapFuture};
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, Message,
+    MessageResult, ResponseActFuture, ResponseFuture, SpawnHandle, WrapFuture,
+};
 use anyhow::Context as _;
-use tracing::{info, instrument, warn};
+use chrono::{DateTime, Utc};
+use tapo::responses::DeviceUsageEnergyMonitoringResult;
+use tokio::sync::broadcast;
+use tracing::{Instrument, debug, error, info, instrument, warn};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::settings::Settings;
+use crate::settings::{Device, DeviceAction, DeviceType, Settings, ValidationError};
+use crate::system::alerts::alert_actor::AlertActor;
 use crate::system::api::api_actor::ApiActor;
+use crate::system::automations::automation_actor::AutomationActor;
+use crate::system::automations::rules::AutomationStatus;
+use crate::system::cycles::{Cycle, CycleEvent};
 use crate::system::device_actor::DeviceActor;
-use crate::system::messages::{DeviceUsageMessage, HealthCheckMessage};
+use crate::system::device_status::{
+    DeviceStatus, DeviceSummary, LightError, LightState, OutletError, OutletState, TimerStatus,
+};
+use crate::system::discovery::arp;
+use crate::system::discovery::discovery_actor::DiscoveryActor;
+use crate::system::discovery::protocol::DiscoveredDevice;
+use crate::system::energy::{EnergyData, EnergyInterval, EnergyWatermark};
+use crate::system::events::{DeviceCommand, DeviceEvent, DeviceEventKind, DeviceSample};
+use crate::system::messages::{
+    ActivateSceneMessage, AlertMessage, BackfillEnergyDataMessage, CancelDeviceTimerMessage,
+    CycleEventMessage, DeleteScheduleMessage, DeviceAddressChangedMessage, DeviceControlledMessage,
+    DeviceDiscoveredMessage, DeviceEventMessage, DeviceUnreachableMessage, DeviceUsageMessage,
+    EnergyDataMessage, EnergyPublishedMessage, FindDeviceMessage, GetAutomationsMessage,
+    GetDeviceCyclesMessage, GetDeviceHistoryMessage, GetDeviceStatusMessage, GetDevicesMessage,
+    GetDevicesUsageMessage, GetDiscoveredDevicesMessage, GetEnergyDataMessage,
+    GetEnergyWatermarksMessage, GetSchedulesMessage, GetSensorsMessage, HealthCheckMessage,
+    LightStateMessage, ListDevicesMessage, ManualOverrideMessage, MqttPublishMessage,
+    OutletsStateMessage, PublishOutcome, ResolveDeviceAddressMessage, SensorEventsMessage,
+    SensorsStateMessage, SetDevicePowerMessage, SetDeviceStateMessage, SetDeviceTimerMessage,
+    SetLightMessage, SetOutletMessage, SetRefreshRateMessage, SetScheduleMessage, ShutdownMessage,
+    SubscribeEventsMessage, SubscribeScenesMessage,
+};
 use crate::system::mqtt_actor::MqttActor;
+use crate::system::scenes::{SceneActivation, SceneDeviceResult};
+use crate::system::scheduler::schedule::{ScheduleError, ScheduleStatus};
+use crate::system::scheduler::scheduler_actor::SchedulerActor;
+use crate::system::sensors::Sensor;
+use crate::system::storage::database::Sample;
+use crate::system::storage::storage_actor::StorageActor;
 use crate::telemetry::record_error;
 
+/// Events kept for the subscribers that fall behind, before they start missing some.
+const EVENTS_CAPACITY: usize = 256;
+
 #[derive(Debug)]
 pub struct CoordinatorActor {
     settings: Settings,
-    api_actor_addr: Addr<ApiActor>,
+    api_actor_addr: Option<Addr<ApiActor>>,
     mqtt_actor_addr: Addr<MqttActor>,
+    discovery_actor_addr: Option<Addr<DiscoveryActor>>,
+    storage_actor_addr: Option<Addr<StorageActor>>,
+    alert_actor_addr: Option<Addr<AlertActor>>,
+    scheduler_actor_addr: Option<Addr<SchedulerActor>>,
+    automation_actor_addr: Option<Addr<AutomationActor>>,
     device_actors: HashMap<String, Addr<DeviceActor>>,
+    /// Last usage read from every device, by device name.
+    devices_usage: HashMap<String, DeviceUsageEnergyMonitoringResult>,
+    /// Start of the last published energy interval, by device name.
+    energy_watermarks: HashMap<String, HashMap<EnergyInterval, DateTime<Utc>>>,
+    /// Last outlets reported by every power strip, by device name.
+    outlets: HashMap<String, Vec<OutletState>>,
+    /// Last sensors reported by every hub, by device name.
+    sensors: HashMap<String, Vec<Sensor>>,
+    /// Streams the events of every device to the API.
+    events: broadcast::Sender<DeviceEvent>,
+    /// Whether every device was last seen on, by device name.
+    devices_on: HashMap<String, bool>,
+    /// Devices whose last poll failed.
+    unavailable_devices: HashSet<String>,
+    health_check_handle: Option<SpawnHandle>,
+    shutting_down: bool,
 }
 
 impl CoordinatorActor {
@@ -32,23 +93,161 @@ impl CoordinatorActor {
     pub fn new(settings: Settings) -> anyhow::Result<Self> {
         let span = tracing::Span::current();
 
-        let mqtt_actor = MqttActor::new(settings.mqtt.clone())
+        let mqtt_actor = MqttActor::new(settings.mqtt.clone(), settings.tariff.clone())
             .inspect_err(|e| {
                 record_error(&span, &e);
             })
             .context("Failed to create the MQTT actor")?;
         let mqtt_actor_addr = mqtt_actor.start();
 
-        let api_actor = ApiActor::new(settings.api.clone(), settings.tapo.clone());
-        let api_actor_addr = api_actor.start();
-
         Ok(Self {
             settings,
-            api_actor_addr,
+            api_actor_addr: None,
             mqtt_actor_addr,
+            discovery_actor_addr: None,
+            storage_actor_addr: None,
+            alert_actor_addr: None,
+            scheduler_actor_addr: None,
+            automation_actor_addr: None,
             device_actors: HashMap::new(),
+            devices_usage: HashMap::new(),
+            energy_watermarks: HashMap::new(),
+            outlets: HashMap::new(),
+            sensors: HashMap::new(),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+            devices_on: HashMap::new(),
+            unavailable_devices: HashSet::new(),
+            health_check_handle: None,
+            shutting_down: false,
         })
     }
+
+    /// Has the MQTT actor trigger the scenes published to the scene topic.
+    fn subscribe_scenes(&self, addr: Addr<Self>) {
+        if self.settings.scenes.is_empty() {
+            return;
+        }
+
+        let span = tracing::Span::current();
+
+        if let Err(e) = self.mqtt_actor_addr.try_send(SubscribeScenesMessage {
+            span_context: span.context(),
+            recipient: addr.recipient(),
+        }) {
+            record_error(&span, &e);
+        }
+    }
+
+    /// Keeps the latest of the known and the given watermark.
+    /// Returns whether the given watermark is the latest.
+    fn update_energy_watermark(&mut self, watermark: &EnergyWatermark) -> bool {
+        let watermarks = self
+            .energy_watermarks
+            .entry(watermark.device_name.clone())
+            .or_default();
+
+        match watermarks.get(&watermark.interval) {
+            Some(known) if *known >= watermark.start_date_time => false,
+            _ => {
+                watermarks.insert(watermark.interval, watermark.start_date_time);
+                true
+            }
+        }
+    }
+
+    /// Asks every device with a known watermark to publish what it has missed since.
+    fn request_energy_backfill(&self, span: &tracing::Span) {
+        if self.settings.energy.is_none() {
+            return;
+        }
+
+        for (device_name, device_actor_addr) in &self.device_actors {
+            let Some(since) = self.energy_watermarks.get(device_name) else {
+                continue;
+            };
+
+            let result = device_actor_addr.try_send(BackfillEnergyDataMessage {
+                span_context: span.context(),
+                since: since.clone(),
+            });
+
+            if let Err(e) = result {
+                record_error(span, &e);
+            }
+        }
+    }
+
+    /// Streams `event`, along with the availability and state changes it reveals.
+    fn publish_event(&mut self, event: DeviceEvent) {
+        let device_on = match &event.kind {
+            DeviceEventKind::Sample(DeviceSample::Usage { device_on, .. }) => Some(*device_on),
+            DeviceEventKind::Sample(DeviceSample::Light(state)) => Some(state.device_on),
+            // the outlets are switched, not the power strip
+            DeviceEventKind::Command {
+                command: DeviceCommand::Outlet { .. },
+                ..
+            } => None,
+            DeviceEventKind::Command { device_on, .. } => *device_on,
+            _ => None,
+        };
+
+        if matches!(event.kind, DeviceEventKind::Sample(_))
+            && self.unavailable_devices.remove(&event.device)
+        {
+            self.send_event(DeviceEvent::new(
+                &event.device,
+                DeviceEventKind::Availability { available: true },
+            ));
+        }
+
+        let device_name = event.device.clone();
+        self.send_event(event);
+
+        if let Some(device_on) = device_on
+            && self
+                .devices_on
+                .insert(device_name.clone(), device_on)
+                .is_some_and(|was_on| was_on != device_on)
+        {
+            self.send_event(DeviceEvent::new(
+                &device_name,
+                DeviceEventKind::State { device_on },
+            ));
+        }
+    }
+
+    fn send_event(&self, event: DeviceEvent) {
+        // only fails when nobody is listening
+        let _ = self.events.send(event);
+    }
+
+    /// Has the actor at `addr` stream the outcome of a command, from the futures it's run in.
+    fn report_command(
+        addr: &Addr<Self>,
+        span: &tracing::Span,
+        device_name: &str,
+        command: DeviceCommand,
+        result: Result<bool, String>,
+    ) {
+        if let Err(e) = addr.try_send(DeviceEventMessage {
+            span_context: span.context(),
+            event: DeviceEvent::command(device_name, command, result),
+        }) {
+            record_error(span, &e);
+        }
+    }
+
+    fn start_device_actor(&mut self, addr: Addr<CoordinatorActor>, device: Device) {
+        let device_actor = DeviceActor::new(
+            addr,
+            self.settings.tapo.clone(),
+            self.settings.energy.clone(),
+            device.clone(),
+        );
+        let device_actor_addr = device_actor.start();
+
+        self.device_actors.insert(device.name, device_actor_addr);
+    }
 }
 
 impl Actor for CoordinatorActor {
@@ -58,6 +257,8 @@ impl Actor for CoordinatorActor {
     fn started(&mut self, ctx: &mut Self::Context) {
         let addr = ctx.address();
 
+        self.subscribe_scenes(addr.clone());
+
         let fut = async move {
             let mut interval = interval(Duration::from_secs(60));
 
@@ -87,7 +288,7 @@ impl Actor for CoordinatorActor {
         }
         .into_actor(self);
 
-        ctx.spawn(fut);
+        self.health_check_handle = Some(ctx.spawn(fut));
     }
 
     #[instrument(name = "CoordinatorActor::stopped", level = "error", skip_all)]
@@ -111,27 +312,158 @@ impl Handler<HealthCheckMessage> for CoordinatorActor {
     fn handle(&mut self, message: HealthCheckMessage, ctx: &mut Context<Self>) -> Self::Result {
         let _ = tracing::Span::current().set_parent(message.span_context);
 
+        if self.shutting_down {
+            return;
+        }
+
+        let addr = ctx.address();
+
         // check api
-        if !self.api_actor_addr.connected() {
+        if self
+            .api_actor_addr
+            .as_ref()
+            .is_some_and(|api_actor_addr| !api_actor_addr.connected())
+        {
             warn!("API Actor is not connected, restarting...");
-            let api_actor = ApiActor::new(self.settings.api.clone(), self.settings.tapo.clone());
-            self.api_actor_addr = api_actor.start();
+            self.api_actor_addr = None;
+        }
+
+        if self.api_actor_addr.is_none() {
+            let api_actor = ApiActor::new(
+                self.settings.api.clone(),
+                self.settings.tapo.clone(),
+                self.settings.tariff.clone(),
+                addr.clone(),
+            );
+            self.api_actor_addr = Some(api_actor.start());
         }
 
         // check mqtt
         if !self.mqtt_actor_addr.connected() {
             warn!("MQTT Actor is not connected, restarting...");
-            let mqtt_actor = MqttActor::new(self.settings.mqtt.clone())
-                .expect("failed to create the MQTT client");
+            let mqtt_actor =
+                MqttActor::new(self.settings.mqtt.clone(), self.settings.tariff.clone())
+                    .expect("failed to create the MQTT client");
             self.mqtt_actor_addr = mqtt_actor.start();
+            self.subscribe_scenes(addr.clone());
         }
 
-        // check devices
-        let addr = ctx.address();
+        // check discovery
+        if let Some(discovery) = &self.settings.discovery {
+            if self
+                .discovery_actor_addr
+                .as_ref()
+                .is_some_and(|discovery_actor_addr| !discovery_actor_addr.connected())
+            {
+                warn!("Discovery Actor is not connected, restarting...");
+                self.discovery_actor_addr = None;
+            }
+
+            if self.discovery_actor_addr.is_none() {
+                match DiscoveryActor::new(addr.clone(), discovery.clone()) {
+                    Ok(discovery_actor) => {
+                        self.discovery_actor_addr = Some(discovery_actor.start());
+                    }
+                    Err(e) => error!("Failed to create the Discovery Actor: {e:?}"),
+                }
+            }
+        }
+
+        // check storage
+        if let Some(storage) = &self.settings.storage {
+            if self
+                .storage_actor_addr
+                .as_ref()
+                .is_some_and(|storage_actor_addr| !storage_actor_addr.connected())
+            {
+                warn!("Storage Actor is not connected, restarting...");
+                self.storage_actor_addr = None;
+            }
+
+            if self.storage_actor_addr.is_none() {
+                match StorageActor::new(storage.clone()) {
+                    Ok(storage_actor) => {
+                        let storage_actor_addr = storage_actor.start();
+
+                        // pick up where the previous run left off
+                        let fut = storage_actor_addr
+                            .send(GetEnergyWatermarksMessage {
+                                span_context: tracing::Span::current().context(),
+                            })
+                            .into_actor(self)
+                            .map(|result, actor, _| match result {
+                                Ok(Ok(watermarks)) => {
+                                    for watermark in watermarks {
+                                        actor.update_energy_watermark(&watermark);
+                                    }
+                                }
+                                Ok(Err(e)) => error!("Failed to load the energy watermarks: {e:?}"),
+                                Err(e) => error!("Failed to load the energy watermarks: {e:?}"),
+                            });
+                        ctx.spawn(fut);
+
+                        self.storage_actor_addr = Some(storage_actor_addr);
+                    }
+                    Err(e) => error!("Failed to create the Storage Actor: {e:?}"),
+                }
+            }
+        }
 
-        for device in &self.settings.devices {
-            if self.device_actors.contains_key(&device.ip_address) {
-                let device_actor = self.device_actors.get(&device.ip_address);
+        // check alerts
+        if let Some(alerts) = &self.settings.alerts {
+            if self
+                .alert_actor_addr
+                .as_ref()
+                .is_some_and(|alert_actor_addr| !alert_actor_addr.connected())
+            {
+                warn!("Alert Actor is not connected, restarting...");
+                self.alert_actor_addr = None;
+            }
+
+            if self.alert_actor_addr.is_none() {
+                let alert_actor = AlertActor::new(addr.clone(), alerts.clone());
+                self.alert_actor_addr = Some(alert_actor.start());
+            }
+        }
+
+        // check scheduler
+        if let Some(scheduler) = &self.settings.scheduler {
+            if self
+                .scheduler_actor_addr
+                .as_ref()
+                .is_some_and(|scheduler_actor_addr| !scheduler_actor_addr.connected())
+            {
+                warn!("Scheduler Actor is not connected, restarting...");
+                self.scheduler_actor_addr = None;
+            }
+
+            if self.scheduler_actor_addr.is_none() {
+                let scheduler_actor = SchedulerActor::new(addr.clone(), scheduler.clone());
+                self.scheduler_actor_addr = Some(scheduler_actor.start());
+            }
+        }
+
+        // check automations
+        if let Some(automations) = &self.settings.automations {
+            if self
+                .automation_actor_addr
+                .as_ref()
+                .is_some_and(|automation_actor_addr| !automation_actor_addr.connected())
+            {
+                warn!("Automation Actor is not connected, restarting...");
+                self.automation_actor_addr = None;
+            }
+
+            if self.automation_actor_addr.is_none() {
+                let automation_actor = AutomationActor::new(addr.clone(), automations.clone());
+                self.automation_actor_addr = Some(automation_actor.start());
+            }
+        }
+
+        // check devices
+        for device in self.settings.devices.clone() {
+            if self.device_actors.contains_key(&device.name) {
+                let device_actor = self.device_actors.get(&device.name);
 
                 if let Some(device_actor) = device_actor {
                     if device_actor.connected() {
@@ -154,12 +486,7 @@ impl Handler<HealthCheckMessage> for CoordinatorActor {
             }
 
             // device actor hasn't been created yet or has died -> (re)create
-            let device_actor =
-                DeviceActor::new(addr.clone(), self.settings.tapo.clone(), device.clone());
-            let device_actor_addr = device_actor.start();
-
-            self.device_actors
-                .insert(device.ip_address.clone(), device_actor_addr);
+            self.start_device_actor(addr.clone(), device);
         }
     }
 }
@@ -188,16 +515,1907 @@ impl Handler<DeviceUsageMessage> for CoordinatorActor {
         let span = tracing::Span::current();
         let _ = span.set_parent(message.span_context);
 
-        let result = self.mqtt_actor_addr.try_send({
-            DeviceUsageMessage {
+        self.devices_usage
+            .insert(message.device.name.clone(), message.device_usage.clone());
+        self.publish_event(DeviceEvent::usage(
+            &message.device.name,
+            &message.device_usage,
+            message.current_power,
+            message.device_on,
+        ));
+
+        let message = DeviceUsageMessage {
+            span_context: span.context(),
+            ..message
+        };
+
+        forward(self.alert_actor_addr.as_ref(), message.clone(), &span);
+        forward(self.automation_actor_addr.as_ref(), message.clone(), &span);
+        forward(self.storage_actor_addr.as_ref(), message.clone(), &span);
+        forward(Some(&self.mqtt_actor_addr), message, &span);
+    }
+}
+
+impl Handler<EnergyDataMessage> for CoordinatorActor {
+    type Result = ResponseActFuture<Self, PublishOutcome>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<EnergyDataMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "EnergyDataMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: EnergyDataMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device_name = message.device.name.clone();
+        let interval = message.energy_data.interval;
+        let last_entry_start = message.energy_data.last_entry_start(Utc::now());
+
+        let mut energy_data = message.energy_data;
+        if let Some(tariff) = &self.settings.tariff {
+            tariff.apply(&mut energy_data);
+        }
+
+        let fut = self
+            .mqtt_actor_addr
+            .send(EnergyDataMessage {
                 span_context: span.context(),
                 device: message.device,
-                device_usage: message.device_usage,
+                energy_data,
+                backfilled: message.backfilled,
+            })
+            .into_actor(self)
+            .map(move |result, actor, _| {
+                let outcome = result
+                    .inspect_err(|e| record_error(&span, e))
+                    .unwrap_or_default();
+
+                // backfill from the watermarks as they were before this message moved them
+                if outcome.reconnected {
+                    actor.request_energy_backfill(&span);
+                }
+
+                if let Some(start_date_time) = last_entry_start.filter(|_| outcome.published) {
+                    let watermark = EnergyWatermark {
+                        device_name,
+                        interval,
+                        start_date_time,
+                    };
+
+                    if actor.update_energy_watermark(&watermark)
+                        && let Some(storage_actor_addr) = &actor.storage_actor_addr
+                        && let Err(e) = storage_actor_addr.try_send(EnergyPublishedMessage {
+                            span_context: span.context(),
+                            watermark,
+                        })
+                    {
+                        record_error(&span, &e);
+                    }
+                }
+
+                outcome
+            });
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<DeviceDiscoveredMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<DeviceDiscoveredMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceDiscoveredMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.model = %message.device.device_model,
+            device.ip_address = %message.device.ip_address,
+            device.mac = %message.device.mac,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: DeviceDiscoveredMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let discovered = message.device;
+
+        if self.shutting_down {
+            return;
+        }
+
+        if self.settings.devices.iter().any(|device| {
+            device.ip_address == discovered.ip_address
+                || (device.has_identity()
+                    && device.is_identified_by(&discovered.mac, &discovered.device_id))
+        }) {
+            return;
+        }
+
+        if !discovered.is_energy_monitoring_plug() {
+            debug!("Discovered device doesn't report energy usage, skipping registration");
+            return;
+        }
+
+        let model = discovered
+            .device_model
+            .split('(')
+            .next()
+            .unwrap_or(&discovered.device_model);
+
+        let device = Device {
+            ip_address: discovered.ip_address.clone(),
+            name: format!("{model}_{}", discovered.mac.replace(':', "").to_lowercase()),
+            device_type: DeviceType::Plug,
+            record_time_usage: true,
+            refresh_rate_s: None,
+            credentials: None,
+            mac: Some(discovered.mac.clone()),
+            device_id: Some(discovered.device_id.clone()),
+            cycle: None,
+        };
+
+        info!(
+            device.name = device.name,
+            "Registering discovered device, creating a new device actor...",
+        );
+
+        self.settings.devices.push(device.clone());
+        self.start_device_actor(ctx.address(), device);
+    }
+}
+
+impl Handler<GetDiscoveredDevicesMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<Vec<DiscoveredDevice>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetDiscoveredDevicesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDiscoveredDevicesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: GetDiscoveredDevicesMessage,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let discovery_actor_addr = self.discovery_actor_addr.clone();
+
+        Box::pin(async move {
+            discovery_actor_addr?
+                .send(GetDiscoveredDevicesMessage {
+                    span_context: span.context(),
+                })
+                .await
+                .ok()
+                .flatten()
+        })
+    }
+}
+
+impl Handler<ResolveDeviceAddressMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<ResolveDeviceAddressMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ResolveDeviceAddressMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: ResolveDeviceAddressMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device = message.device;
+        let discovery_actor_addr = self.discovery_actor_addr.clone();
+
+        let fut = {
+            let device = device.clone();
+            let span = span.clone();
+
+            async move {
+                if let Some(discovery_actor_addr) = discovery_actor_addr {
+                    let result = discovery_actor_addr
+                        .send(FindDeviceMessage {
+                            span_context: span.context(),
+                            device: device.clone(),
+                        })
+                        .await;
+
+                    match result {
+                        Ok(Some(discovered)) => return Some(discovered.ip_address),
+                        Ok(None) => {}
+                        Err(e) => record_error(&span, &e),
+                    }
+                }
+
+                device.mac.as_deref().and_then(arp::lookup)
+            }
+        }
+        .instrument(span.clone())
+        .into_actor(self)
+        .map(move |ip_address, actor, _| {
+            let _enter = span.enter();
+
+            let Some(ip_address) = ip_address.filter(|ip| *ip != device.ip_address) else {
+                warn!("Failed to resolve a new address for the device");
+                return;
+            };
+
+            info!(
+                device.new_ip_address = ip_address,
+                "Resolved a new address for the device"
+            );
+
+            if let Some(configured) = actor
+                .settings
+                .devices
+                .iter_mut()
+                .find(|configured| configured.name == device.name)
+            {
+                configured.ip_address = ip_address.clone();
+            }
+
+            if let Some(device_actor_addr) = actor.device_actors.get(&device.name) {
+                let result = device_actor_addr.try_send(DeviceAddressChangedMessage {
+                    span_context: span.context(),
+                    ip_address,
+                });
+
+                if let Err(e) = result {
+                    record_error(&span, &e);
+                }
             }
         });
 
-        if let Err(e) = result {
-            record_error(&span, &e);
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<SetRefreshRateMessage> for CoordinatorActor {
+    type Result = ResponseFuture<bool>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SetRefreshRateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetRefreshRateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+            device.refresh_rate_s = message.refresh_rate_s,
+        )
+    )]
+    fn handle(&mut self, message: SetRefreshRateMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // keep the settings in sync so that a restarted device actor keeps the new refresh rate
+        if let Some(device) = self
+            .settings
+            .devices
+            .iter_mut()
+            .find(|device| device.name == message.device_name)
+        {
+            device.refresh_rate_s = Some(message.refresh_rate_s);
         }
+
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let device_name = message.device_name;
+        let refresh_rate_s = message.refresh_rate_s;
+
+        let fut = async move {
+            let Some(device_actor_addr) = device_actor_addr else {
+                return false;
+            };
+
+            device_actor_addr
+                .send(SetRefreshRateMessage {
+                    span_context: span.context(),
+                    device_name,
+                    refresh_rate_s,
+                })
+                .await
+                .unwrap_or(false)
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<GetDevicesMessage> for CoordinatorActor {
+    type Result = MessageResult<GetDevicesMessage>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetDevicesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDevicesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(&mut self, message: GetDevicesMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        MessageResult(self.settings.devices.clone())
+    }
+}
+
+impl Handler<OutletsStateMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<OutletsStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "OutletsStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: OutletsStateMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.outlets
+            .insert(message.device.name.clone(), message.outlets.clone());
+        self.publish_event(DeviceEvent::new(
+            &message.device.name,
+            DeviceEventKind::Sample(DeviceSample::Outlets {
+                outlets: message.outlets.clone(),
+            }),
+        ));
+
+        let result = self.mqtt_actor_addr.try_send(OutletsStateMessage {
+            span_context: span.context(),
+            device: message.device,
+            outlets: message.outlets,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<SetOutletMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<Result<OutletState, OutletError>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SetOutletMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetOutletMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+            device.outlet = message.position,
+        )
+    )]
+    fn handle(&mut self, message: SetOutletMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let addr = ctx.address();
+        let device_type = self
+            .settings
+            .devices
+            .iter()
+            .find(|device| device.name == message.device_name)
+            .map(|device| device.device_type);
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let SetOutletMessage {
+            device_name,
+            position,
+            action,
+            ..
+        } = message;
+
+        let fut = async move {
+            if device_type? != DeviceType::PowerStrip {
+                return Some(Err(OutletError::NotFound));
+            }
+
+            let result = match device_actor_addr?
+                .send(SetOutletMessage {
+                    span_context: span.context(),
+                    device_name: device_name.clone(),
+                    position,
+                    action,
+                })
+                .await
+            {
+                Ok(result) => result?,
+                Err(e) => Err(OutletError::Failed(e.into())),
+            };
+
+            let outcome = match &result {
+                Ok(outlet) => Ok(outlet.device_on),
+                Err(e) => Err(e.to_string()),
+            };
+            Self::report_command(
+                &addr,
+                &span,
+                &device_name,
+                DeviceCommand::Outlet { position, action },
+                outcome,
+            );
+
+            Some(result)
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<ListDevicesMessage> for CoordinatorActor {
+    type Result = MessageResult<ListDevicesMessage>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<ListDevicesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ListDevicesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(&mut self, message: ListDevicesMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let devices = self
+            .settings
+            .devices
+            .iter()
+            .map(|device| {
+                let outlets = self.outlets.get(&device.name).cloned().unwrap_or_default();
+                let sensors = self.sensors.get(&device.name).cloned().unwrap_or_default();
+
+                DeviceSummary::new(device, outlets, sensors)
+            })
+            .collect();
+
+        MessageResult(devices)
+    }
+}
+
+impl Handler<SensorsStateMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SensorsStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SensorsStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SensorsStateMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.sensors
+            .insert(message.device.name.clone(), message.sensors.clone());
+        self.publish_event(DeviceEvent::new(
+            &message.device.name,
+            DeviceEventKind::Sample(DeviceSample::Sensors {
+                sensors: message.sensors.clone(),
+            }),
+        ));
+
+        let result = self.mqtt_actor_addr.try_send(SensorsStateMessage {
+            span_context: span.context(),
+            device: message.device,
+            sensors: message.sensors,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<SensorEventsMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SensorEventsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SensorEventsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SensorEventsMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if let Some(automation_actor_addr) = &self.automation_actor_addr
+            && let Err(e) = automation_actor_addr.try_send(SensorEventsMessage {
+                span_context: span.context(),
+                device: message.device.clone(),
+                events: message.events.clone(),
+            })
+        {
+            record_error(&span, &e);
+        }
+
+        let result = self.mqtt_actor_addr.try_send(SensorEventsMessage {
+            span_context: span.context(),
+            device: message.device,
+            events: message.events,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetSensorsMessage> for CoordinatorActor {
+    type Result = MessageResult<GetSensorsMessage>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetSensorsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetSensorsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: GetSensorsMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let is_hub = self.settings.devices.iter().any(|device| {
+            device.name == message.device_name && device.device_type == DeviceType::Hub
+        });
+
+        if !is_hub {
+            return MessageResult(None);
+        }
+
+        // a hub that hasn't been polled yet has no sensors to report
+        MessageResult(Some(
+            self.sensors
+                .get(&message.device_name)
+                .cloned()
+                .unwrap_or_default(),
+        ))
+    }
+}
+
+impl Handler<ShutdownMessage> for CoordinatorActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // stop (re)creating actors while they are being shut down
+        self.shutting_down = true;
+        // dropping the sender ends the event streams, which the API would otherwise wait for
+        self.events = broadcast::channel(EVENTS_CAPACITY).0;
+        if let Some(health_check_handle) = self.health_check_handle.take() {
+            ctx.cancel_future(health_check_handle);
+        }
+
+        let api_actor_addr = self.api_actor_addr.take();
+        let discovery_actor_addr = self.discovery_actor_addr.take();
+        let storage_actor_addr = self.storage_actor_addr.take();
+        let alert_actor_addr = self.alert_actor_addr.take();
+        let scheduler_actor_addr = self.scheduler_actor_addr.take();
+        let automation_actor_addr = self.automation_actor_addr.take();
+        let device_actors: Vec<_> = self.device_actors.drain().map(|(_, addr)| addr).collect();
+        let mqtt_actor_addr = self.mqtt_actor_addr.clone();
+
+        let fut = async move {
+            let span = tracing::Span::current();
+
+            info!("Stopping the API...");
+            if let Some(api_actor_addr) = api_actor_addr
+                && let Err(e) = api_actor_addr
+                    .send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                    .await
+            {
+                record_error(&span, &e);
+            }
+
+            if let Some(discovery_actor_addr) = discovery_actor_addr
+                && let Err(e) = discovery_actor_addr
+                    .send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                    .await
+            {
+                record_error(&span, &e);
+            }
+
+            // no more scheduled or automated switching while the devices are being stopped
+            if let Some(scheduler_actor_addr) = scheduler_actor_addr
+                && let Err(e) = scheduler_actor_addr
+                    .send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                    .await
+            {
+                record_error(&span, &e);
+            }
+
+            if let Some(automation_actor_addr) = automation_actor_addr
+                && let Err(e) = automation_actor_addr
+                    .send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                    .await
+            {
+                record_error(&span, &e);
+            }
+
+            info!(
+                "Waiting for {} device(s) to finish polling...",
+                device_actors.len()
+            );
+            // every message is queued before awaiting any of them, so the devices drain in parallel
+            let requests: Vec<_> = device_actors
+                .iter()
+                .map(|device_actor_addr| {
+                    device_actor_addr.send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                })
+                .collect();
+
+            for request in requests {
+                if let Err(e) = request.await {
+                    record_error(&span, &e);
+                }
+            }
+
+            info!("Delivering the alerts...");
+            if let Some(alert_actor_addr) = alert_actor_addr
+                && let Err(e) = alert_actor_addr
+                    .send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                    .await
+            {
+                record_error(&span, &e);
+            }
+
+            info!("Flushing the MQTT messages...");
+            if let Err(e) = mqtt_actor_addr
+                .send(ShutdownMessage {
+                    span_context: span.context(),
+                })
+                .await
+            {
+                record_error(&span, &e);
+            }
+
+            if let Some(storage_actor_addr) = storage_actor_addr
+                && let Err(e) = storage_actor_addr
+                    .send(ShutdownMessage {
+                        span_context: span.context(),
+                    })
+                    .await
+            {
+                record_error(&span, &e);
+            }
+        }
+        .instrument(span)
+        .into_actor(self)
+        .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<GetDeviceHistoryMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<Vec<Sample>>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetDeviceHistoryMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDeviceHistoryMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: GetDeviceHistoryMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let storage_enabled = self.settings.storage.is_some();
+        let storage_actor_addr = self.storage_actor_addr.clone();
+        let GetDeviceHistoryMessage {
+            device_name,
+            from,
+            to,
+            resolution,
+            ..
+        } = message;
+
+        let fut = async move {
+            if !storage_enabled {
+                return None;
+            }
+
+            let Some(storage_actor_addr) = storage_actor_addr else {
+                return Some(Err(anyhow::anyhow!("the storage actor is not running")));
+            };
+
+            let result = storage_actor_addr
+                .send(GetDeviceHistoryMessage {
+                    span_context: span.context(),
+                    device_name,
+                    from,
+                    to,
+                    resolution,
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<GetEnergyDataMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<EnergyData>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetEnergyDataMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetEnergyDataMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: GetEnergyDataMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let device_name = message.device_name;
+        let request = message.request;
+        let tariff = self.settings.tariff.clone();
+
+        let fut = async move {
+            let result = device_actor_addr?
+                .send(GetEnergyDataMessage {
+                    span_context: span.context(),
+                    device_name,
+                    request,
+                })
+                .await;
+
+            match result {
+                Ok(result) => Some(result?.map(|mut energy_data| {
+                    if let Some(tariff) = &tariff {
+                        tariff.apply(&mut energy_data);
+                    }
+                    energy_data
+                })),
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<GetDevicesUsageMessage> for CoordinatorActor {
+    type Result = MessageResult<GetDevicesUsageMessage>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetDevicesUsageMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDevicesUsageMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(&mut self, message: GetDevicesUsageMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let devices_usage = self
+            .settings
+            .devices
+            .iter()
+            .map(|device| {
+                let device_usage = self.devices_usage.get(&device.name).cloned();
+                (device.clone(), device_usage)
+            })
+            .collect();
+
+        MessageResult(devices_usage)
+    }
+}
+
+impl Handler<DeviceUnreachableMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<DeviceUnreachableMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceUnreachableMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: DeviceUnreachableMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.unavailable_devices.insert(message.device.name.clone()) {
+            self.publish_event(DeviceEvent::new(
+                &message.device.name,
+                DeviceEventKind::Availability { available: false },
+            ));
+        }
+
+        if let Some(automation_actor_addr) = &self.automation_actor_addr {
+            let result = automation_actor_addr.try_send(DeviceUnreachableMessage {
+                span_context: span.context(),
+                device: message.device.clone(),
+            });
+
+            if let Err(e) = result {
+                record_error(&span, &e);
+            }
+        }
+
+        if let Some(alert_actor_addr) = &self.alert_actor_addr {
+            let result = alert_actor_addr.try_send(DeviceUnreachableMessage {
+                span_context: span.context(),
+                device: message.device,
+            });
+
+            if let Err(e) = result {
+                record_error(&span, &e);
+            }
+        }
+    }
+}
+
+impl Handler<AlertMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<AlertMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "AlertMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.alert.device_name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: AlertMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let result = self.mqtt_actor_addr.try_send(AlertMessage {
+            span_context: span.context(),
+            alert: message.alert,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<CycleEventMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<CycleEventMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "CycleEventMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: CycleEventMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if let CycleEvent::CycleFinished(_) = &message.event
+            && let Some(storage_actor_addr) = &self.storage_actor_addr
+            && let Err(e) = storage_actor_addr.try_send(CycleEventMessage {
+                span_context: span.context(),
+                device: message.device.clone(),
+                event: message.event.clone(),
+            })
+        {
+            record_error(&span, &e);
+        }
+
+        let result = self.mqtt_actor_addr.try_send(CycleEventMessage {
+            span_context: span.context(),
+            device: message.device,
+            event: message.event,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetDeviceCyclesMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<Vec<Cycle>>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetDeviceCyclesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDeviceCyclesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: GetDeviceCyclesMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let storage_enabled = self.settings.storage.is_some();
+        let storage_actor_addr = self.storage_actor_addr.clone();
+        let GetDeviceCyclesMessage {
+            device_name,
+            from,
+            to,
+            ..
+        } = message;
+
+        let fut = async move {
+            if !storage_enabled {
+                return None;
+            }
+
+            let Some(storage_actor_addr) = storage_actor_addr else {
+                return Some(Err(anyhow::anyhow!("the storage actor is not running")));
+            };
+
+            let result = storage_actor_addr
+                .send(GetDeviceCyclesMessage {
+                    span_context: span.context(),
+                    device_name,
+                    from,
+                    to,
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SetDevicePowerMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<bool>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SetDevicePowerMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetDevicePowerMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: SetDevicePowerMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let addr = ctx.address();
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let SetDevicePowerMessage {
+            device_name,
+            action,
+            ..
+        } = message;
+
+        let fut = async move {
+            let device_actor_addr = device_actor_addr?;
+
+            let result = device_actor_addr
+                .send(SetDevicePowerMessage {
+                    span_context: span.context(),
+                    device_name: device_name.clone(),
+                    action,
+                })
+                .await;
+
+            let result = match result {
+                Ok(result) => result?,
+                Err(e) => Err(e.into()),
+            };
+
+            let outcome = match &result {
+                Ok(device_on) => Ok(*device_on),
+                Err(e) => Err(format!("{e:#}")),
+            };
+            Self::report_command(
+                &addr,
+                &span,
+                &device_name,
+                DeviceCommand::Power { action },
+                outcome,
+            );
+
+            Some(result)
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<LightStateMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<LightStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "LightStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: LightStateMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.publish_event(DeviceEvent::new(
+            &message.device.name,
+            DeviceEventKind::Sample(DeviceSample::Light(message.state.clone())),
+        ));
+
+        let result = self.mqtt_actor_addr.try_send(LightStateMessage {
+            span_context: span.context(),
+            device: message.device,
+            state: message.state,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<SetLightMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<Result<LightState, LightError>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SetLightMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetLightMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: SetLightMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let addr = ctx.address();
+        let device_type = self
+            .settings
+            .devices
+            .iter()
+            .find(|device| device.name == message.device_name)
+            .map(|device| device.device_type);
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let SetLightMessage {
+            device_name, light, ..
+        } = message;
+
+        let fut = async move {
+            if let Err(e) = light.validate(device_type?) {
+                return Some(Err(LightError::Invalid(e)));
+            }
+
+            let result = match device_actor_addr?
+                .send(SetLightMessage {
+                    span_context: span.context(),
+                    device_name: device_name.clone(),
+                    light,
+                })
+                .await
+            {
+                Ok(result) => result?,
+                Err(e) => Err(LightError::Failed(e.into())),
+            };
+
+            let outcome = match &result {
+                Ok(state) => Ok(state.device_on),
+                Err(e) => Err(e.to_string()),
+            };
+            Self::report_command(&addr, &span, &device_name, DeviceCommand::Light, outcome);
+
+            Some(result)
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<GetDeviceStatusMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<DeviceStatus>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetDeviceStatusMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDeviceStatusMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: GetDeviceStatusMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let device_name = message.device_name;
+
+        let fut = async move {
+            let device_actor_addr = device_actor_addr?;
+
+            let result = device_actor_addr
+                .send(GetDeviceStatusMessage {
+                    span_context: span.context(),
+                    device_name,
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SetDeviceTimerMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<TimerStatus>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SetDeviceTimerMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetDeviceTimerMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+            device.timer_s = message.duration_s,
+        )
+    )]
+    fn handle(&mut self, message: SetDeviceTimerMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let SetDeviceTimerMessage {
+            device_name,
+            action,
+            duration_s,
+            ..
+        } = message;
+
+        let fut = async move {
+            device_actor_addr?
+                .send(SetDeviceTimerMessage {
+                    span_context: span.context(),
+                    device_name,
+                    action,
+                    duration_s,
+                })
+                .await
+                .ok()
+                .flatten()
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<CancelDeviceTimerMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<bool>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<CancelDeviceTimerMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "CancelDeviceTimerMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: CancelDeviceTimerMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device_actor_addr = self.device_actors.get(&message.device_name).cloned();
+        let device_name = message.device_name;
+
+        let fut = async move {
+            device_actor_addr?
+                .send(CancelDeviceTimerMessage {
+                    span_context: span.context(),
+                    device_name,
+                })
+                .await
+                .ok()
+                .flatten()
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<ActivateSceneMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<SceneActivation>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<ActivateSceneMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ActivateSceneMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            scene.name = %message.scene_name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: ActivateSceneMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let Some(scene) = self
+            .settings
+            .scenes
+            .iter()
+            .find(|scene| scene.name == message.scene_name)
+            .cloned()
+        else {
+            warn!("Unknown scene '{}'", message.scene_name);
+            return Box::pin(async { None });
+        };
+
+        info!("Activating scene '{}'", scene.name);
+
+        // the devices are switched in parallel, each by its own actor
+        let switches = scene
+            .devices
+            .into_iter()
+            .map(|(device_name, state)| {
+                let addr = ctx.address();
+                let device_actor_addr = self.device_actors.get(&device_name).cloned();
+                let span = span.clone();
+                let scene_name = scene.name.clone();
+
+                async move {
+                    let result = match device_actor_addr {
+                        Some(device_actor_addr) => {
+                            let result = device_actor_addr
+                                .send(SetDeviceStateMessage {
+                                    span_context: span.context(),
+                                    device_name: device_name.clone(),
+                                    state,
+                                })
+                                .await
+                                .map_err(anyhow::Error::from)
+                                .and_then(|result| result.context("unknown device")?);
+
+                            let outcome = match &result {
+                                Ok(device_on) => Ok(*device_on),
+                                Err(e) => Err(format!("{e:#}")),
+                            };
+                            Self::report_command(
+                                &addr,
+                                &span,
+                                &device_name,
+                                DeviceCommand::Scene { scene: scene_name },
+                                outcome,
+                            );
+
+                            result
+                        }
+                        None => Err(anyhow::anyhow!("unknown device")),
+                    };
+
+                    if let Err(e) = &result {
+                        warn!("Failed to apply the scene to '{device_name}': {e:#}");
+                    }
+
+                    SceneDeviceResult::new(device_name, result)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let scheduler_actor_addr = self.scheduler_actor_addr.clone();
+
+        let fut = async move {
+            let devices = futures::future::join_all(switches).await;
+
+            // like the devices switched through the API, the schedules leave them alone for a while
+            if let Some(scheduler_actor_addr) = scheduler_actor_addr {
+                for result in devices.iter().filter(|result| result.success) {
+                    if let Err(e) = scheduler_actor_addr.try_send(ManualOverrideMessage {
+                        span_context: span.context(),
+                        device_name: result.device.clone(),
+                    }) {
+                        record_error(&span, &e);
+                    }
+                }
+            }
+
+            Some(SceneActivation {
+                scene: scene.name,
+                devices,
+            })
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<DeviceControlledMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<DeviceControlledMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceControlledMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.ip_address = %message.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: DeviceControlledMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // devices that aren't configured have no schedules, nor events
+        let Some(device) = self
+            .settings
+            .devices
+            .iter()
+            .find(|device| device.ip_address == message.ip_address)
+            .cloned()
+        else {
+            return;
+        };
+
+        let action = match message.device_on {
+            true => DeviceAction::On,
+            false => DeviceAction::Off,
+        };
+        self.publish_event(DeviceEvent::command(
+            &device.name,
+            DeviceCommand::Power { action },
+            Ok(message.device_on),
+        ));
+
+        let Some(scheduler_actor_addr) = &self.scheduler_actor_addr else {
+            return;
+        };
+
+        if let Err(e) = scheduler_actor_addr.try_send(ManualOverrideMessage {
+            span_context: span.context(),
+            device_name: device.name.clone(),
+        }) {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetSchedulesMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<Vec<ScheduleStatus>>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetSchedulesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetSchedulesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(&mut self, message: GetSchedulesMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let scheduler_enabled = self.settings.scheduler.is_some();
+        let scheduler_actor_addr = self.scheduler_actor_addr.clone();
+
+        let fut = async move {
+            if !scheduler_enabled {
+                return None;
+            }
+
+            let Some(scheduler_actor_addr) = scheduler_actor_addr else {
+                return Some(Err(anyhow::anyhow!("the scheduler actor is not running")));
+            };
+
+            let result = scheduler_actor_addr
+                .send(GetSchedulesMessage {
+                    span_context: span.context(),
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SetScheduleMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<Result<bool, ScheduleError>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SetScheduleMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetScheduleMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            schedule.name = %message.schedule.name,
+        )
+    )]
+    fn handle(&mut self, message: SetScheduleMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let Some(scheduler) = &mut self.settings.scheduler else {
+            return Box::pin(async { None });
+        };
+
+        let schedule = message.schedule;
+
+        let mut errors = schedule
+            .validate(scheduler.location)
+            .err()
+            .unwrap_or_default();
+
+        if !self
+            .settings
+            .devices
+            .iter()
+            .any(|device| device.name == schedule.device)
+        {
+            errors.errors.push(ValidationError {
+                key: "device".to_string(),
+                message: format!("device '{}' not found", schedule.device),
+            });
+        }
+
+        if !errors.errors.is_empty() {
+            return Box::pin(async { Some(Err(ScheduleError::Invalid(errors))) });
+        }
+
+        // keep the settings in sync so that a restarted scheduler actor keeps the schedule
+        let existing = scheduler
+            .schedules
+            .iter_mut()
+            .find(|existing| existing.name == schedule.name);
+
+        let replaced = match existing {
+            Some(existing) => {
+                *existing = schedule.clone();
+                true
+            }
+            None => {
+                scheduler.schedules.push(schedule.clone());
+                false
+            }
+        };
+
+        let scheduler_actor_addr = self.scheduler_actor_addr.clone();
+
+        let fut = async move {
+            // the schedule is picked up once the scheduler actor is (re)started
+            let Some(scheduler_actor_addr) = scheduler_actor_addr else {
+                return Some(Ok(replaced));
+            };
+
+            let result = scheduler_actor_addr
+                .send(SetScheduleMessage {
+                    span_context: span.context(),
+                    schedule,
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(ScheduleError::Failed(e.into()))),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<DeleteScheduleMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<bool>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<DeleteScheduleMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeleteScheduleMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            schedule.name = %message.name,
+        )
+    )]
+    fn handle(&mut self, message: DeleteScheduleMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let Some(scheduler) = &mut self.settings.scheduler else {
+            return Box::pin(async { None });
+        };
+
+        let count = scheduler.schedules.len();
+        scheduler
+            .schedules
+            .retain(|schedule| schedule.name != message.name);
+        let deleted = scheduler.schedules.len() != count;
+
+        let scheduler_actor_addr = self.scheduler_actor_addr.clone();
+        let name = message.name;
+
+        let fut = async move {
+            let Some(scheduler_actor_addr) = scheduler_actor_addr else {
+                return Some(Ok(deleted));
+            };
+
+            let result = scheduler_actor_addr
+                .send(DeleteScheduleMessage {
+                    span_context: span.context(),
+                    name,
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<MqttPublishMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<MqttPublishMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "MqttPublishMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            messaging.mqtt.topic = %message.topic,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: MqttPublishMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let result = self.mqtt_actor_addr.try_send(MqttPublishMessage {
+            span_context: span.context(),
+            topic: message.topic,
+            payload: message.payload,
+        });
+
+        if let Err(e) = result {
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetAutomationsMessage> for CoordinatorActor {
+    type Result = ResponseFuture<Option<anyhow::Result<Vec<AutomationStatus>>>>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<GetAutomationsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetAutomationsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(&mut self, message: GetAutomationsMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let automations_enabled = self.settings.automations.is_some();
+        let automation_actor_addr = self.automation_actor_addr.clone();
+
+        let fut = async move {
+            if !automations_enabled {
+                return None;
+            }
+
+            let Some(automation_actor_addr) = automation_actor_addr else {
+                return Some(Err(anyhow::anyhow!("the automation actor is not running")));
+            };
+
+            let result = automation_actor_addr
+                .send(GetAutomationsMessage {
+                    span_context: span.context(),
+                })
+                .await;
+
+            match result {
+                Ok(result) => result,
+                Err(e) => Some(Err(e.into())),
+            }
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SubscribeEventsMessage> for CoordinatorActor {
+    type Result = MessageResult<SubscribeEventsMessage>;
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<SubscribeEventsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SubscribeEventsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+        )
+    )]
+    fn handle(&mut self, message: SubscribeEventsMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        MessageResult(self.events.subscribe())
+    }
+}
+
+impl Handler<DeviceEventMessage> for CoordinatorActor {
+    type Result = ();
+
+    #[instrument(
+        name = "CoordinatorActor::Handler<DeviceEventMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceEventMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "CoordinatorActor",
+            device.name = %message.event.device,
+        )
+    )]
+    fn handle(&mut self, message: DeviceEventMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        self.publish_event(message.event);
+    }
+}
+
+/// Sends the message to the actor when it's running, recording the failure to deliver it on `span`.
+fn forward<A, M>(addr: Option<&Addr<A>>, message: M, span: &tracing::Span)
+where
+    A: Actor<Context = Context<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    if let Some(addr) = addr
+        && let Err(e) = addr.try_send(message)
+    {
+        record_error(span, &e);
     }
 }
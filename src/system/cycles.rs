@@ -0,0 +1,104 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::CycleDetection;
+
+/// A finished appliance cycle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cycle {
+    pub started_at: DateTime<Utc>,
+    /// When the appliance went idle for good.
+    pub finished_at: DateTime<Utc>,
+    pub duration_s: u64,
+    /// Energy in watt-hour (Wh)
+    pub energy_wh: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CycleEvent {
+    CycleStarted { started_at: DateTime<Utc> },
+    CycleFinished(Cycle),
+}
+
+#[derive(Debug, Clone)]
+enum CycleState {
+    Idle,
+    Running {
+        started_at: DateTime<Utc>,
+        last_sample: (DateTime<Utc>, u64),
+        energy_wh: f64,
+        idle_since: Option<DateTime<Utc>>,
+    },
+}
+
+/// Follows the current power of an appliance to tell when its cycles start and finish.
+#[derive(Debug, Clone)]
+pub struct CycleDetector {
+    config: CycleDetection,
+    state: CycleState,
+}
+
+impl CycleDetector {
+    pub fn new(config: CycleDetection) -> Self {
+        Self {
+            config,
+            state: CycleState::Idle,
+        }
+    }
+
+    /// Advances the detector with a current power sample, in watts.
+    pub fn observe(&mut self, current_power: u64, now: DateTime<Utc>) -> Option<CycleEvent> {
+        match &mut self.state {
+            CycleState::Idle => {
+                if current_power < self.config.start_threshold_w {
+                    return None;
+                }
+
+                self.state = CycleState::Running {
+                    started_at: now,
+                    last_sample: (now, current_power),
+                    energy_wh: 0.0,
+                    idle_since: None,
+                };
+
+                Some(CycleEvent::CycleStarted { started_at: now })
+            }
+            CycleState::Running {
+                started_at,
+                last_sample,
+                energy_wh,
+                idle_since,
+            } => {
+                // the power is assumed to have changed linearly between the samples
+                let (last_sampled_at, last_power) = *last_sample;
+                let hours = (now - last_sampled_at).as_seconds_f64() / 3600.0;
+                *energy_wh += (last_power + current_power) as f64 / 2.0 * hours;
+                *last_sample = (now, current_power);
+
+                if current_power > self.config.idle_threshold_w {
+                    *idle_since = None;
+                    return None;
+                }
+
+                let idle_since = *idle_since.get_or_insert(now);
+                let min_idle_s = i64::try_from(self.config.min_idle_s).unwrap_or(i64::MAX);
+
+                if now - idle_since < TimeDelta::seconds(min_idle_s) {
+                    return None;
+                }
+
+                let cycle = Cycle {
+                    started_at: *started_at,
+                    finished_at: idle_since,
+                    duration_s: u64::try_from((idle_since - *started_at).num_seconds())
+                        .unwrap_or_default(),
+                    energy_wh: energy_wh.round() as u64,
+                };
+                self.state = CycleState::Idle;
+
+                Some(CycleEvent::CycleFinished(cycle))
+            }
+        }
+    }
+}
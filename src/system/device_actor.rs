@@ -1,23 +1,96 @@
+use std::collections::HashMap;
+use std::pin::Pin;
 use std::time::Duration;
 
-use actix::{Actor, Addr, AsyncContext, Context, Handler, WrapFuture, clock::interval};
-use tapo::ApiClient;
-use tracing::{Instrument, error, instrument};
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, ResponseActFuture,
+    ResponseFuture, SpawnHandle, WrapFuture,
+    clock::{interval, sleep},
+};
+use anyhow::Context as _;
+use chrono::{DateTime, Local, TimeDelta, TimeZone as _, Utc};
+use rand::Rng as _;
+use tapo::requests::ColorLightSetDeviceInfoParams;
+use tapo::{ApiClient, HubDevice, HubHandler, Plug, PowerStripHandler, RgbicLightStripHandler};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, error, info, instrument, warn};
 use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
 use crate::{
-    settings::{Device, Tapo},
-    system::messages::{DeviceUsageMessage, GetDeviceDataMessage},
+    settings::{
+        Credentials, Device, DeviceAction, DeviceType, Energy, LightSettings, SceneState, Tapo,
+    },
+    system::cycles::CycleDetector,
+    system::device_status::{
+        DeviceInfo, DeviceStatus, LightError, LightState, OutletError, OutletState, TimerStatus,
+    },
+    system::energy::{EnergyData, EnergyInterval, EnergyRequest},
+    system::events::{DeviceCommand, DeviceEvent},
+    system::messages::{
+        BackfillEnergyDataMessage, CancelDeviceTimerMessage, CycleEventMessage,
+        DeviceAddressChangedMessage, DeviceEventMessage, DeviceTimerElapsedMessage,
+        DeviceUnreachableMessage, DeviceUsageMessage, EnergyDataMessage, FetchEnergyDataMessage,
+        GetDeviceDataMessage, GetDeviceStatusMessage, GetEnergyDataMessage, LightStateMessage,
+        OutletsStateMessage, ResolveDeviceAddressMessage, SensorEventsMessage, SensorsStateMessage,
+        SetDevicePowerMessage, SetDeviceStateMessage, SetDeviceTimerMessage, SetLightMessage,
+        SetOutletMessage, SetRefreshRateMessage, ShutdownMessage,
+    },
+    system::sensors::{Sensor, SensorEvent, SensorReading, unseen_events},
     telemetry::record_error,
 };
 
 use super::coordinator_actor::CoordinatorActor;
 
+/// Number of consecutive failed polls after which the device's address is re-resolved.
+const FAILURES_BEFORE_RESOLVE: u32 = 3;
+
+/// Trigger log entries read from a sensor on every poll, newest first.
+const TRIGGER_LOG_PAGE_SIZE: u64 = 50;
+
+#[derive(Debug)]
+enum PollOutcome {
+    Success {
+        identity_verified: bool,
+        reading: PollReading,
+    },
+    Failure,
+    IdentityMismatch,
+}
+
+/// What the actor takes from a successful poll.
+#[derive(Debug, Default)]
+struct PollReading {
+    /// Only reported by plugs.
+    current_power: Option<u64>,
+    /// Only reported by hubs, the trigger log events since the previous poll.
+    sensor_events: Vec<SensorEvent>,
+    /// Only reported by hubs, the id of the last trigger log event read from every sensor.
+    sensor_event_ids: Option<HashMap<String, u64>>,
+}
+
+/// Countdown kept by the actor, the Tapo client doesn't expose the on-device countdown rules.
+#[derive(Debug)]
+struct DeviceTimer {
+    action: DeviceAction,
+    fires_at: DateTime<Utc>,
+    handle: SpawnHandle,
+}
+
 #[derive(Debug)]
 pub struct DeviceActor {
     coordinator_actor_addr: Addr<CoordinatorActor>,
     config: Tapo,
+    energy: Option<Energy>,
     device: Device,
+    identity_verified: bool,
+    consecutive_failures: u32,
+    poll_handle: Option<SpawnHandle>,
+    energy_handle: Option<SpawnHandle>,
+    cycle_detector: Option<CycleDetector>,
+    timer: Option<DeviceTimer>,
+    /// Id of the last trigger log event read from every sensor of a hub, by sensor device id.
+    sensor_event_ids: HashMap<String, u64>,
+    in_flight: TaskTracker,
 }
 
 impl DeviceActor {
@@ -28,12 +101,24 @@ impl DeviceActor {
     pub fn new(
         coordinator_actor_addr: Addr<CoordinatorActor>,
         config: Tapo,
+        energy: Option<Energy>,
         device: Device,
     ) -> Self {
+        let cycle_detector = device.cycle.clone().map(CycleDetector::new);
+
         Self {
             coordinator_actor_addr,
             config,
+            energy,
             device,
+            identity_verified: false,
+            consecutive_failures: 0,
+            poll_handle: None,
+            energy_handle: None,
+            cycle_detector,
+            timer: None,
+            sensor_event_ids: HashMap::new(),
+            in_flight: TaskTracker::new(),
         }
     }
 
@@ -42,57 +127,471 @@ impl DeviceActor {
         tapo_username: String,
         tapo_password: String,
         coordinator_actor_addr: Addr<CoordinatorActor>,
-    ) {
+        verify_identity: bool,
+    ) -> PollOutcome {
         let span = tracing::Span::current();
 
         let result = async {
             let client = ApiClient::new(tapo_username, tapo_password);
             let handler = client.p110(device.ip_address.clone()).await?;
 
+            let device_info = handler.get_device_info().await?;
+
+            if verify_identity && !device.is_identified_by(&device_info.mac, &device_info.device_id)
+            {
+                return Ok(None);
+            }
+
             let device_usage = handler.get_device_usage().await?;
+            let current_power = handler.get_current_power().await?.current_power;
+
+            let message = DeviceUsageMessage {
+                span_context: span.context(),
+                device: device.clone(),
+                device_usage,
+                current_power,
+                device_on: device_info.device_on,
+            };
+            let reading = PollReading {
+                current_power: Some(current_power),
+                ..Default::default()
+            };
+
+            Ok::<_, anyhow::Error>(Some((message, reading)))
+        }
+        .await;
+
+        Self::report_poll(device, coordinator_actor_addr, verify_identity, result)
+    }
+
+    async fn query_light_state(
+        device: Device,
+        tapo_username: String,
+        tapo_password: String,
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        verify_identity: bool,
+    ) -> PollOutcome {
+        let span = tracing::Span::current();
+
+        let result = async {
+            let client = ApiClient::new(tapo_username, tapo_password);
+            let handler = client.generic_device(device.ip_address.clone()).await?;
+
+            let device_info = handler.get_device_info_json().await?;
+
+            if verify_identity && !Self::matches_identity(&device, device_info.clone())? {
+                return Ok(None);
+            }
+
+            let message = LightStateMessage {
+                span_context: span.context(),
+                device: device.clone(),
+                state: LightState::try_from(device_info)?,
+            };
+
+            Ok::<_, anyhow::Error>(Some((message, PollReading::default())))
+        }
+        .await;
+
+        Self::report_poll(device, coordinator_actor_addr, verify_identity, result)
+    }
+
+    async fn query_outlets(
+        device: Device,
+        tapo_username: String,
+        tapo_password: String,
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        verify_identity: bool,
+    ) -> PollOutcome {
+        let span = tracing::Span::current();
+
+        let result = async {
+            let client = ApiClient::new(tapo_username, tapo_password);
+            let handler = client.generic_device(device.ip_address.clone()).await?;
+
+            if verify_identity
+                && !Self::matches_identity(&device, handler.get_device_info_json().await?)?
+            {
+                return Ok(None);
+            }
+
+            let outlets = PowerStripHandler::from(handler)
+                .get_child_device_list()
+                .await?;
+
+            let message = OutletsStateMessage {
+                span_context: span.context(),
+                device: device.clone(),
+                outlets: outlets.into_iter().map(OutletState::from).collect(),
+            };
+
+            Ok::<_, anyhow::Error>(Some((message, PollReading::default())))
+        }
+        .await;
+
+        Self::report_poll(device, coordinator_actor_addr, verify_identity, result)
+    }
+
+    async fn query_sensors(
+        device: Device,
+        tapo_username: String,
+        tapo_password: String,
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        verify_identity: bool,
+        mut sensor_event_ids: HashMap<String, u64>,
+    ) -> PollOutcome {
+        let span = tracing::Span::current();
+
+        let result = async {
+            let client = ApiClient::new(tapo_username, tapo_password);
+            let handler = client.generic_device(device.ip_address.clone()).await?;
+
+            if verify_identity
+                && !Self::matches_identity(&device, handler.get_device_info_json().await?)?
+            {
+                return Ok(None);
+            }
 
-            Ok::<_, tapo::Error>(device_usage)
+            let hub = HubHandler::from(handler);
+            let sensors: Vec<_> = hub
+                .get_child_device_list()
+                .await?
+                .into_iter()
+                .filter_map(Sensor::from_child)
+                .collect();
+
+            let sensor_events =
+                Self::read_sensor_events(&device, &hub, &sensors, &mut sensor_event_ids).await;
+
+            let message = SensorsStateMessage {
+                span_context: span.context(),
+                device: device.clone(),
+                sensors,
+            };
+            let reading = PollReading {
+                sensor_events,
+                sensor_event_ids: Some(sensor_event_ids),
+                ..Default::default()
+            };
+
+            Ok::<_, anyhow::Error>(Some((message, reading)))
         }
         .await;
 
+        Self::report_poll(device, coordinator_actor_addr, verify_identity, result)
+    }
+
+    /// Sends what was read from the device to the coordinator, or reports the device as unreachable
+    /// when it couldn't be read. `None` is a device that doesn't match the configured identity.
+    fn report_poll<M>(
+        device: Device,
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        verify_identity: bool,
+        result: anyhow::Result<Option<(M, PollReading)>>,
+    ) -> PollOutcome
+    where
+        M: actix::Message + Send + 'static,
+        M::Result: Send,
+        CoordinatorActor: Handler<M>,
+    {
+        let span = tracing::Span::current();
+
         match result {
-            Ok(device_usage) => {
-                let result = coordinator_actor_addr.try_send(DeviceUsageMessage {
-                    span_context: span.context(),
-                    device,
-                    device_usage,
-                });
+            Ok(Some((message, reading))) => {
+                let result = coordinator_actor_addr.try_send(message);
 
                 if let Err(e) = result {
                     record_error(&span, &e);
                 }
+
+                PollOutcome::Success {
+                    identity_verified: verify_identity,
+                    reading,
+                }
+            }
+            Ok(None) => {
+                warn!(
+                    "Device at '{}' doesn't match the identity configured for '{}'",
+                    device.ip_address, device.name
+                );
+                PollOutcome::IdentityMismatch
             }
             Err(e) => {
-                error!(
-                    "Failed to query device usage for '{}': {:?}",
-                    device.name, e
+                error!("Failed to poll '{}': {:?}", device.name, e);
+                record_error(&span, &*e);
+
+                // the bound on `M` would otherwise be picked for this message as well
+                let result = coordinator_actor_addr.try_send::<DeviceUnreachableMessage>(
+                    DeviceUnreachableMessage {
+                        span_context: span.context(),
+                        device,
+                    },
                 );
-                record_error(&span, &e);
+
+                if let Err(e) = result {
+                    record_error(&span, &e);
+                }
+
+                PollOutcome::Failure
             }
         }
     }
-}
 
-impl Actor for DeviceActor {
-    type Context = Context<Self>;
+    /// Reads the trigger logs of the contact and motion sensors, keeping the events since the
+    /// previous read. A sensor whose log can't be read is caught up with on the next poll.
+    async fn read_sensor_events(
+        device: &Device,
+        hub: &HubHandler,
+        sensors: &[Sensor],
+        sensor_event_ids: &mut HashMap<String, u64>,
+    ) -> Vec<SensorEvent> {
+        let mut new_events = Vec::new();
+
+        for sensor in sensors {
+            let identifier = HubDevice::ByDeviceId(sensor.device_id.clone());
+
+            let events = match sensor.reading {
+                SensorReading::Contact { .. } => {
+                    async {
+                        let logs = hub
+                            .t110(identifier)
+                            .await?
+                            .get_trigger_logs(TRIGGER_LOG_PAGE_SIZE, 0)
+                            .await?;
+
+                        Ok::<_, tapo::Error>(
+                            logs.logs
+                                .into_iter()
+                                .map(|log| SensorEvent::from_t110(sensor, log))
+                                .collect(),
+                        )
+                    }
+                    .await
+                }
+                SensorReading::Motion { .. } => {
+                    async {
+                        let logs = hub
+                            .t100(identifier)
+                            .await?
+                            .get_trigger_logs(TRIGGER_LOG_PAGE_SIZE, 0)
+                            .await?;
+
+                        Ok(logs
+                            .logs
+                            .into_iter()
+                            .map(|log| SensorEvent::from_t100(sensor, log))
+                            .collect())
+                    }
+                    .await
+                }
+                SensorReading::Climate { .. } => continue,
+            };
+
+            let events = match events {
+                Ok(events) => events,
+                Err(e) => {
+                    warn!(
+                        "Failed to read the trigger log of '{}' on '{}': {e:?}",
+                        sensor.nickname, device.name
+                    );
+                    continue;
+                }
+            };
+
+            let last_event_id = sensor_event_ids.get(&sensor.device_id).copied();
+
+            let (events, newest_id) = unseen_events(events, last_event_id);
+
+            if events.len() as u64 >= TRIGGER_LOG_PAGE_SIZE {
+                warn!(
+                    "More than {TRIGGER_LOG_PAGE_SIZE} events of '{}' on '{}' since the last poll, the older ones are skipped",
+                    sensor.nickname, device.name
+                );
+            }
+
+            if let Some(newest_id) = newest_id {
+                sensor_event_ids.insert(sensor.device_id.clone(), newest_id);
+            }
+
+            new_events.extend(events);
+        }
+
+        new_events
+    }
+
+    /// Whether the device info read at the device's address belongs to the configured device.
+    fn matches_identity(device: &Device, device_info: serde_json::Value) -> anyhow::Result<bool> {
+        let info = DeviceInfo::try_from(device_info)?;
+
+        Ok(device.is_identified_by(&info.mac, &info.device_id))
+    }
+
+    async fn query_energy_data(
+        ip_address: String,
+        credentials: Credentials,
+        requests: Vec<EnergyRequest>,
+    ) -> Result<Vec<EnergyData>, tapo::Error> {
+        let client = ApiClient::new(credentials.username, credentials.password);
+        let handler = client.p110(ip_address).await?;
+
+        let mut energy_data = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            let result = handler.get_energy_data(request.into()).await?;
+            energy_data.push((request.interval, result).into());
+        }
+
+        Ok(energy_data)
+    }
+
+    /// Returns whether the device is on afterwards.
+    async fn switch(
+        ip_address: String,
+        credentials: Credentials,
+        action: DeviceAction,
+    ) -> Result<bool, tapo::Error> {
+        let client = ApiClient::new(credentials.username, credentials.password);
+        let handler = client.generic_device(ip_address).await?;
+
+        let device_on = match action {
+            DeviceAction::On => true,
+            DeviceAction::Off => false,
+            DeviceAction::Toggle => !handler.get_device_info().await?.device_on.unwrap_or(false),
+        };
+
+        if device_on {
+            handler.on().await?;
+        } else {
+            handler.off().await?;
+        }
+
+        Ok(device_on)
+    }
+
+    /// Returns the outlets once switched, `None` when there's no outlet at the position.
+    async fn switch_outlet(
+        ip_address: String,
+        credentials: Credentials,
+        position: u8,
+        action: DeviceAction,
+    ) -> Result<Option<Vec<OutletState>>, tapo::Error> {
+        let client = ApiClient::new(credentials.username, credentials.password);
+        let strip = PowerStripHandler::from(client.generic_device(ip_address).await?);
+
+        let outlets = strip.get_child_device_list().await?;
+        let Some(outlet) = outlets.iter().find(|outlet| outlet.position == position) else {
+            return Ok(None);
+        };
+
+        let device_on = match action {
+            DeviceAction::On => true,
+            DeviceAction::Off => false,
+            DeviceAction::Toggle => !outlet.device_on,
+        };
+
+        let plug = strip
+            .plug(Plug::ByDeviceId(outlet.device_id.clone()))
+            .await?;
+        if device_on {
+            plug.on().await?;
+        } else {
+            plug.off().await?;
+        }
+
+        let outlets = strip.get_child_device_list().await?;
+
+        Ok(Some(outlets.into_iter().map(OutletState::from).collect()))
+    }
+
+    /// Applies a scene state, setting the brightness and color turns the device on.
+    async fn apply(
+        ip_address: String,
+        credentials: Credentials,
+        state: SceneState,
+    ) -> Result<bool, tapo::Error> {
+        if !state.has_light_settings() {
+            return Self::switch(ip_address, credentials, state.action).await;
+        }
+
+        let client = ApiClient::new(credentials.username, credentials.password);
+        let handler = RgbicLightStripHandler::from(client.generic_device(ip_address).await?);
+
+        Self::apply_light(&handler, state.light()).await?;
+
+        Ok(true)
+    }
+
+    /// Applies the settings in a single request, which also turns the device on.
+    ///
+    /// The RGBIC light strip handler is used for every light since its requests are a superset
+    /// of the other lights', validation already rejects settings a device type doesn't support.
+    async fn apply_light(
+        handler: &RgbicLightStripHandler,
+        light: LightSettings,
+    ) -> Result<(), tapo::Error> {
+        if let Some(lighting_effect) = light.lighting_effect {
+            return handler.set_lighting_effect(lighting_effect).await;
+        }
+
+        let mut params = ColorLightSetDeviceInfoParams::new().on();
+        if let Some(brightness) = light.brightness {
+            params = params.brightness(brightness);
+        }
+        if let (Some(hue), Some(saturation)) = (light.hue, light.saturation) {
+            params = params.hue_saturation(hue, saturation);
+        }
+        if let Some(color_temperature) = light.color_temperature {
+            params = params.color_temperature(color_temperature);
+        }
+
+        params.send(handler).await
+    }
+
+    fn cancel_timer(&mut self, ctx: &mut Context<Self>) -> bool {
+        let Some(timer) = self.timer.take() else {
+            return false;
+        };
+
+        ctx.cancel_future(timer.handle);
+        true
+    }
+
+    fn credentials(&self) -> Option<Credentials> {
+        let credentials = self.config.credentials(self.device.credentials.as_deref());
+
+        if credentials.is_none() {
+            error!(
+                "Unknown credentials profile '{}' for '{}'",
+                self.device.credentials.as_deref().unwrap_or_default(),
+                self.device.name
+            );
+        }
+
+        credentials
+    }
+
+    fn refresh_rate(&self) -> Duration {
+        Duration::from_secs(
+            self.device
+                .refresh_rate_s
+                .unwrap_or(self.config.refresh_rate_s),
+        )
+    }
+
+    /// (Re)starts the polling loop, replacing the running one if any.
+    fn start_polling(&mut self, ctx: &mut Context<Self>, initial_delay: Duration) {
+        if let Some(poll_handle) = self.poll_handle.take() {
+            ctx.cancel_future(poll_handle);
+        }
 
-    #[instrument(name = "DeviceActor::started", skip_all, fields(
-        device.name = %self.device.name,
-        device.ip_address = %self.device.ip_address,
-    ))]
-    fn started(&mut self, ctx: &mut Self::Context) {
         let addr = ctx.address();
-        let refresh_rate = Duration::from_secs(self.config.refresh_rate_s);
+        let refresh_rate = self.refresh_rate();
 
         let device_name = self.device.name.clone();
-        let device_ip_address = self.device.ip_address.clone();
 
         let fut = async move {
+            sleep(initial_delay).await;
+
             let mut interval = interval(refresh_rate);
 
             loop {
@@ -106,7 +605,6 @@ impl Actor for DeviceActor {
                     messaging.operation.type = "send",
                     messaging.destination.name = "DeviceActor",
                     device.name = %device_name,
-                    device.ip_address = %device_ip_address,
                     otel.status_code = tracing::field::Empty,
                     exception.type = tracing::field::Empty,
                     exception.message = tracing::field::Empty,
@@ -123,8 +621,84 @@ impl Actor for DeviceActor {
         }
         .into_actor(self);
 
-        ctx.spawn(fut);
+        self.poll_handle = Some(ctx.spawn(fut));
+    }
+
+    fn start_energy_polling(&mut self, ctx: &mut Context<Self>, refresh_rate: Duration) {
+        let addr = ctx.address();
+        let device_name = self.device.name.clone();
+
+        let fut = async move {
+            let mut interval = interval(refresh_rate);
+
+            loop {
+                interval.tick().await;
+
+                let span = tracing::info_span!(
+                    "DeviceActor::EnergyIntervalTick",
+                    otel.kind = "producer",
+                    messaging.message.id = "FetchEnergyDataMessage",
+                    messaging.operation.name = "send",
+                    messaging.operation.type = "send",
+                    messaging.destination.name = "DeviceActor",
+                    device.name = %device_name,
+                    otel.status_code = tracing::field::Empty,
+                    exception.type = tracing::field::Empty,
+                    exception.message = tracing::field::Empty,
+                    exception.stacktrace = tracing::field::Empty,
+                );
+                let _enter = span.enter();
+
+                if let Err(e) = addr.try_send(FetchEnergyDataMessage {
+                    span_context: span.context(),
+                }) {
+                    record_error(&span, &e);
+                }
+            }
+        }
+        .into_actor(self);
+
+        self.energy_handle = Some(ctx.spawn(fut));
+    }
+
+    fn request_address_resolution(&self, span: &tracing::Span) {
+        let result = self
+            .coordinator_actor_addr
+            .try_send(ResolveDeviceAddressMessage {
+                span_context: span.context(),
+                device: self.device.clone(),
+            });
+
+        if let Err(e) = result {
+            record_error(span, &e);
+        }
+    }
+}
+
+impl Actor for DeviceActor {
+    type Context = Context<Self>;
+
+    #[instrument(name = "DeviceActor::started", skip_all, fields(
+        device.name = %self.device.name,
+        device.ip_address = %self.device.ip_address,
+    ))]
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let initial_delay = match self.config.poll_jitter_s {
+            0 => Duration::ZERO,
+            jitter_s => Duration::from_millis(rand::thread_rng().gen_range(0..jitter_s * 1000)),
+        };
+
+        self.start_polling(ctx, initial_delay);
+
+        // only plugs keep energy data
+        if let Some(energy) = &self.energy
+            && self.device.device_type == DeviceType::Plug
+        {
+            let refresh_rate = Duration::from_secs(energy.refresh_rate_s);
+            self.start_energy_polling(ctx, refresh_rate);
+        }
     }
+
     #[instrument(name = "DeviceActor::stopped", level = "error", skip_all, fields(
         device.name = %self.device.name,
         device.ip_address = %self.device.ip_address,
@@ -156,16 +730,905 @@ impl Handler<GetDeviceDataMessage> for DeviceActor {
         let span = tracing::Span::current();
         let _ = span.set_parent(message.span_context);
 
+        if self.in_flight.is_closed() {
+            // shutting down, don't start new polls
+            return;
+        }
+
+        let Some(credentials) = self.credentials() else {
+            return;
+        };
+
         let device = self.device.clone();
-        let tapo_username = self.config.username.clone();
-        let tapo_password = self.config.password.clone();
+        let tapo_username = credentials.username;
+        let tapo_password = credentials.password;
         let coordinator_actor_addr = self.coordinator_actor_addr.clone();
+        let verify_identity = self.device.has_identity() && !self.identity_verified;
+
+        let query: Pin<Box<dyn Future<Output = PollOutcome>>> = match self.device.device_type {
+            DeviceType::Plug => Box::pin(Self::query_device_usage(
+                device,
+                tapo_username,
+                tapo_password,
+                coordinator_actor_addr,
+                verify_identity,
+            )),
+            DeviceType::PowerStrip => Box::pin(Self::query_outlets(
+                device,
+                tapo_username,
+                tapo_password,
+                coordinator_actor_addr,
+                verify_identity,
+            )),
+            DeviceType::Hub => Box::pin(Self::query_sensors(
+                device,
+                tapo_username,
+                tapo_password,
+                coordinator_actor_addr,
+                verify_identity,
+                self.sensor_event_ids.clone(),
+            )),
+            _ => Box::pin(Self::query_light_state(
+                device,
+                tapo_username,
+                tapo_password,
+                coordinator_actor_addr,
+                verify_identity,
+            )),
+        };
 
-        let fut =
-            Self::query_device_usage(device, tapo_username, tapo_password, coordinator_actor_addr)
-                .instrument(span)
-                .into_actor(self);
+        let fut = self
+            .in_flight
+            .track_future(query)
+            .instrument(span.clone())
+            .into_actor(self)
+            .map(move |outcome, actor, _| match outcome {
+                PollOutcome::Success {
+                    identity_verified,
+                    reading,
+                } => {
+                    actor.consecutive_failures = 0;
+                    actor.identity_verified |= identity_verified;
+
+                    if let Some(cycle_detector) = &mut actor.cycle_detector
+                        && let Some(current_power) = reading.current_power
+                        && let Some(event) = cycle_detector.observe(current_power, Utc::now())
+                    {
+                        let result = actor.coordinator_actor_addr.try_send(CycleEventMessage {
+                            span_context: span.context(),
+                            device: actor.device.clone(),
+                            event,
+                        });
+
+                        if let Err(e) = result {
+                            record_error(&span, &e);
+                        }
+                    }
+
+                    if let Some(sensor_event_ids) = reading.sensor_event_ids {
+                        actor.sensor_event_ids = sensor_event_ids;
+                    }
+
+                    if !reading.sensor_events.is_empty() {
+                        let result = actor.coordinator_actor_addr.try_send(SensorEventsMessage {
+                            span_context: span.context(),
+                            device: actor.device.clone(),
+                            events: reading.sensor_events,
+                        });
+
+                        if let Err(e) = result {
+                            record_error(&span, &e);
+                        }
+                    }
+                }
+                PollOutcome::Failure => {
+                    actor.consecutive_failures += 1;
+
+                    if actor.device.has_identity()
+                        && actor.consecutive_failures % FAILURES_BEFORE_RESOLVE == 0
+                    {
+                        actor.request_address_resolution(&span);
+                    }
+                }
+                PollOutcome::IdentityMismatch => {
+                    actor.request_address_resolution(&span);
+                }
+            });
 
         ctx.spawn(fut);
     }
 }
+
+impl Handler<DeviceAddressChangedMessage> for DeviceActor {
+    type Result = ();
+
+    #[instrument(
+        name = "DeviceActor::Handler<DeviceAddressChangedMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceAddressChangedMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %message.ip_address,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: DeviceAddressChangedMessage,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        info!(
+            "Device '{}' moved from '{}' to '{}'",
+            self.device.name, self.device.ip_address, message.ip_address
+        );
+
+        self.device.ip_address = message.ip_address;
+        self.identity_verified = false;
+        self.consecutive_failures = 0;
+    }
+}
+
+impl Handler<SetRefreshRateMessage> for DeviceActor {
+    type Result = bool;
+
+    #[instrument(
+        name = "DeviceActor::Handler<SetRefreshRateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetRefreshRateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            device.refresh_rate_s = message.refresh_rate_s,
+        )
+    )]
+    fn handle(&mut self, message: SetRefreshRateMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        self.device.refresh_rate_s = Some(message.refresh_rate_s);
+        self.start_polling(ctx, Duration::ZERO);
+
+        true
+    }
+}
+
+impl Handler<ShutdownMessage> for DeviceActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        for handle in [self.poll_handle.take(), self.energy_handle.take()]
+            .into_iter()
+            .flatten()
+        {
+            ctx.cancel_future(handle);
+        }
+
+        self.in_flight.close();
+        let in_flight = self.in_flight.clone();
+
+        let fut = async move { in_flight.wait().await }
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<FetchEnergyDataMessage> for DeviceActor {
+    type Result = ();
+
+    #[instrument(
+        name = "DeviceActor::Handler<FetchEnergyDataMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "FetchEnergyDataMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: FetchEnergyDataMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            return;
+        }
+
+        let Some(credentials) = self.credentials() else {
+            return;
+        };
+
+        let today = Local::now().date_naive();
+        let requests = [
+            EnergyInterval::Hourly,
+            EnergyInterval::Daily,
+            EnergyInterval::Monthly,
+        ]
+        .into_iter()
+        .map(|interval| EnergyRequest::current(interval, today))
+        .collect();
+
+        let device = self.device.clone();
+        let coordinator_actor_addr = self.coordinator_actor_addr.clone();
+
+        let fut = async move {
+            let span = tracing::Span::current();
+
+            let result =
+                Self::query_energy_data(device.ip_address.clone(), credentials, requests).await;
+
+            match result {
+                Ok(energy_data) => {
+                    for energy_data in energy_data {
+                        let result = coordinator_actor_addr.try_send(EnergyDataMessage {
+                            span_context: span.context(),
+                            device: device.clone(),
+                            energy_data,
+                            backfilled: false,
+                        });
+
+                        if let Err(e) = result {
+                            record_error(&span, &e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to query energy data for '{}': {:?}", device.name, e);
+                    record_error(&span, &e);
+                }
+            }
+        };
+
+        let fut = self
+            .in_flight
+            .track_future(fut)
+            .instrument(span)
+            .into_actor(self);
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<GetEnergyDataMessage> for DeviceActor {
+    type Result = ResponseFuture<Option<anyhow::Result<EnergyData>>>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<GetEnergyDataMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetEnergyDataMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: GetEnergyDataMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let credentials = self.credentials();
+        let ip_address = self.device.ip_address.clone();
+        let request = message.request;
+
+        let query = async move {
+            let credentials = credentials.context("unknown credentials profile")?;
+
+            let energy_data = Self::query_energy_data(ip_address, credentials, vec![request])
+                .await
+                .inspect_err(|e| record_error(&tracing::Span::current(), e))?
+                .pop()
+                .context("the device returned no energy data")?;
+
+            Ok(energy_data)
+        }
+        .instrument(span);
+
+        let fut = async move { Some(query.await) };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<BackfillEnergyDataMessage> for DeviceActor {
+    type Result = ();
+
+    #[instrument(
+        name = "DeviceActor::Handler<BackfillEnergyDataMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "BackfillEnergyDataMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: BackfillEnergyDataMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let Some(energy) = &self.energy else {
+            return;
+        };
+
+        if self.in_flight.is_closed() {
+            return;
+        }
+
+        let today = Local::now().date_naive();
+        let oldest = today - TimeDelta::days(i64::try_from(energy.backfill_days).unwrap_or(0));
+        let oldest_date_time = Local
+            .from_local_datetime(&oldest.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .earliest()
+            .map(|oldest| oldest.to_utc())
+            .unwrap_or_default();
+
+        let since = message.since;
+        let requests: Vec<_> = since
+            .iter()
+            .flat_map(|(interval, since)| {
+                let since = since.with_timezone(&Local).date_naive();
+                EnergyRequest::backfill(*interval, since, oldest, today)
+            })
+            .collect();
+
+        if requests.is_empty() {
+            return;
+        }
+
+        let Some(credentials) = self.credentials() else {
+            return;
+        };
+
+        info!(
+            "Backfilling {} energy breakdown(s) of '{}'",
+            requests.len(),
+            self.device.name
+        );
+
+        let device = self.device.clone();
+        let coordinator_actor_addr = self.coordinator_actor_addr.clone();
+
+        let fut = async move {
+            let span = tracing::Span::current();
+
+            let result =
+                Self::query_energy_data(device.ip_address.clone(), credentials, requests).await;
+
+            let energy_data = match result {
+                Ok(energy_data) => energy_data,
+                Err(e) => {
+                    error!(
+                        "Failed to backfill energy data for '{}': {:?}",
+                        device.name, e
+                    );
+                    record_error(&span, &e);
+                    return;
+                }
+            };
+
+            for mut energy_data in energy_data {
+                // the last published interval is published again, it may have been incomplete
+                let Some(since) = since.get(&energy_data.interval) else {
+                    continue;
+                };
+                energy_data.entries.retain(|entry| {
+                    entry.start_date_time >= *since && entry.start_date_time >= oldest_date_time
+                });
+
+                if energy_data.entries.is_empty() {
+                    continue;
+                }
+
+                let result = coordinator_actor_addr.try_send(EnergyDataMessage {
+                    span_context: span.context(),
+                    device: device.clone(),
+                    energy_data,
+                    backfilled: true,
+                });
+
+                if let Err(e) = result {
+                    record_error(&span, &e);
+                }
+            }
+        };
+
+        let fut = self
+            .in_flight
+            .track_future(fut)
+            .instrument(span)
+            .into_actor(self);
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<SetDevicePowerMessage> for DeviceActor {
+    type Result = ResponseFuture<Option<anyhow::Result<bool>>>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<SetDevicePowerMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetDevicePowerMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SetDevicePowerMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let credentials = self.credentials();
+        let ip_address = self.device.ip_address.clone();
+        let action = message.action;
+
+        let switch = async move {
+            let credentials = credentials.context("unknown credentials profile")?;
+
+            let device_on = Self::switch(ip_address, credentials, action)
+                .await
+                .inspect_err(|e| record_error(&tracing::Span::current(), e))?;
+
+            Ok(device_on)
+        }
+        .instrument(span);
+
+        let fut = async move { Some(switch.await) };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SetDeviceStateMessage> for DeviceActor {
+    type Result = ResponseFuture<Option<anyhow::Result<bool>>>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<SetDeviceStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetDeviceStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SetDeviceStateMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let credentials = self.credentials();
+        let ip_address = self.device.ip_address.clone();
+        let state = message.state;
+
+        let apply = async move {
+            let credentials = credentials.context("unknown credentials profile")?;
+
+            let device_on = Self::apply(ip_address, credentials, state)
+                .await
+                .inspect_err(|e| record_error(&tracing::Span::current(), e))?;
+
+            Ok(device_on)
+        }
+        .instrument(span);
+
+        let fut = async move { Some(apply.await) };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<GetDeviceStatusMessage> for DeviceActor {
+    type Result = ResponseFuture<Option<anyhow::Result<DeviceStatus>>>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<GetDeviceStatusMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDeviceStatusMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: GetDeviceStatusMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let credentials = self.credentials();
+        let name = self.device.name.clone();
+        let ip_address = self.device.ip_address.clone();
+        let timer = self
+            .timer
+            .as_ref()
+            .map(|timer| TimerStatus::new(timer.action, timer.fires_at, Utc::now()));
+
+        let status = async move {
+            let credentials = credentials.context("unknown credentials profile")?;
+
+            let info = async {
+                let client = ApiClient::new(credentials.username, credentials.password);
+                let handler = client.generic_device(ip_address).await?;
+
+                DeviceInfo::read(&handler).await
+            }
+            .await
+            .inspect_err(|e| record_error(&tracing::Span::current(), &**e))?;
+
+            Ok(DeviceStatus { name, info, timer })
+        }
+        .instrument(span);
+
+        let fut = async move { Some(status.await) };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SetDeviceTimerMessage> for DeviceActor {
+    type Result = Option<TimerStatus>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<SetDeviceTimerMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetDeviceTimerMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            device.timer_s = message.duration_s,
+        )
+    )]
+    fn handle(&mut self, message: SetDeviceTimerMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        self.cancel_timer(ctx);
+
+        let now = Utc::now();
+        let fires_at = now + TimeDelta::seconds(message.duration_s as i64);
+        let device_name = self.device.name.clone();
+
+        let handle = ctx.run_later(Duration::from_secs(message.duration_s), move |_, ctx| {
+            let span = tracing::info_span!(
+                "DeviceActor::TimerElapsed",
+                otel.kind = "producer",
+                messaging.message.id = "DeviceTimerElapsedMessage",
+                messaging.operation.name = "send",
+                messaging.operation.type = "send",
+                messaging.destination.name = "DeviceActor",
+                device.name = %device_name,
+                otel.status_code = tracing::field::Empty,
+                exception.type = tracing::field::Empty,
+                exception.message = tracing::field::Empty,
+                exception.stacktrace = tracing::field::Empty,
+            );
+            let _enter = span.enter();
+
+            if let Err(e) = ctx.address().try_send(DeviceTimerElapsedMessage {
+                span_context: span.context(),
+            }) {
+                record_error(&span, &e);
+            }
+        });
+
+        self.timer = Some(DeviceTimer {
+            action: message.action,
+            fires_at,
+            handle,
+        });
+
+        Some(TimerStatus::new(message.action, fires_at, now))
+    }
+}
+
+impl Handler<CancelDeviceTimerMessage> for DeviceActor {
+    type Result = Option<bool>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<CancelDeviceTimerMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "CancelDeviceTimerMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: CancelDeviceTimerMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        Some(self.cancel_timer(ctx))
+    }
+}
+
+impl Handler<DeviceTimerElapsedMessage> for DeviceActor {
+    type Result = ();
+
+    #[instrument(
+        name = "DeviceActor::Handler<DeviceTimerElapsedMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceTimerElapsedMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: DeviceTimerElapsedMessage,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let Some(timer) = self.timer.take() else {
+            return;
+        };
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the timer, the Device Actor is shutting down");
+            return;
+        }
+
+        let Some(credentials) = self.credentials() else {
+            return;
+        };
+
+        info!("Timer of '{}' elapsed", self.device.name);
+
+        let ip_address = self.device.ip_address.clone();
+        let device_name = self.device.name.clone();
+        let coordinator_actor_addr = self.coordinator_actor_addr.clone();
+
+        let fut = self
+            .in_flight
+            .track_future(async move {
+                let span = tracing::Span::current();
+
+                let result = Self::switch(ip_address, credentials, timer.action).await;
+                if let Err(e) = &result {
+                    record_error(&span, e);
+                }
+
+                if let Err(e) = coordinator_actor_addr.try_send(DeviceEventMessage {
+                    span_context: span.context(),
+                    event: DeviceEvent::command(
+                        &device_name,
+                        DeviceCommand::Timer {
+                            action: timer.action,
+                        },
+                        result.map_err(|e| e.to_string()),
+                    ),
+                }) {
+                    record_error(&span, &e);
+                }
+            })
+            .instrument(span)
+            .into_actor(self);
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<SetLightMessage> for DeviceActor {
+    type Result = ResponseFuture<Option<Result<LightState, LightError>>>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<SetLightMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetLightMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SetLightMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let credentials = self.credentials();
+        let device = self.device.clone();
+        let coordinator_actor_addr = self.coordinator_actor_addr.clone();
+        let light = message.light;
+
+        let set = async move {
+            let credentials = credentials.context("unknown credentials profile")?;
+
+            let state = async {
+                let client = ApiClient::new(credentials.username, credentials.password);
+                let handler = RgbicLightStripHandler::from(
+                    client.generic_device(device.ip_address.clone()).await?,
+                );
+
+                Self::apply_light(&handler, light).await?;
+
+                LightState::try_from(handler.get_device_info_json().await?)
+            }
+            .await
+            .inspect_err(|e| record_error(&tracing::Span::current(), &**e))?;
+
+            // publish the change right away instead of on the next poll
+            if let Err(e) = coordinator_actor_addr.try_send(LightStateMessage {
+                span_context: tracing::Span::current().context(),
+                device,
+                state: state.clone(),
+            }) {
+                record_error(&tracing::Span::current(), &e);
+            }
+
+            Ok(state)
+        }
+        .instrument(span);
+
+        let fut = async move { Some(set.await.map_err(LightError::Failed)) };
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<SetOutletMessage> for DeviceActor {
+    type Result = ResponseFuture<Option<Result<OutletState, OutletError>>>;
+
+    #[instrument(
+        name = "DeviceActor::Handler<SetOutletMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetOutletMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DeviceActor",
+            device.name = %self.device.name,
+            device.ip_address = %self.device.ip_address,
+            device.outlet = message.position,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SetOutletMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let credentials = self.credentials();
+        let device = self.device.clone();
+        let coordinator_actor_addr = self.coordinator_actor_addr.clone();
+        let SetOutletMessage {
+            position, action, ..
+        } = message;
+
+        let set = async move {
+            let credentials = credentials
+                .context("unknown credentials profile")
+                .map_err(OutletError::Failed)?;
+
+            let outlets =
+                Self::switch_outlet(device.ip_address.clone(), credentials, position, action)
+                    .await
+                    .inspect_err(|e| record_error(&tracing::Span::current(), e))
+                    .map_err(|e| OutletError::Failed(e.into()))?
+                    .ok_or(OutletError::NotFound)?;
+
+            let outlet = outlets
+                .iter()
+                .find(|outlet| outlet.position == position)
+                .cloned()
+                .ok_or(OutletError::NotFound)?;
+
+            // publish the change right away instead of on the next poll
+            if let Err(e) = coordinator_actor_addr.try_send(OutletsStateMessage {
+                span_context: tracing::Span::current().context(),
+                device,
+                outlets,
+            }) {
+                record_error(&tracing::Span::current(), &e);
+            }
+
+            Ok(outlet)
+        }
+        .instrument(span);
+
+        let fut = async move { Some(set.await) };
+
+        Box::pin(fut)
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::Context as _;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use tapo::GenericDeviceHandler;
+use tapo::responses::PowerStripPlugResult;
+
+use crate::settings::{Device, DeviceAction, DeviceType, ValidationErrors};
+use crate::system::sensors::Sensor;
+
+/// A countdown that switches a device once it elapses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimerStatus {
+    pub action: DeviceAction,
+    pub fires_at: DateTime<Utc>,
+    pub remaining_s: u64,
+}
+
+impl TimerStatus {
+    pub fn new(action: DeviceAction, fires_at: DateTime<Utc>, now: DateTime<Utc>) -> Self {
+        Self {
+            action,
+            fires_at,
+            remaining_s: (fires_at - now).num_seconds().max(0) as u64,
+        }
+    }
+}
+
+/// What a device reports about itself, enough for basic diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub device_id: String,
+    pub device_type: String,
+    pub model: String,
+    pub firmware_version: String,
+    pub hardware_version: String,
+    pub nickname: String,
+    pub mac: String,
+    pub ip_address: String,
+    pub ssid: String,
+    /// In dBm.
+    pub rssi: i16,
+    /// From 0 (no signal) to 3 (good signal).
+    pub signal_level: u8,
+    /// `None` for devices that can't be turned on or off, e.g. hubs.
+    pub device_on: Option<bool>,
+    /// How long the device has been on since it was last switched.
+    pub on_time_s: Option<u64>,
+    pub overheated: bool,
+}
+
+/// The device info as the devices send it. Plugs report an `overheat_status`, bulbs and light
+/// strips an `overheated` flag.
+#[derive(Deserialize)]
+struct RawDeviceInfo {
+    device_id: String,
+    #[serde(rename = "type")]
+    device_type: String,
+    model: String,
+    fw_ver: String,
+    hw_ver: String,
+    nickname: String,
+    mac: String,
+    ip: String,
+    ssid: String,
+    rssi: i16,
+    signal_level: u8,
+    device_on: Option<bool>,
+    on_time: Option<u64>,
+    #[serde(default)]
+    overheated: bool,
+    overheat_status: Option<String>,
+}
+
+impl DeviceInfo {
+    pub async fn read(handler: &GenericDeviceHandler) -> anyhow::Result<Self> {
+        let device_info = handler.get_device_info_json().await?;
+
+        Self::try_from(device_info)
+    }
+}
+
+impl TryFrom<serde_json::Value> for DeviceInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let raw: RawDeviceInfo = serde_json::from_value(value).context("unexpected device info")?;
+
+        Ok(Self {
+            device_id: raw.device_id,
+            device_type: raw.device_type,
+            model: raw.model,
+            firmware_version: raw.fw_ver,
+            hardware_version: raw.hw_ver,
+            nickname: decode(&raw.nickname).context("invalid nickname")?,
+            mac: raw.mac,
+            ip_address: raw.ip,
+            ssid: decode(&raw.ssid).context("invalid ssid")?,
+            rssi: raw.rssi,
+            signal_level: raw.signal_level,
+            device_on: raw.device_on,
+            on_time_s: raw.on_time,
+            overheated: raw.overheated
+                || raw
+                    .overheat_status
+                    .is_some_and(|overheat_status| overheat_status != "normal"),
+        })
+    }
+}
+
+/// The devices send the nickname and the SSID base64 encoded.
+fn decode(value: &str) -> anyhow::Result<String> {
+    let bytes = BASE64_STANDARD.decode(value)?;
+
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceStatus {
+    pub name: String,
+    #[serde(flatten)]
+    pub info: DeviceInfo,
+    pub timer: Option<TimerStatus>,
+}
+
+/// What a bulb or light strip is showing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightState {
+    pub device_on: bool,
+    pub brightness: Option<u8>,
+    pub hue: Option<u16>,
+    pub saturation: Option<u8>,
+    /// 0 while showing a hue and saturation.
+    pub color_temperature: Option<u16>,
+    /// Name of the lighting effect that's running, if any.
+    pub lighting_effect: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawLightState {
+    device_on: bool,
+    brightness: Option<u8>,
+    hue: Option<u16>,
+    saturation: Option<u16>,
+    color_temp: Option<u16>,
+    lighting_effect: Option<RawLightingEffect>,
+}
+
+#[derive(Deserialize)]
+struct RawLightingEffect {
+    enable: u8,
+    name: String,
+}
+
+impl TryFrom<serde_json::Value> for LightState {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let raw: RawLightState = serde_json::from_value(value).context("unexpected light state")?;
+
+        Ok(Self {
+            device_on: raw.device_on,
+            brightness: raw.brightness,
+            hue: raw.hue,
+            saturation: raw
+                .saturation
+                .and_then(|saturation| u8::try_from(saturation).ok()),
+            color_temperature: raw.color_temp,
+            lighting_effect: raw
+                .lighting_effect
+                .filter(|lighting_effect| lighting_effect.enable != 0)
+                .map(|lighting_effect| lighting_effect.name),
+        })
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum LightError {
+    /// The settings don't pass validation or aren't supported by the device.
+    #[display("{}", _0.errors.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Invalid(ValidationErrors),
+    #[display("{_0}")]
+    Failed(anyhow::Error),
+}
+
+/// State of an outlet of a power strip.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutletState {
+    /// Position on the strip, starting at 1.
+    pub position: u8,
+    pub device_id: String,
+    pub nickname: String,
+    pub device_on: bool,
+    pub on_time_s: u64,
+}
+
+impl From<PowerStripPlugResult> for OutletState {
+    fn from(outlet: PowerStripPlugResult) -> Self {
+        Self {
+            position: outlet.position,
+            device_id: outlet.device_id,
+            nickname: outlet.nickname,
+            device_on: outlet.device_on,
+            on_time_s: outlet.on_time,
+        }
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum OutletError {
+    #[display("no outlet at this position")]
+    NotFound,
+    #[display("{_0}")]
+    Failed(anyhow::Error),
+}
+
+/// A configured device, with the outlets last reported by power strips and the sensors last
+/// reported by hubs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSummary {
+    pub name: String,
+    pub device_type: DeviceType,
+    pub ip_address: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outlets: Vec<OutletState>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sensors: Vec<Sensor>,
+}
+
+impl DeviceSummary {
+    pub fn new(device: &Device, outlets: Vec<OutletState>, sensors: Vec<Sensor>) -> Self {
+        Self {
+            name: device.name.clone(),
+            device_type: device.device_type,
+            ip_address: device.ip_address.clone(),
+            outlets,
+            sensors,
+        }
+    }
+}
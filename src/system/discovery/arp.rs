@@ -0,0 +1,33 @@
+use std::fs;
+
+use crate::settings::normalize_mac;
+
+const ARP_TABLE_PATH: &str = "/proc/net/arp";
+
+// entries with this flag value haven't been resolved yet
+const INCOMPLETE_ENTRY_FLAGS: &str = "0x0";
+
+/// Looks up the IP address of a MAC address in the kernel's ARP table.
+pub fn lookup(mac: &str) -> Option<String> {
+    let table = fs::read_to_string(ARP_TABLE_PATH).ok()?;
+
+    find_ip_address(&table, mac)
+}
+
+/// Finds the IP address of a MAC address in the contents of `/proc/net/arp`.
+pub fn find_ip_address(table: &str, mac: &str) -> Option<String> {
+    let mac = normalize_mac(mac);
+
+    table.lines().skip(1).find_map(|line| {
+        let columns: Vec<_> = line.split_whitespace().collect();
+
+        match columns.as_slice() {
+            [ip_address, _, flags, hw_address, ..]
+                if *flags != INCOMPLETE_ENTRY_FLAGS && normalize_mac(hw_address) == mac =>
+            {
+                Some(ip_address.to_string())
+            }
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::clock::interval;
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, MessageResult,
+    ResponseActFuture, WrapFuture,
+};
+use tracing::{Instrument, error, info, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::settings::Discovery;
+use crate::system::coordinator_actor::CoordinatorActor;
+use crate::system::discovery::protocol::{self, DiscoveredDevice};
+use crate::system::messages::{
+    DeviceDiscoveredMessage, FindDeviceMessage, GetDiscoveredDevicesMessage, RunDiscoveryMessage,
+    ShutdownMessage,
+};
+use crate::telemetry::record_error;
+
+#[derive(Debug)]
+pub struct DiscoveryActor {
+    coordinator_actor_addr: Addr<CoordinatorActor>,
+    config: Discovery,
+    probe: Vec<u8>,
+    devices: HashMap<String, DiscoveredDevice>,
+}
+
+impl DiscoveryActor {
+    #[instrument(name = "DiscoveryActor::new", skip_all, fields(
+        otel.status_code = tracing::field::Empty,
+        exception.type = tracing::field::Empty,
+        exception.message = tracing::field::Empty,
+        exception.stacktrace = tracing::field::Empty,
+    ))]
+    pub fn new(
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        config: Discovery,
+    ) -> anyhow::Result<Self> {
+        let span = tracing::Span::current();
+
+        let probe = protocol::build_probe().inspect_err(|e| {
+            record_error(&span, &**e);
+        })?;
+
+        Ok(Self {
+            coordinator_actor_addr,
+            config,
+            probe,
+            devices: HashMap::new(),
+        })
+    }
+}
+
+impl Actor for DiscoveryActor {
+    type Context = Context<Self>;
+
+    #[instrument(name = "DiscoveryActor::started", skip_all)]
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let addr = ctx.address();
+        let refresh_rate = Duration::from_secs(self.config.interval_s);
+
+        let fut = async move {
+            let mut interval = interval(refresh_rate);
+
+            loop {
+                interval.tick().await;
+
+                let span = tracing::info_span!(
+                    "DiscoveryActor::IntervalTick",
+                    otel.kind = "producer",
+                    messaging.message.id = "RunDiscoveryMessage",
+                    messaging.operation.name = "send",
+                    messaging.operation.type = "send",
+                    messaging.destination.name = "DiscoveryActor",
+                    otel.status_code = tracing::field::Empty,
+                    exception.type = tracing::field::Empty,
+                    exception.message = tracing::field::Empty,
+                    exception.stacktrace = tracing::field::Empty,
+                );
+                let _enter = span.enter();
+
+                if let Err(e) = addr.try_send(RunDiscoveryMessage {
+                    span_context: span.context(),
+                }) {
+                    record_error(&span, &e);
+                }
+            }
+        }
+        .into_actor(self);
+
+        ctx.spawn(fut);
+    }
+
+    #[instrument(name = "DiscoveryActor::stopped", level = "error", skip_all)]
+    fn stopped(&mut self, _: &mut Self::Context) {}
+}
+
+impl Handler<RunDiscoveryMessage> for DiscoveryActor {
+    type Result = ();
+
+    #[instrument(
+        name = "DiscoveryActor::Handler<RunDiscoveryMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "RunDiscoveryMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DiscoveryActor",
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: RunDiscoveryMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let probe = self.probe.clone();
+        let targets = self.config.targets.clone();
+        let port = self.config.port;
+        let timeout = Duration::from_secs(self.config.timeout_s);
+
+        let fut = async move { protocol::discover(&probe, &targets, port, timeout).await }
+            .instrument(span.clone())
+            .into_actor(self)
+            .map(move |result, actor, _| {
+                let _enter = span.enter();
+
+                let devices = match result {
+                    Ok(devices) => devices,
+                    Err(e) => {
+                        error!("Failed to discover devices: {e:?}");
+                        record_error(&span, &*e);
+                        return;
+                    }
+                };
+
+                info!("Discovered {} device(s)", devices.len());
+
+                for device in devices {
+                    if actor.config.auto_register {
+                        let result =
+                            actor
+                                .coordinator_actor_addr
+                                .try_send(DeviceDiscoveredMessage {
+                                    span_context: span.context(),
+                                    device: device.clone(),
+                                });
+
+                        if let Err(e) = result {
+                            record_error(&span, &e);
+                        }
+                    }
+
+                    actor.devices.insert(device.mac.clone(), device);
+                }
+            });
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<GetDiscoveredDevicesMessage> for DiscoveryActor {
+    type Result = MessageResult<GetDiscoveredDevicesMessage>;
+
+    #[instrument(
+        name = "DiscoveryActor::Handler<GetDiscoveredDevicesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDiscoveredDevicesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DiscoveryActor",
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: GetDiscoveredDevicesMessage,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let mut devices: Vec<_> = self.devices.values().cloned().collect();
+        devices.sort_by(|a, b| a.ip_address.cmp(&b.ip_address));
+
+        MessageResult(Some(devices))
+    }
+}
+
+impl Handler<FindDeviceMessage> for DiscoveryActor {
+    type Result = ResponseActFuture<Self, Option<DiscoveredDevice>>;
+
+    #[instrument(
+        name = "DiscoveryActor::Handler<FindDeviceMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "FindDeviceMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DiscoveryActor",
+            device.name = %message.device.name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: FindDeviceMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let device = message.device;
+
+        let probe = self.probe.clone();
+        let targets = self.config.targets.clone();
+        let port = self.config.port;
+        let timeout = Duration::from_secs(self.config.timeout_s);
+
+        let fut = async move { protocol::discover(&probe, &targets, port, timeout).await }
+            .instrument(span.clone())
+            .into_actor(self)
+            .map(move |result, actor, _| {
+                let _enter = span.enter();
+
+                let devices = result
+                    .inspect_err(|e| {
+                        error!("Failed to discover devices: {e:?}");
+                        record_error(&span, &**e);
+                    })
+                    .ok()?;
+
+                for discovered in &devices {
+                    actor
+                        .devices
+                        .insert(discovered.mac.clone(), discovered.clone());
+                }
+
+                devices.into_iter().find(|discovered| {
+                    device.has_identity()
+                        && device.is_identified_by(&discovered.mac, &discovered.device_id)
+                })
+            });
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<ShutdownMessage> for DiscoveryActor {
+    type Result = ();
+
+    #[instrument(
+        name = "DiscoveryActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "DiscoveryActor",
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        // a discovery that is still running has nothing worth waiting for
+        ctx.stop();
+    }
+}
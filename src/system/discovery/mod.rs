@@ -0,0 +1,3 @@
+pub mod arp;
+pub mod discovery_actor;
+pub mod protocol;
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::Context as _;
+use crc32fast::Hasher;
+use rsa::pkcs1::{EncodeRsaPublicKey, LineEnding};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::net::UdpSocket;
+use tracing::{debug, instrument, warn};
+
+use crate::settings::normalize_mac;
+
+/// UDP port on which Tapo devices listen for discovery probes.
+pub const DISCOVERY_PORT: u16 = 20002;
+
+const HEADER_LEN: usize = 16;
+const INITIAL_CRC: u32 = 0x5A6B7C8D;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscoveredDevice {
+    pub device_id: String,
+    pub device_type: String,
+    pub device_model: String,
+    pub ip_address: String,
+    pub mac: String,
+}
+
+impl DiscoveredDevice {
+    /// Whether the device is a plug that reports energy usage, i.e. one that a `DeviceActor` can poll.
+    pub fn is_energy_monitoring_plug(&self) -> bool {
+        ["P110", "P115"]
+            .iter()
+            .any(|model| self.device_model.starts_with(model))
+    }
+}
+
+#[derive(Deserialize)]
+struct DiscoveryResponse {
+    result: DiscoveryResponseResult,
+}
+
+#[derive(Deserialize)]
+struct DiscoveryResponseResult {
+    device_id: String,
+    device_type: String,
+    device_model: String,
+    #[serde(default)]
+    ip: String,
+    mac: String,
+}
+
+/// Builds the discovery probe: a 16 byte header followed by a JSON payload carrying an RSA public key.
+pub fn build_probe() -> anyhow::Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+
+    let private_key =
+        RsaPrivateKey::new(&mut rng, 1024).context("failed to generate the RSA key")?;
+    let public_key = RsaPublicKey::from(&private_key)
+        .to_pkcs1_pem(LineEnding::LF)
+        .context("failed to encode the RSA key")?;
+
+    let payload = serde_json::to_vec(&json!({
+        "params": {
+            "rsa_key": public_key,
+        }
+    }))?;
+
+    let version = 2u8;
+    let msg_type = 0u8;
+    let op_code = 1u16;
+    let msg_size = u16::try_from(payload.len()).context("probe payload too large")?;
+    let flags = 17u8;
+    let padding = 0u8;
+    let serial = rand::random::<u32>();
+
+    let mut probe = Vec::with_capacity(HEADER_LEN + payload.len());
+    probe.push(version);
+    probe.push(msg_type);
+    probe.extend_from_slice(&op_code.to_be_bytes());
+    probe.extend_from_slice(&msg_size.to_be_bytes());
+    probe.push(flags);
+    probe.push(padding);
+    probe.extend_from_slice(&serial.to_be_bytes());
+    probe.extend_from_slice(&INITIAL_CRC.to_be_bytes());
+    probe.extend_from_slice(&payload);
+
+    let mut hasher = Hasher::new();
+    hasher.update(&probe);
+    probe[12..HEADER_LEN].copy_from_slice(&hasher.finalize().to_be_bytes());
+
+    Ok(probe)
+}
+
+/// Decodes a discovery response.
+/// `source` is used as the IP address when the device doesn't report one itself.
+pub fn decode_response(buf: &[u8], source: IpAddr) -> anyhow::Result<DiscoveredDevice> {
+    if buf.len() <= HEADER_LEN {
+        anyhow::bail!("response too short ({} bytes)", buf.len());
+    }
+
+    let response: DiscoveryResponse =
+        serde_json::from_slice(&buf[HEADER_LEN..]).context("failed to decode the response")?;
+    let result = response.result;
+
+    Ok(DiscoveredDevice {
+        device_id: result.device_id,
+        device_type: result.device_type,
+        device_model: result.device_model,
+        ip_address: if result.ip.is_empty() {
+            source.to_string()
+        } else {
+            result.ip
+        },
+        mac: normalize_mac(&result.mac),
+    })
+}
+
+/// Resolves a target into the address the probe is sent to.
+/// Subnets in CIDR notation (e.g. `192.168.1.0/24`) resolve to their broadcast address.
+pub fn resolve_target(target: &str) -> anyhow::Result<IpAddr> {
+    let Some((network, prefix)) = target.split_once('/') else {
+        return target
+            .parse()
+            .with_context(|| format!("invalid discovery target '{target}'"));
+    };
+
+    let network: Ipv4Addr = network
+        .parse()
+        .with_context(|| format!("invalid discovery subnet '{target}'"))?;
+    let prefix: u32 = prefix
+        .parse()
+        .ok()
+        .filter(|prefix| *prefix <= 32)
+        .with_context(|| format!("invalid discovery subnet prefix '{target}'"))?;
+
+    let host_mask = u32::MAX.checked_shr(prefix).unwrap_or(0);
+
+    Ok(IpAddr::V4(Ipv4Addr::from(u32::from(network) | host_mask)))
+}
+
+/// Sends the probe to every target and collects the responses received within `timeout`.
+#[instrument(name = "discovery::discover", skip(probe))]
+pub async fn discover(
+    probe: &[u8],
+    targets: &[String],
+    port: u16,
+    timeout: Duration,
+) -> anyhow::Result<Vec<DiscoveredDevice>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+
+    let mut sent = 0;
+
+    // a target that can't be reached, e.g. the subnet of an interface that's down, doesn't keep
+    // the others from being discovered
+    for target in targets {
+        let address = match resolve_target(target) {
+            Ok(ip_address) => SocketAddr::new(ip_address, port),
+            Err(e) => {
+                warn!("Skipping the discovery target '{target}': {e:#}");
+                continue;
+            }
+        };
+
+        match socket.send_to(probe, address).await {
+            Ok(_) => sent += 1,
+            Err(e) => warn!("Failed to send the discovery probe to {address}: {e}"),
+        }
+    }
+
+    if sent == 0 && !targets.is_empty() {
+        anyhow::bail!("the discovery probe couldn't be sent to any target");
+    }
+
+    let mut devices = HashMap::new();
+    let mut buf = [0; 4096];
+
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    while let Ok(result) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        // e.g. an ICMP port unreachable from one of the targets, the responses already received
+        // and the ones still coming are kept
+        let (size, source) = match result {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Failed to receive a discovery response: {e}");
+                continue;
+            }
+        };
+
+        match decode_response(&buf[..size], source.ip()) {
+            Ok(device) => {
+                devices.insert(device.mac.clone(), device);
+            }
+            Err(e) => debug!("Ignoring discovery response from {source}: {e:?}"),
+        }
+    }
+
+    Ok(devices.into_values().collect())
+}
@@ -0,0 +1,246 @@
+use chrono::{DateTime, Datelike as _, Months, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tapo::requests::EnergyDataInterval;
+use tapo::responses::EnergyDataResult;
+
+/// Longest span of an hourly request the devices accept, in days.
+const MAX_HOURLY_SPAN_DAYS: i64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnergyInterval {
+    Hourly,
+    Daily,
+    Monthly,
+}
+
+impl EnergyInterval {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EnergyInterval::Hourly => "hourly",
+            EnergyInterval::Daily => "daily",
+            EnergyInterval::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hourly" => Some(EnergyInterval::Hourly),
+            "daily" => Some(EnergyInterval::Daily),
+            "monthly" => Some(EnergyInterval::Monthly),
+            _ => None,
+        }
+    }
+}
+
+/// The start of the last interval of a device that has been published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnergyWatermark {
+    pub device_name: String,
+    pub interval: EnergyInterval,
+    pub start_date_time: DateTime<Utc>,
+}
+
+/// A request for an energy breakdown, within the limits of what the devices support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnergyRequest {
+    pub interval: EnergyInterval,
+    pub start: NaiveDate,
+    /// Inclusive, only used by hourly requests.
+    pub end: NaiveDate,
+}
+
+impl EnergyRequest {
+    /// The period that `today` is part of: the day itself, its quarter or its year.
+    pub fn current(interval: EnergyInterval, today: NaiveDate) -> Self {
+        let start = match interval {
+            EnergyInterval::Hourly => today,
+            EnergyInterval::Daily => {
+                let quarter_month = (today.month0() / 3) * 3 + 1;
+                NaiveDate::from_ymd_opt(today.year(), quarter_month, 1).unwrap_or(today)
+            }
+            EnergyInterval::Monthly => NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap_or(today),
+        };
+
+        Self {
+            interval,
+            start,
+            end: today,
+        }
+    }
+
+    /// Builds a request, defaulting to the period that `today` is part of.
+    ///
+    /// Daily breakdowns must start on the first day of a quarter and monthly ones on the first day
+    /// of a year. Only hourly breakdowns take an `end`, at most 8 days after `start`.
+    pub fn new(
+        interval: EnergyInterval,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+        today: NaiveDate,
+    ) -> Result<Self, String> {
+        let Some(start) = start else {
+            return match end {
+                Some(_) => Err("end requires a start".to_string()),
+                None => Ok(Self::current(interval, today)),
+            };
+        };
+
+        match interval {
+            EnergyInterval::Hourly => {
+                let end = end.unwrap_or(start);
+
+                if end < start {
+                    return Err("end must not be before start".to_string());
+                }
+
+                if end - start > TimeDelta::days(MAX_HOURLY_SPAN_DAYS) {
+                    return Err(format!(
+                        "end must not be more than {MAX_HOURLY_SPAN_DAYS} days after start"
+                    ));
+                }
+
+                Ok(Self {
+                    interval,
+                    start,
+                    end,
+                })
+            }
+            EnergyInterval::Daily | EnergyInterval::Monthly => {
+                if end.is_some() {
+                    return Err("end is only supported by hourly breakdowns".to_string());
+                }
+
+                let expected = Self::current(interval, start).start;
+                if start != expected {
+                    return Err(match interval {
+                        EnergyInterval::Daily => "start must be the first day of a quarter",
+                        _ => "start must be the first day of a year",
+                    }
+                    .to_string());
+                }
+
+                Ok(Self {
+                    interval,
+                    start,
+                    end: start,
+                })
+            }
+        }
+    }
+
+    /// The requests covering the completed periods from `since` up to, but excluding, the period
+    /// that `today` is part of, which is kept up to date by the regular fetches anyway.
+    /// Nothing older than `oldest` is requested. Monthly breakdowns are always fetched in full.
+    pub fn backfill(
+        interval: EnergyInterval,
+        since: NaiveDate,
+        oldest: NaiveDate,
+        today: NaiveDate,
+    ) -> Vec<Self> {
+        let current = Self::current(interval, today).start;
+        let mut start = Self::current(interval, since.max(oldest)).start;
+        let mut requests = Vec::new();
+
+        while start < current {
+            let request = match interval {
+                EnergyInterval::Hourly => {
+                    let end = (start + TimeDelta::days(MAX_HOURLY_SPAN_DAYS))
+                        .min(current - TimeDelta::days(1));
+
+                    Self {
+                        interval,
+                        start,
+                        end,
+                    }
+                }
+                EnergyInterval::Daily => Self {
+                    interval,
+                    start,
+                    end: start,
+                },
+                EnergyInterval::Monthly => break,
+            };
+
+            start = match interval {
+                EnergyInterval::Hourly => request.end + TimeDelta::days(1),
+                _ => start + Months::new(3),
+            };
+            requests.push(request);
+        }
+
+        requests
+    }
+}
+
+impl From<EnergyRequest> for EnergyDataInterval {
+    fn from(request: EnergyRequest) -> Self {
+        match request.interval {
+            EnergyInterval::Hourly => EnergyDataInterval::Hourly {
+                start_date: request.start,
+                end_date: request.end,
+            },
+            EnergyInterval::Daily => EnergyDataInterval::Daily {
+                start_date: request.start,
+            },
+            EnergyInterval::Monthly => EnergyDataInterval::Monthly {
+                start_date: request.start,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyEntry {
+    pub start_date_time: DateTime<Utc>,
+    /// Energy in watt-hour (Wh)
+    pub energy: u64,
+    /// Cost of the energy, only set when a tariff is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyData {
+    pub interval: EnergyInterval,
+    pub start_date_time: DateTime<Utc>,
+    /// Length of every entry in minutes
+    pub interval_length: u64,
+    /// Currency of the entries' costs, only set when a tariff is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    pub entries: Vec<EnergyEntry>,
+}
+
+impl EnergyData {
+    /// Start of the most recent entry that has already begun, i.e. the last published interval.
+    pub fn last_entry_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.start_date_time)
+            .filter(|start_date_time| *start_date_time <= now)
+            .max()
+    }
+}
+
+impl From<(EnergyInterval, EnergyDataResult)> for EnergyData {
+    fn from(data: (EnergyInterval, EnergyDataResult)) -> Self {
+        let (interval, result) = data;
+
+        Self {
+            interval,
+            start_date_time: result.start_date_time,
+            interval_length: result.interval_length,
+            currency: None,
+            entries: result
+                .entries
+                .into_iter()
+                .map(|entry| EnergyEntry {
+                    start_date_time: entry.start_date_time,
+                    energy: entry.energy,
+                    cost: None,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tapo::responses::DeviceUsageEnergyMonitoringResult;
+
+use crate::settings::DeviceAction;
+use crate::system::device_status::{LightState, OutletState};
+use crate::system::sensors::Sensor;
+
+/// Something that happened to a configured device, as streamed by the API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceEvent {
+    pub device: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: DeviceEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeviceEventKind {
+    /// What a poll of the device read.
+    Sample(DeviceSample),
+    /// The device has been turned on or off.
+    State { device_on: bool },
+    /// The device stopped or started answering its polls again.
+    Availability { available: bool },
+    /// Outcome of switching or changing the device.
+    Command {
+        #[serde(flatten)]
+        command: DeviceCommand,
+        success: bool,
+        /// Whether the device is on afterwards, when it's been applied.
+        device_on: Option<bool>,
+        error: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceSample {
+    /// Plugs.
+    Usage {
+        /// Current power in watts (W)
+        current_power: u64,
+        device_on: bool,
+        /// Today's power usage in watt-hour (Wh)
+        power_usage_today: Option<u64>,
+    },
+    /// Bulbs and light strips.
+    Light(LightState),
+    /// Power strips.
+    Outlets { outlets: Vec<OutletState> },
+    /// Hubs.
+    Sensors { sensors: Vec<Sensor> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DeviceCommand {
+    /// Switched by the API, a schedule or an automation.
+    Power {
+        action: DeviceAction,
+    },
+    Light,
+    Outlet {
+        position: u8,
+        action: DeviceAction,
+    },
+    Scene {
+        scene: String,
+    },
+    Timer {
+        action: DeviceAction,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceEventType {
+    Sample,
+    State,
+    Availability,
+    Command,
+}
+
+impl DeviceEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceEventType::Sample => "sample",
+            DeviceEventType::State => "state",
+            DeviceEventType::Availability => "availability",
+            DeviceEventType::Command => "command",
+        }
+    }
+}
+
+impl FromStr for DeviceEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sample" => Ok(DeviceEventType::Sample),
+            "state" => Ok(DeviceEventType::State),
+            "availability" => Ok(DeviceEventType::Availability),
+            "command" => Ok(DeviceEventType::Command),
+            _ => Err(format!("unknown event type '{s}'")),
+        }
+    }
+}
+
+impl DeviceEvent {
+    pub fn new(device: &str, kind: DeviceEventKind) -> Self {
+        Self {
+            device: device.to_string(),
+            timestamp: Utc::now(),
+            kind,
+        }
+    }
+
+    pub fn usage(
+        device: &str,
+        device_usage: &DeviceUsageEnergyMonitoringResult,
+        current_power: u64,
+        device_on: bool,
+    ) -> Self {
+        Self::new(
+            device,
+            DeviceEventKind::Sample(DeviceSample::Usage {
+                current_power,
+                device_on,
+                power_usage_today: device_usage.power_usage.today,
+            }),
+        )
+    }
+
+    /// `result` holds whether the device is on afterwards, or why the command failed.
+    pub fn command(device: &str, command: DeviceCommand, result: Result<bool, String>) -> Self {
+        let kind = match result {
+            Ok(device_on) => DeviceEventKind::Command {
+                command,
+                success: true,
+                device_on: Some(device_on),
+                error: None,
+            },
+            Err(error) => DeviceEventKind::Command {
+                command,
+                success: false,
+                device_on: None,
+                error: Some(error),
+            },
+        };
+
+        Self::new(device, kind)
+    }
+
+    pub fn event_type(&self) -> DeviceEventType {
+        match self.kind {
+            DeviceEventKind::Sample(_) => DeviceEventType::Sample,
+            DeviceEventKind::State { .. } => DeviceEventType::State,
+            DeviceEventKind::Availability { .. } => DeviceEventType::Availability,
+            DeviceEventKind::Command { .. } => DeviceEventType::Command,
+        }
+    }
+}
+
+/// Which events a subscriber wants, everything by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub devices: Option<HashSet<String>>,
+    pub event_types: Option<HashSet<DeviceEventType>>,
+}
+
+impl EventFilter {
+    /// Both lists are comma separated, e.g. `device=heater,fan&type=sample,state`.
+    pub fn parse(devices: Option<&str>, event_types: Option<&str>) -> Result<Self, String> {
+        let devices = devices.map(|devices| split(devices).map(str::to_string).collect());
+        let event_types = event_types
+            .map(|event_types| split(event_types).map(str::parse).collect())
+            .transpose()?;
+
+        Ok(Self {
+            devices,
+            event_types,
+        })
+    }
+
+    pub fn matches(&self, event: &DeviceEvent) -> bool {
+        self.devices
+            .as_ref()
+            .is_none_or(|devices| devices.contains(&event.device))
+            && self
+                .event_types
+                .as_ref()
+                .is_none_or(|event_types| event_types.contains(&event.event_type()))
+    }
+}
+
+fn split(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
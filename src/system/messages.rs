@@ -1,8 +1,26 @@
-use actix::Message;
+use std::collections::HashMap;
+
+use actix::{Message, Recipient};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use tapo::responses::DeviceUsageEnergyMonitoringResult;
+use tokio::sync::broadcast;
 
-use crate::settings::Device;
+use crate::settings::{Device, DeviceAction, LightSettings, SceneState, Schedule};
+use crate::system::alerts::rules::Alert;
+use crate::system::automations::rules::AutomationStatus;
+use crate::system::cycles::{Cycle, CycleEvent};
+use crate::system::device_status::{
+    DeviceStatus, DeviceSummary, LightError, LightState, OutletError, OutletState, TimerStatus,
+};
+use crate::system::discovery::protocol::DiscoveredDevice;
+use crate::system::energy::{EnergyData, EnergyInterval, EnergyRequest, EnergyWatermark};
+use crate::system::events::DeviceEvent;
+use crate::system::scenes::SceneActivation;
+use crate::system::scheduler::schedule::{ScheduleError, ScheduleStatus};
+use crate::system::sensors::{Sensor, SensorEvent};
+use crate::system::storage::database::{Resolution, Sample};
+use crate::system::tariff::UsageCost;
 
 #[derive(Debug, Message)]
 #[rtype(result = "()")]
@@ -16,12 +34,354 @@ pub struct GetDeviceDataMessage {
     pub span_context: opentelemetry::Context,
 }
 
-#[derive(Debug, Message)]
+#[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
 pub struct DeviceUsageMessage {
     pub span_context: opentelemetry::Context,
     pub device: Device,
     pub device_usage: DeviceUsageEnergyMonitoringResult,
+    /// Current power in watts (W)
+    pub current_power: u64,
+    pub device_on: bool,
+}
+
+/// Polled state of a bulb or light strip, or the one it's left in after being changed.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct LightStateMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+    pub state: LightState,
+}
+
+/// Polled state of the outlets of a power strip, or the one they're left in after being switched.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct OutletsStateMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+    pub outlets: Vec<OutletState>,
+}
+
+/// Polled readings of the sensors paired with a hub.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct SensorsStateMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+    pub sensors: Vec<Sensor>,
+}
+
+/// Events read from the trigger logs of the sensors paired with a hub since its previous poll,
+/// oldest first.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct SensorEventsMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+    pub events: Vec<SensorEvent>,
+}
+
+/// Sent by a `DeviceActor` whenever a poll of its device fails.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct DeviceUnreachableMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct AlertMessage {
+    pub span_context: opentelemetry::Context,
+    pub alert: Alert,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct RunDiscoveryMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct DeviceDiscoveredMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: DiscoveredDevice,
+}
+
+/// Runs a discovery and resolves to the discovered device matching the identity of `device`.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<DiscoveredDevice>")]
+pub struct FindDeviceMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+}
+
+/// Sent by a `DeviceActor` whose device stopped answering or no longer matches its configured identity.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct ResolveDeviceAddressMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct DeviceAddressChangedMessage {
+    pub span_context: opentelemetry::Context,
+    pub ip_address: String,
+}
+
+/// Resolves to the currently configured devices, including auto-registered ones.
+#[derive(Debug, Message)]
+#[rtype(result = "Vec<Device>")]
+pub struct GetDevicesMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Lists the configured devices, with the outlets of the power strips as sub-devices.
+#[derive(Debug, Message)]
+#[rtype(result = "Vec<DeviceSummary>")]
+pub struct ListDevicesMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Last readings of the sensors paired with a hub. Resolves to `None` when there's no hub with
+/// the given name.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<Vec<Sensor>>")]
+pub struct GetSensorsMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+}
+
+/// Resolves to `false` when there's no device with the given name.
+#[derive(Debug, Message)]
+#[rtype(result = "bool")]
+pub struct SetRefreshRateMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub refresh_rate_s: u64,
+}
+
+/// Resolves to `None` when discovery is disabled.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<Vec<DiscoveredDevice>>")]
+pub struct GetDiscoveredDevicesMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct FetchEnergyDataMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PublishOutcome {
+    pub published: bool,
+    /// Whether the connection to the broker has been (re)established since the previous outcome.
+    pub reconnected: bool,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "PublishOutcome")]
+pub struct EnergyDataMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+    pub energy_data: EnergyData,
+    /// Whether the data is being published late, after downtime.
+    pub backfilled: bool,
+}
+
+/// Asks a device to publish the intervals that started at or after the given ones.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct BackfillEnergyDataMessage {
+    pub span_context: opentelemetry::Context,
+    pub since: HashMap<EnergyInterval, DateTime<Utc>>,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct EnergyPublishedMessage {
+    pub span_context: opentelemetry::Context,
+    pub watermark: EnergyWatermark,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "anyhow::Result<Vec<EnergyWatermark>>")]
+pub struct GetEnergyWatermarksMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Resolves to `None` when there's no device with the given name.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<EnergyData>>")]
+pub struct GetEnergyDataMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub request: EnergyRequest,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct RunStorageMaintenanceMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Resolves to `None` when storage is disabled.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<Vec<Sample>>>")]
+pub struct GetDeviceHistoryMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub resolution: Resolution,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct CycleEventMessage {
+    pub span_context: opentelemetry::Context,
+    pub device: Device,
+    pub event: CycleEvent,
+}
+
+/// Resolves to `None` when storage is disabled.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<Vec<Cycle>>>")]
+pub struct GetDeviceCyclesMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// Resolves to every configured device along with the last usage read from it, if any.
+#[derive(Debug, Message)]
+#[rtype(result = "Vec<(Device, Option<DeviceUsageEnergyMonitoringResult>)>")]
+pub struct GetDevicesUsageMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Asks an actor to stop taking on new work and to resolve once its in-flight work has drained.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct ShutdownMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+#[derive(Serialize)]
+pub struct EnergyMqttMessagePayload {
+    device_name: String,
+    #[serde(flatten)]
+    energy_data: EnergyData,
+    backfilled: bool,
+}
+
+impl From<(Device, EnergyData, bool)> for EnergyMqttMessagePayload {
+    fn from(data: (Device, EnergyData, bool)) -> Self {
+        let (device, energy_data, backfilled) = data;
+
+        EnergyMqttMessagePayload {
+            device_name: device.name,
+            energy_data,
+            backfilled,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LightMqttMessagePayload {
+    device_name: String,
+    #[serde(flatten)]
+    state: LightState,
+}
+
+impl From<(Device, LightState)> for LightMqttMessagePayload {
+    fn from(data: (Device, LightState)) -> Self {
+        let (device, state) = data;
+
+        LightMqttMessagePayload {
+            device_name: device.name,
+            state,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OutletMqttMessagePayload {
+    device_name: String,
+    #[serde(flatten)]
+    outlet: OutletState,
+}
+
+impl From<(&Device, OutletState)> for OutletMqttMessagePayload {
+    fn from(data: (&Device, OutletState)) -> Self {
+        let (device, outlet) = data;
+
+        OutletMqttMessagePayload {
+            device_name: device.name.clone(),
+            outlet,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SensorMqttMessagePayload {
+    hub_name: String,
+    #[serde(flatten)]
+    sensor: Sensor,
+}
+
+impl From<(&Device, Sensor)> for SensorMqttMessagePayload {
+    fn from(data: (&Device, Sensor)) -> Self {
+        let (device, sensor) = data;
+
+        SensorMqttMessagePayload {
+            hub_name: device.name.clone(),
+            sensor,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SensorEventMqttMessagePayload {
+    hub_name: String,
+    #[serde(flatten)]
+    event: SensorEvent,
+}
+
+impl From<(&Device, SensorEvent)> for SensorEventMqttMessagePayload {
+    fn from(data: (&Device, SensorEvent)) -> Self {
+        let (device, event) = data;
+
+        SensorEventMqttMessagePayload {
+            hub_name: device.name.clone(),
+            event,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CycleMqttMessagePayload {
+    device_name: String,
+    #[serde(flatten)]
+    event: CycleEvent,
+}
+
+impl From<(Device, CycleEvent)> for CycleMqttMessagePayload {
+    fn from(data: (Device, CycleEvent)) -> Self {
+        let (device, event) = data;
+
+        CycleMqttMessagePayload {
+            device_name: device.name,
+            event,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -39,11 +399,36 @@ pub struct MqttMessagePayload {
     power_usage_past7: Option<u64>,
     // Today's power usage in watt-hour (Wh)
     power_usage_past30: Option<u64>,
+    // Current power in watts (W)
+    current_power: u64,
+    // Currency of the costs, only set when a tariff is configured
+    currency: Option<String>,
+    // Today's power usage cost
+    cost_today: Option<f64>,
+    // Past 7 days power usage cost
+    cost_past7: Option<f64>,
+    // Past 30 days power usage cost
+    cost_past30: Option<f64>,
 }
 
-impl From<(Device, DeviceUsageEnergyMonitoringResult)> for MqttMessagePayload {
-    fn from(data: (Device, DeviceUsageEnergyMonitoringResult)) -> Self {
-        let (device, dur) = data;
+impl
+    From<(
+        Device,
+        DeviceUsageEnergyMonitoringResult,
+        u64,
+        Option<UsageCost>,
+    )> for MqttMessagePayload
+{
+    fn from(
+        data: (
+            Device,
+            DeviceUsageEnergyMonitoringResult,
+            u64,
+            Option<UsageCost>,
+        ),
+    ) -> Self {
+        let (device, dur, current_power, cost) = data;
+        let cost = cost.as_ref();
 
         MqttMessagePayload {
             device_name: device.name,
@@ -62,6 +447,185 @@ impl From<(Device, DeviceUsageEnergyMonitoringResult)> for MqttMessagePayload {
             power_usage_today: dur.power_usage.today,
             power_usage_past7: dur.power_usage.past7,
             power_usage_past30: dur.power_usage.past30,
+            current_power,
+            currency: cost.map(|cost| cost.currency.clone()),
+            cost_today: cost
+                .and_then(|cost| cost.today.as_ref())
+                .map(|today| today.cost),
+            cost_past7: cost
+                .and_then(|cost| cost.past7.as_ref())
+                .map(|past7| past7.cost),
+            cost_past30: cost
+                .and_then(|cost| cost.past30.as_ref())
+                .map(|past30| past30.cost),
         }
     }
 }
+
+/// Switches a device. Resolves to `None` when there's no device with the given name, otherwise
+/// to whether the device is on afterwards.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<bool>>")]
+pub struct SetDevicePowerMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub action: DeviceAction,
+}
+
+/// Reads the state of a device. Resolves to `None` when there's no device with the given name.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<DeviceStatus>>")]
+pub struct GetDeviceStatusMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+}
+
+/// Switches a device once `duration_s` elapses, replacing its current timer.
+/// Resolves to `None` when there's no device with the given name.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<TimerStatus>")]
+pub struct SetDeviceTimerMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub action: DeviceAction,
+    pub duration_s: u64,
+}
+
+/// Resolves to `None` when there's no device with the given name, otherwise to whether a timer
+/// has been cancelled.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<bool>")]
+pub struct CancelDeviceTimerMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct DeviceTimerElapsedMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Changes a bulb or light strip. Resolves to `None` when there's no device with the given name,
+/// otherwise to the state it's left in.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<Result<LightState, LightError>>")]
+pub struct SetLightMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub light: LightSettings,
+}
+
+/// Switches an outlet of a power strip. Resolves to `None` when there's no device with the given
+/// name, otherwise to the state the outlet is left in.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<Result<OutletState, OutletError>>")]
+pub struct SetOutletMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub position: u8,
+    pub action: DeviceAction,
+}
+
+/// Applies the state of a scene to a device. Resolves to `None` when there's no device with the
+/// given name, otherwise to whether the device is on afterwards.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<bool>>")]
+pub struct SetDeviceStateMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+    pub state: SceneState,
+}
+
+/// Resolves to `None` when there's no scene with the given name.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<SceneActivation>")]
+pub struct ActivateSceneMessage {
+    pub span_context: opentelemetry::Context,
+    pub scene_name: String,
+}
+
+/// Has the MQTT actor forward the scene names published to the scene topic to `recipient`.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct SubscribeScenesMessage {
+    pub span_context: opentelemetry::Context,
+    pub recipient: Recipient<ActivateSceneMessage>,
+}
+
+/// Sent by the API whenever it switches a device, so that the schedules leave it alone for a while.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct DeviceControlledMessage {
+    pub span_context: opentelemetry::Context,
+    pub ip_address: String,
+    pub device_on: bool,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct ManualOverrideMessage {
+    pub span_context: opentelemetry::Context,
+    pub device_name: String,
+}
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct RunSchedulesMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Resolves to `None` when the scheduler is disabled.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<Vec<ScheduleStatus>>>")]
+pub struct GetSchedulesMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Adds or replaces the schedule with the same name. Resolves to `None` when the scheduler is
+/// disabled, otherwise to whether a schedule was replaced.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<Result<bool, ScheduleError>>")]
+pub struct SetScheduleMessage {
+    pub span_context: opentelemetry::Context,
+    pub schedule: Schedule,
+}
+
+/// Resolves to `None` when the scheduler is disabled, otherwise to whether the schedule existed.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<bool>>")]
+pub struct DeleteScheduleMessage {
+    pub span_context: opentelemetry::Context,
+    pub name: String,
+}
+
+/// Publishes `payload` as is to `topic`, outside of the configured topics.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct MqttPublishMessage {
+    pub span_context: opentelemetry::Context,
+    pub topic: String,
+    pub payload: String,
+}
+
+/// Resolves to `None` when there are no automations.
+#[derive(Debug, Message)]
+#[rtype(result = "Option<anyhow::Result<Vec<AutomationStatus>>>")]
+pub struct GetAutomationsMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Streams the events of every device, see [`DeviceEvent`].
+#[derive(Debug, Message)]
+#[rtype(result = "broadcast::Receiver<DeviceEvent>")]
+pub struct SubscribeEventsMessage {
+    pub span_context: opentelemetry::Context,
+}
+
+/// Sent by the devices for what the coordinator doesn't see, e.g. their timers going off.
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct DeviceEventMessage {
+    pub span_context: opentelemetry::Context,
+    pub event: DeviceEvent,
+}
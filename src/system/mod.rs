@@ -1,5 +1,17 @@
+pub mod alerts;
 pub mod api;
+pub mod automations;
 pub mod coordinator_actor;
+pub mod cycles;
 mod device_actor;
-mod messages;
+pub mod device_status;
+pub mod discovery;
+pub mod energy;
+pub mod events;
+pub mod messages;
 mod mqtt_actor;
+pub mod scenes;
+pub mod scheduler;
+pub mod sensors;
+pub mod storage;
+pub mod tariff;
@@ -1,19 +1,43 @@
-use actix::{Actor, AsyncContext, Context, Handler, WrapFuture};
-use paho_mqtt::{AsyncClient, Message, QOS_1};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use actix::{
+    Actor, ActorContext, ActorFutureExt, AsyncContext, Context, Handler, ResponseActFuture,
+    WrapFuture,
+};
+use chrono::Utc;
+use paho_mqtt::{AsyncClient, ConnectOptionsBuilder, Message, QOS_1};
 use serde_json::json;
-use tapo::responses::DeviceUsageEnergyMonitoringResult;
-use tracing::{Instrument, info, instrument};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, debug, info, instrument, warn};
 use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
 use crate::{
-    settings::{Device, Mqtt},
-    system::messages::{DeviceUsageMessage, MqttMessagePayload},
+    settings::{Mqtt, Tariff},
+    system::{
+        messages::{
+            ActivateSceneMessage, AlertMessage, CycleEventMessage, CycleMqttMessagePayload,
+            DeviceUsageMessage, EnergyDataMessage, EnergyMqttMessagePayload,
+            LightMqttMessagePayload, LightStateMessage, MqttMessagePayload, MqttPublishMessage,
+            OutletMqttMessagePayload, OutletsStateMessage, PublishOutcome,
+            SensorEventMqttMessagePayload, SensorEventsMessage, SensorMqttMessagePayload,
+            SensorsStateMessage, ShutdownMessage, SubscribeScenesMessage,
+        },
+        tariff::UsageCost,
+    },
     telemetry::record_error,
 };
 
+const STATUS_ONLINE: &str = "online";
+const STATUS_OFFLINE: &str = "offline";
+
 pub struct MqttActor {
     config: Mqtt,
+    tariff: Option<Tariff>,
     client: AsyncClient,
+    in_flight: TaskTracker,
+    /// Set whenever a connection is established, cleared once reported through a [`PublishOutcome`].
+    reconnected: Arc<AtomicBool>,
 }
 
 impl MqttActor {
@@ -23,31 +47,63 @@ impl MqttActor {
         exception.message = tracing::field::Empty,
         exception.stacktrace = tracing::field::Empty,
     ))]
-    pub fn new(config: Mqtt) -> Result<Self, paho_mqtt::Error> {
+    pub fn new(config: Mqtt, tariff: Option<Tariff>) -> Result<Self, paho_mqtt::Error> {
         let span = tracing::Span::current();
 
         let client = AsyncClient::new(config.address.clone()).inspect_err(|e| {
             record_error(&span, &e);
         })?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            tariff,
+            client,
+            in_flight: TaskTracker::new(),
+            reconnected: Arc::new(AtomicBool::new(false)),
+        })
     }
 
+    /// Connects if needed, leaving an `offline` last will and announcing that we're `online`.
+    async fn ensure_connected(
+        client: &AsyncClient,
+        status_topic_name: &str,
+        reconnected: &AtomicBool,
+    ) -> Result<(), paho_mqtt::Error> {
+        if client.is_connected() {
+            return Ok(());
+        }
+
+        let connect_options = ConnectOptionsBuilder::new()
+            .will_message(Message::new_retained(
+                status_topic_name,
+                STATUS_OFFLINE,
+                QOS_1,
+            ))
+            .finalize();
+        client.connect(connect_options).await?;
+        reconnected.store(true, Ordering::SeqCst);
+
+        client
+            .publish(Message::new_retained(
+                status_topic_name,
+                STATUS_ONLINE,
+                QOS_1,
+            ))
+            .await
+    }
+
+    /// Returns whether the message has been published.
     pub async fn send_mqtt_message(
-        device: Device,
-        device_usage: DeviceUsageEnergyMonitoringResult,
+        payload: String,
         client: AsyncClient,
         topic_name: String,
-    ) {
+        status_topic_name: String,
+        reconnected: Arc<AtomicBool>,
+    ) -> bool {
         let span = tracing::Span::current();
 
-        let payload: MqttMessagePayload = (device, device_usage).into();
-        let payload = json!(payload).to_string();
-
         let result = async {
-            if !client.is_connected() {
-                client.connect(None).await?;
-            }
+            Self::ensure_connected(&client, &status_topic_name, &reconnected).await?;
 
             let message = Message::new(topic_name, payload.clone(), QOS_1);
             let delivery_token = client.publish(message);
@@ -58,8 +114,14 @@ impl MqttActor {
         .await;
 
         match result {
-            Ok(_) => info!("Sent MQTT message: {payload}"),
-            Err(e) => record_error(&span, &e),
+            Ok(_) => {
+                info!("Sent MQTT message: {payload}");
+                true
+            }
+            Err(e) => {
+                record_error(&span, &e);
+                false
+            }
         }
     }
 }
@@ -98,12 +160,627 @@ impl Handler<DeviceUsageMessage> for MqttActor {
         let span = tracing::Span::current();
         let _ = span.set_parent(message.span_context);
 
+        if self.in_flight.is_closed() {
+            warn!("Dropping the device usage, the MQTT Actor is shutting down");
+            return;
+        }
+
         let client = self.client.clone();
         let topic_name = self.config.topic_name.clone();
+        let status_topic_name = self.config.status_topic();
+
+        let cost = self
+            .tariff
+            .as_ref()
+            .map(|tariff| UsageCost::new(tariff, &message.device_usage, Utc::now()));
+        let payload: MqttMessagePayload = (
+            message.device,
+            message.device_usage,
+            message.current_power,
+            cost,
+        )
+            .into();
+        let payload = json!(payload).to_string();
+
+        let fut = self
+            .in_flight
+            .track_future(Self::send_mqtt_message(
+                payload,
+                client,
+                topic_name,
+                status_topic_name,
+                self.reconnected.clone(),
+            ))
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, _| ());
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<EnergyDataMessage> for MqttActor {
+    type Result = ResponseActFuture<Self, PublishOutcome>;
+
+    #[instrument(
+        name = "MqttActor::Handler<EnergyDataMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "EnergyDataMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: EnergyDataMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the energy data, the MQTT Actor is shutting down");
+            return Box::pin(actix::fut::ready(PublishOutcome::default()));
+        }
+
+        let client = self.client.clone();
+        let topic_name = self.config.energy_topic();
+        let status_topic_name = self.config.status_topic();
+
+        let payload: EnergyMqttMessagePayload =
+            (message.device, message.energy_data, message.backfilled).into();
+        let payload = json!(payload).to_string();
+
+        let fut = self
+            .in_flight
+            .track_future(Self::send_mqtt_message(
+                payload,
+                client,
+                topic_name,
+                status_topic_name,
+                self.reconnected.clone(),
+            ))
+            .instrument(span)
+            .into_actor(self)
+            .map(|published, actor, _| PublishOutcome {
+                published,
+                reconnected: published && actor.reconnected.swap(false, Ordering::SeqCst),
+            });
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<AlertMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<AlertMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "AlertMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.alert.device_name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: AlertMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the alert, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let client = self.client.clone();
+        let topic_name = self.config.alert_topic();
+        let status_topic_name = self.config.status_topic();
+
+        let payload = json!(message.alert).to_string();
+
+        let fut = self
+            .in_flight
+            .track_future(Self::send_mqtt_message(
+                payload,
+                client,
+                topic_name,
+                status_topic_name,
+                self.reconnected.clone(),
+            ))
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, _| ());
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<CycleEventMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<CycleEventMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "CycleEventMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: CycleEventMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the cycle event, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let client = self.client.clone();
+        let topic_name = self.config.cycle_topic();
+        let status_topic_name = self.config.status_topic();
+
+        let payload: CycleMqttMessagePayload = (message.device, message.event).into();
+        let payload = json!(payload).to_string();
+
+        let fut = self
+            .in_flight
+            .track_future(Self::send_mqtt_message(
+                payload,
+                client,
+                topic_name,
+                status_topic_name,
+                self.reconnected.clone(),
+            ))
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, _| ());
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<LightStateMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<LightStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "LightStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: LightStateMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the light state, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let client = self.client.clone();
+        let topic_name = self.config.light_topic();
+        let status_topic_name = self.config.status_topic();
+
+        let payload: LightMqttMessagePayload = (message.device, message.state).into();
+        let payload = json!(payload).to_string();
+
+        let fut = self
+            .in_flight
+            .track_future(Self::send_mqtt_message(
+                payload,
+                client,
+                topic_name,
+                status_topic_name,
+                self.reconnected.clone(),
+            ))
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, _| ());
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<OutletsStateMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<OutletsStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "OutletsStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: OutletsStateMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the outlets state, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let topic_name = self.config.outlet_topic();
+        let status_topic_name = self.config.status_topic();
+
+        // one message per outlet, so each can be consumed like a device of its own
+        for outlet in message.outlets {
+            let payload: OutletMqttMessagePayload = (&message.device, outlet).into();
+            let payload = json!(payload).to_string();
+
+            let fut = self
+                .in_flight
+                .track_future(Self::send_mqtt_message(
+                    payload,
+                    self.client.clone(),
+                    topic_name.clone(),
+                    status_topic_name.clone(),
+                    self.reconnected.clone(),
+                ))
+                .instrument(span.clone())
+                .into_actor(self)
+                .map(|_, _, _| ());
+
+            ctx.spawn(fut);
+        }
+    }
+}
+
+impl Handler<SensorsStateMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<SensorsStateMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SensorsStateMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SensorsStateMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the sensor readings, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let status_topic_name = self.config.status_topic();
+
+        for sensor in message.sensors {
+            let topic_name = self.config.sensor_topic(&sensor.device_id);
+            let payload: SensorMqttMessagePayload = (&message.device, sensor).into();
+            let payload = json!(payload).to_string();
+
+            let fut = self
+                .in_flight
+                .track_future(Self::send_mqtt_message(
+                    payload,
+                    self.client.clone(),
+                    topic_name,
+                    status_topic_name.clone(),
+                    self.reconnected.clone(),
+                ))
+                .instrument(span.clone())
+                .into_actor(self)
+                .map(|_, _, _| ());
+
+            ctx.spawn(fut);
+        }
+    }
+}
+
+impl Handler<SensorEventsMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<SensorEventsMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SensorEventsMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SensorEventsMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the sensor events, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let status_topic_name = self.config.status_topic();
+
+        for event in message.events {
+            let topic_name = format!("{}/events", self.config.sensor_topic(&event.device_id));
+            let payload: SensorEventMqttMessagePayload = (&message.device, event).into();
+            let payload = json!(payload).to_string();
+
+            let fut = self
+                .in_flight
+                .track_future(Self::send_mqtt_message(
+                    payload,
+                    self.client.clone(),
+                    topic_name,
+                    status_topic_name.clone(),
+                    self.reconnected.clone(),
+                ))
+                .instrument(span.clone())
+                .into_actor(self)
+                .map(|_, _, _| ());
+
+            ctx.spawn(fut);
+        }
+    }
+}
+
+impl Handler<ShutdownMessage> for MqttActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "MqttActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        self.in_flight.close();
+
+        let in_flight = self.in_flight.clone();
+        let client = self.client.clone();
+        let status_topic_name = self.config.status_topic();
+
+        let fut = async move {
+            // let the messages that are already on their way reach the broker first
+            in_flight.wait().await;
+
+            if !client.is_connected() {
+                return;
+            }
+
+            let result = async {
+                client
+                    .publish(Message::new_retained(
+                        status_topic_name,
+                        STATUS_OFFLINE,
+                        QOS_1,
+                    ))
+                    .await?;
+                client.disconnect(None).await?;
+
+                Ok::<_, paho_mqtt::Error>(())
+            }
+            .await;
+
+            match result {
+                Ok(_) => info!("Disconnected from the MQTT broker"),
+                Err(e) => {
+                    warn!("Failed to disconnect from the MQTT broker cleanly: {e:?}");
+                    record_error(&tracing::Span::current(), &e);
+                }
+            }
+        }
+        .instrument(span)
+        .into_actor(self)
+        .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
+
+impl Handler<MqttPublishMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<MqttPublishMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "MqttPublishMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            messaging.mqtt.topic = %message.topic,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: MqttPublishMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if self.in_flight.is_closed() {
+            warn!("Dropping the message, the MQTT Actor is shutting down");
+            return;
+        }
+
+        let client = self.client.clone();
+        let status_topic_name = self.config.status_topic();
+
+        let fut = self
+            .in_flight
+            .track_future(Self::send_mqtt_message(
+                message.payload,
+                client,
+                message.topic,
+                status_topic_name,
+                self.reconnected.clone(),
+            ))
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, _| ());
+
+        ctx.spawn(fut);
+    }
+}
+
+impl Handler<SubscribeScenesMessage> for MqttActor {
+    type Result = ();
+
+    #[instrument(
+        name = "MqttActor::Handler<SubscribeScenesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SubscribeScenesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "MqttActor",
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: SubscribeScenesMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let scene_topic_name = self.config.scene_topic();
+        let recipient = message.recipient;
+
+        // subscriptions don't outlive the session, so subscribe again on every connection
+        self.client.set_connected_callback({
+            let scene_topic_name = scene_topic_name.clone();
+            move |client| {
+                client.subscribe(scene_topic_name.clone(), QOS_1);
+            }
+        });
+
+        self.client.set_message_callback({
+            let scene_topic_name = scene_topic_name.clone();
+            move |_, message| {
+                let Some(message) = message else {
+                    return;
+                };
+
+                if message.topic() != scene_topic_name {
+                    return;
+                }
+
+                let scene_name = message.payload_str().trim().to_string();
+                if scene_name.is_empty() {
+                    return;
+                }
+
+                let span = tracing::info_span!(
+                    "MqttActor::SceneTriggered",
+                    otel.kind = "producer",
+                    messaging.message.id = "ActivateSceneMessage",
+                    messaging.operation.name = "send",
+                    messaging.operation.type = "send",
+                    messaging.destination.name = "CoordinatorActor",
+                    scene.name = %scene_name,
+                );
+                let _enter = span.enter();
+
+                debug!("Activating scene '{scene_name}' from MQTT");
+
+                recipient.do_send(ActivateSceneMessage {
+                    span_context: span.context(),
+                    scene_name,
+                });
+            }
+        });
+
+        if self.client.is_connected() {
+            self.client.subscribe(scene_topic_name, QOS_1);
+            return;
+        }
+
+        // nothing may be published for a while, so connect right away to start receiving
+        let client = self.client.clone();
+        let status_topic_name = self.config.status_topic();
+        let reconnected = self.reconnected.clone();
 
-        let fut = Self::send_mqtt_message(message.device, message.device_usage, client, topic_name)
+        let fut = self
+            .in_flight
+            .track_future(async move {
+                if let Err(e) =
+                    Self::ensure_connected(&client, &status_topic_name, &reconnected).await
+                {
+                    record_error(&tracing::Span::current(), &e);
+                }
+            })
             .instrument(span)
-            .into_actor(self);
+            .into_actor(self)
+            .map(|_, _, _| ());
 
         ctx.spawn(fut);
     }
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// Outcome of applying a scene to one of its devices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneDeviceResult {
+    pub device: String,
+    pub success: bool,
+    /// Whether the device is on afterwards, when it's been applied.
+    pub device_on: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl SceneDeviceResult {
+    pub fn new(device: String, result: anyhow::Result<bool>) -> Self {
+        match result {
+            Ok(device_on) => Self {
+                device,
+                success: true,
+                device_on: Some(device_on),
+                error: None,
+            },
+            Err(e) => Self {
+                device,
+                success: false,
+                device_on: None,
+                error: Some(format!("{e:#}")),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneActivation {
+    pub scene: String,
+    pub devices: Vec<SceneDeviceResult>,
+}
+
+impl SceneActivation {
+    pub fn succeeded(&self) -> bool {
+        self.devices.iter().all(|result| result.success)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod schedule;
+pub mod scheduler_actor;
+pub mod sun;
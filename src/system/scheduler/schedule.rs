@@ -0,0 +1,73 @@
+use std::str::FromStr as _;
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{Location, Schedule, ValidationErrors};
+use crate::system::scheduler::sun::sun_event_time;
+
+/// How many days ahead to look for a sunrise/sunset, enough to get through a polar night.
+const MAX_SUN_LOOKAHEAD_DAYS: u64 = 190;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    pub schedule: Schedule,
+    /// Not set for disabled schedules and those that never fire again.
+    pub next_run: Option<DateTime<Utc>>,
+    /// Set while the device has been switched through the API recently and is left alone.
+    pub overridden_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Display)]
+pub enum ScheduleError {
+    /// The schedule doesn't pass validation or refers to an unknown device.
+    #[display("{}", _0.errors.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Invalid(ValidationErrors),
+    #[display("{_0}")]
+    Failed(anyhow::Error),
+}
+
+/// Parses a cron expression, taking the usual 5 fields (minute to day of week) as well as the
+/// 6 and 7 field variants that start with the seconds.
+pub fn parse_cron(expression: &str) -> Result<cron::Schedule, cron::error::Error> {
+    let expression = expression.trim();
+
+    if expression.split_whitespace().count() == 5 {
+        cron::Schedule::from_str(&format!("0 {expression}"))
+    } else {
+        cron::Schedule::from_str(expression)
+    }
+}
+
+/// The first time after `after` that `schedule` fires, ignoring whether it's enabled.
+///
+/// Cron expressions and the days of the sun events follow the time zone of `after`.
+/// Returns `None` when the schedule never fires again or isn't valid.
+pub fn next_run<Tz: TimeZone>(
+    schedule: &Schedule,
+    location: Option<Location>,
+    after: &DateTime<Tz>,
+) -> Option<DateTime<Utc>> {
+    if let Some(expression) = &schedule.cron {
+        return parse_cron(expression)
+            .ok()?
+            .after(after)
+            .next()
+            .map(|run| run.with_timezone(&Utc));
+    }
+
+    let event = schedule.sun?;
+    let location = location?;
+    let offset = TimeDelta::minutes(schedule.offset_min);
+
+    // the event of the previous day can still be ahead once the offset is applied
+    after
+        .date_naive()
+        .pred_opt()?
+        .iter_days()
+        .take(MAX_SUN_LOOKAHEAD_DAYS as usize)
+        .filter_map(|date| sun_event_time(event, location, date))
+        .map(|time| time + offset)
+        .find(|run| *run > after.with_timezone(&Utc))
+}
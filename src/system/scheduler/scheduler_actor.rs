@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::clock::interval;
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Context, Handler, MessageResult,
+    ResponseActFuture, WrapFuture,
+};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, error, info, instrument, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::settings::{Schedule, Scheduler};
+use crate::system::coordinator_actor::CoordinatorActor;
+use crate::system::messages::{
+    DeleteScheduleMessage, GetSchedulesMessage, ManualOverrideMessage, RunSchedulesMessage,
+    SetDevicePowerMessage, SetScheduleMessage, ShutdownMessage,
+};
+use crate::system::scheduler::schedule::{ScheduleStatus, next_run};
+use crate::telemetry::record_error;
+
+/// How often the schedules are checked for runs that are due.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Switches the devices on cron expressions and around sunrise/sunset.
+#[derive(Debug)]
+pub struct SchedulerActor {
+    coordinator_actor_addr: Addr<CoordinatorActor>,
+    config: Scheduler,
+    /// By schedule name.
+    next_runs: HashMap<String, DateTime<Utc>>,
+    /// Until when the schedules leave a device alone, by device name.
+    overrides: HashMap<String, DateTime<Utc>>,
+    in_flight: TaskTracker,
+}
+
+impl SchedulerActor {
+    pub fn new(coordinator_actor_addr: Addr<CoordinatorActor>, config: Scheduler) -> Self {
+        let mut actor = Self {
+            coordinator_actor_addr,
+            config,
+            next_runs: HashMap::new(),
+            overrides: HashMap::new(),
+            in_flight: TaskTracker::new(),
+        };
+
+        for schedule in actor.config.schedules.clone() {
+            actor.plan(&schedule);
+        }
+
+        actor
+    }
+
+    /// Works out the next run of `schedule` from now.
+    fn plan(&mut self, schedule: &Schedule) {
+        let next = schedule
+            .enabled
+            .then(|| next_run(schedule, self.config.location, &Local::now()))
+            .flatten();
+
+        match next {
+            Some(next) => self.next_runs.insert(schedule.name.clone(), next),
+            None => self.next_runs.remove(&schedule.name),
+        };
+    }
+
+    fn overridden_until(&self, device_name: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.overrides
+            .get(device_name)
+            .copied()
+            .filter(|until| *until > now)
+    }
+
+    async fn run(
+        coordinator_actor_addr: Addr<CoordinatorActor>,
+        schedule: Schedule,
+        span_context: opentelemetry::Context,
+    ) {
+        let span = tracing::Span::current();
+
+        let result = coordinator_actor_addr
+            .send(SetDevicePowerMessage {
+                span_context,
+                device_name: schedule.device.clone(),
+                action: schedule.action,
+            })
+            .await;
+
+        match result {
+            Ok(Some(Ok(device_on))) => info!(
+                "Schedule '{}' switched '{}' {}",
+                schedule.name,
+                schedule.device,
+                if device_on { "on" } else { "off" }
+            ),
+            Ok(Some(Err(e))) => {
+                error!(
+                    "Schedule '{}' failed to switch '{}': {e:?}",
+                    schedule.name, schedule.device
+                );
+                record_error(&span, &*e);
+            }
+            Ok(None) => warn!(
+                "Schedule '{}' refers to the unknown device '{}'",
+                schedule.name, schedule.device
+            ),
+            Err(e) => record_error(&span, &e),
+        }
+    }
+}
+
+impl Actor for SchedulerActor {
+    type Context = Context<Self>;
+
+    #[instrument(name = "SchedulerActor::started", skip_all)]
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let addr = ctx.address();
+
+        let fut = async move {
+            let mut interval = interval(TICK_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = addr.try_send(RunSchedulesMessage {
+                    span_context: opentelemetry::Context::new(),
+                }) {
+                    error!("Failed to run the schedules: {e:?}");
+                }
+            }
+        }
+        .into_actor(self);
+
+        ctx.spawn(fut);
+    }
+
+    #[instrument(name = "SchedulerActor::stopped", level = "error", skip_all)]
+    fn stopped(&mut self, _: &mut Self::Context) {}
+}
+
+impl Handler<RunSchedulesMessage> for SchedulerActor {
+    type Result = ();
+
+    fn handle(&mut self, _: RunSchedulesMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let now = Utc::now();
+
+        let due: Vec<_> = self
+            .config
+            .schedules
+            .iter()
+            .filter(|schedule| {
+                self.next_runs
+                    .get(&schedule.name)
+                    .is_some_and(|next_run| *next_run <= now)
+            })
+            .cloned()
+            .collect();
+
+        for schedule in due {
+            self.plan(&schedule);
+
+            // only traced when something is due, the schedules are checked every second
+            let span = tracing::info_span!(
+                "SchedulerActor::Run",
+                otel.kind = "producer",
+                messaging.message.id = "SetDevicePowerMessage",
+                messaging.operation.name = "send",
+                messaging.operation.type = "send",
+                messaging.destination.name = "CoordinatorActor",
+                schedule.name = %schedule.name,
+                device.name = %schedule.device,
+                otel.status_code = tracing::field::Empty,
+                exception.type = tracing::field::Empty,
+                exception.message = tracing::field::Empty,
+                exception.stacktrace = tracing::field::Empty,
+            );
+
+            if let Some(until) = self.overridden_until(&schedule.device, now) {
+                let _enter = span.enter();
+                info!(
+                    "Skipping schedule '{}', '{}' is overridden until {until}",
+                    schedule.name, schedule.device
+                );
+                continue;
+            }
+
+            if self.in_flight.is_closed() {
+                return;
+            }
+
+            let fut = Self::run(
+                self.coordinator_actor_addr.clone(),
+                schedule,
+                span.context(),
+            );
+            let fut = self
+                .in_flight
+                .track_future(fut)
+                .instrument(span)
+                .into_actor(self);
+
+            ctx.spawn(fut);
+        }
+
+        self.overrides.retain(|_, until| *until > now);
+    }
+}
+
+impl Handler<ManualOverrideMessage> for SchedulerActor {
+    type Result = ();
+
+    #[instrument(
+        name = "SchedulerActor::Handler<ManualOverrideMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ManualOverrideMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "SchedulerActor",
+            device.name = %message.device_name,
+        )
+    )]
+    fn handle(&mut self, message: ManualOverrideMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        if self.config.override_s == 0 {
+            return;
+        }
+
+        let override_s = i64::try_from(self.config.override_s).unwrap_or(i64::MAX);
+        let until = Utc::now() + TimeDelta::seconds(override_s);
+
+        self.overrides.insert(message.device_name, until);
+    }
+}
+
+impl Handler<GetSchedulesMessage> for SchedulerActor {
+    type Result = MessageResult<GetSchedulesMessage>;
+
+    #[instrument(
+        name = "SchedulerActor::Handler<GetSchedulesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetSchedulesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "SchedulerActor",
+        )
+    )]
+    fn handle(&mut self, message: GetSchedulesMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let now = Utc::now();
+
+        let schedules = self
+            .config
+            .schedules
+            .iter()
+            .map(|schedule| ScheduleStatus {
+                schedule: schedule.clone(),
+                next_run: self.next_runs.get(&schedule.name).copied(),
+                overridden_until: self.overridden_until(&schedule.device, now),
+            })
+            .collect();
+
+        MessageResult(Some(Ok(schedules)))
+    }
+}
+
+impl Handler<SetScheduleMessage> for SchedulerActor {
+    type Result = MessageResult<SetScheduleMessage>;
+
+    #[instrument(
+        name = "SchedulerActor::Handler<SetScheduleMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "SetScheduleMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "SchedulerActor",
+            schedule.name = %message.schedule.name,
+        )
+    )]
+    fn handle(&mut self, message: SetScheduleMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let schedule = message.schedule;
+        self.plan(&schedule);
+
+        let existing = self
+            .config
+            .schedules
+            .iter_mut()
+            .find(|existing| existing.name == schedule.name);
+
+        let replaced = match existing {
+            Some(existing) => {
+                *existing = schedule;
+                true
+            }
+            None => {
+                self.config.schedules.push(schedule);
+                false
+            }
+        };
+
+        MessageResult(Some(Ok(replaced)))
+    }
+}
+
+impl Handler<DeleteScheduleMessage> for SchedulerActor {
+    type Result = MessageResult<DeleteScheduleMessage>;
+
+    #[instrument(
+        name = "SchedulerActor::Handler<DeleteScheduleMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeleteScheduleMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "SchedulerActor",
+            schedule.name = %message.name,
+        )
+    )]
+    fn handle(&mut self, message: DeleteScheduleMessage, _: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        let count = self.config.schedules.len();
+        self.config
+            .schedules
+            .retain(|schedule| schedule.name != message.name);
+        self.next_runs.remove(&message.name);
+
+        MessageResult(Some(Ok(self.config.schedules.len() != count)))
+    }
+}
+
+impl Handler<ShutdownMessage> for SchedulerActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    #[instrument(
+        name = "SchedulerActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "SchedulerActor",
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // let the devices that are being switched finish, no new runs are started
+        self.in_flight.close();
+        let in_flight = self.in_flight.clone();
+
+        let fut = async move { in_flight.wait().await }
+            .instrument(span)
+            .into_actor(self)
+            .map(|_, _, ctx| ctx.stop());
+
+        Box::pin(fut)
+    }
+}
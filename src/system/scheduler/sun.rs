@@ -0,0 +1,57 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::settings::{Location, SunEvent};
+
+/// Julian date of the Unix epoch.
+const UNIX_EPOCH_JULIAN_DATE: f64 = 2_440_587.5;
+/// Julian date of the J2000 epoch, 2000-01-01 12:00 UTC.
+const J2000_JULIAN_DATE: f64 = 2_451_545.0;
+/// Altitude of the sun's center at sunrise/sunset, accounting for refraction and its radius.
+const SUN_ALTITUDE_DEG: f64 = -0.833;
+/// Obliquity of the ecliptic.
+const EARTH_TILT_DEG: f64 = 23.4397;
+
+/// When `event` happens on `date` at `location`, following the sunrise equation.
+///
+/// Returns `None` when the sun doesn't rise or set that day, i.e. during polar days and nights.
+pub fn sun_event_time(
+    event: SunEvent,
+    location: Location,
+    date: NaiveDate,
+) -> Option<DateTime<Utc>> {
+    let days_since_j2000 = (date - NaiveDate::from_ymd_opt(2000, 1, 1)?).num_days() as f64;
+
+    // mean solar time at the location
+    let solar_time = days_since_j2000 - location.longitude / 360.0;
+
+    let mean_anomaly = (357.5291 + 0.985_600_28 * solar_time).rem_euclid(360.0);
+    let m = mean_anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+
+    let transit = J2000_JULIAN_DATE + solar_time + 0.0053 * m.sin()
+        - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * EARTH_TILT_DEG.to_radians().sin()).asin();
+    let latitude = location.latitude.to_radians();
+
+    let cos_hour_angle = (SUN_ALTITUDE_DEG.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+
+    let julian_date = match event {
+        SunEvent::Sunrise => transit - hour_angle,
+        SunEvent::Sunset => transit + hour_angle,
+    };
+
+    let timestamp_ms = ((julian_date - UNIX_EPOCH_JULIAN_DATE) * 86_400_000.0).round() as i64;
+
+    DateTime::from_timestamp_millis(timestamp_ms)
+}
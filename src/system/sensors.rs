@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tapo::responses::{ChildDeviceHubResult, Status, T100Log, T110Log, TemperatureUnit};
+
+use crate::settings::SensorEventKind;
+
+/// A sensor paired with a hub, with its latest reading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sensor {
+    pub device_id: String,
+    pub nickname: String,
+    /// Model of the sensor, e.g. T110.
+    pub model: String,
+    /// Whether the hub can currently reach the sensor.
+    pub online: bool,
+    pub low_battery: bool,
+    pub signal_level: u8,
+    #[serde(flatten)]
+    pub reading: SensorReading,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SensorReading {
+    /// Temperature and humidity sensor, e.g. T310 or T315.
+    Climate {
+        temperature: f32,
+        temperature_unit: TemperatureUnit,
+        /// Relative humidity, in percent.
+        humidity: u8,
+    },
+    /// Contact sensor, e.g. T110.
+    Contact { open: bool },
+    /// Motion sensor, e.g. T100.
+    Motion { detected: bool },
+}
+
+impl Sensor {
+    /// `None` for the child devices that aren't sensors, or that aren't supported yet.
+    pub fn from_child(child: ChildDeviceHubResult) -> Option<Self> {
+        macro_rules! sensor {
+            ($model:expr, $result:expr, $reading:expr) => {
+                Self {
+                    device_id: $result.device_id,
+                    nickname: $result.nickname,
+                    model: $model.to_string(),
+                    online: $result.status == Status::Online,
+                    low_battery: $result.at_low_battery,
+                    signal_level: $result.signal_level,
+                    reading: $reading,
+                }
+            };
+        }
+
+        let sensor = match child {
+            ChildDeviceHubResult::T31X(result) => {
+                let reading = SensorReading::Climate {
+                    temperature: result.current_temperature,
+                    temperature_unit: result.temperature_unit.clone(),
+                    humidity: result.current_humidity,
+                };
+
+                // the client doesn't tell T310 and T315 sensors apart
+                sensor!("T31X", result, reading)
+            }
+            ChildDeviceHubResult::T110(result) => {
+                let reading = SensorReading::Contact { open: result.open };
+
+                sensor!("T110", result, reading)
+            }
+            ChildDeviceHubResult::T100(result) => {
+                let reading = SensorReading::Motion {
+                    detected: result.detected,
+                };
+
+                sensor!("T100", result, reading)
+            }
+            _ => return None,
+        };
+
+        Some(sensor)
+    }
+}
+
+/// An entry of the trigger log a contact or motion sensor keeps on the hub.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorEvent {
+    pub device_id: String,
+    pub nickname: String,
+    /// Increases with every event of the sensor.
+    pub id: u64,
+    pub event: SensorEventKind,
+    /// When the sensor triggered, rather than when the event was read.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl SensorEvent {
+    fn new(sensor: &Sensor, id: u64, event: SensorEventKind, timestamp: u64) -> Self {
+        Self {
+            device_id: sensor.device_id.clone(),
+            nickname: sensor.nickname.clone(),
+            id,
+            event,
+            timestamp: i64::try_from(timestamp)
+                .ok()
+                .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn from_t110(sensor: &Sensor, log: T110Log) -> Self {
+        match log {
+            T110Log::Open { id, timestamp } => {
+                Self::new(sensor, id, SensorEventKind::Open, timestamp)
+            }
+            T110Log::Close { id, timestamp } => {
+                Self::new(sensor, id, SensorEventKind::Close, timestamp)
+            }
+            T110Log::KeepOpen { id, timestamp } => {
+                Self::new(sensor, id, SensorEventKind::KeepOpen, timestamp)
+            }
+        }
+    }
+
+    pub fn from_t100(sensor: &Sensor, log: T100Log) -> Self {
+        match log {
+            T100Log::Motion { id, timestamp } => {
+                Self::new(sensor, id, SensorEventKind::Motion, timestamp)
+            }
+        }
+    }
+
+    /// Whether the event comes from the sensor with the given device id or nickname.
+    pub fn is_from(&self, sensor: &str) -> bool {
+        self.device_id == sensor || self.nickname == sensor
+    }
+}
+
+/// Keeps the events of a sensor's trigger log that are newer than `last_event_id`, oldest first.
+///
+/// Returns the id of the newest event alongside, to resume from on the next read. Nothing is kept
+/// without a `last_event_id`, so the log isn't replayed every time the service starts.
+pub fn unseen_events(
+    mut events: Vec<SensorEvent>,
+    last_event_id: Option<u64>,
+) -> (Vec<SensorEvent>, Option<u64>) {
+    let newest_id = events.iter().map(|event| event.id).max().max(last_event_id);
+
+    match last_event_id {
+        Some(last_event_id) => events.retain(|event| event.id > last_event_id),
+        None => events.clear(),
+    }
+
+    events.sort_by_key(|event| event.id);
+
+    (events, newest_id)
+}
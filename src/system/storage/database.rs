@@ -0,0 +1,340 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use tapo::responses::DeviceUsageEnergyMonitoringResult;
+
+use crate::settings::{Device, Storage};
+use crate::system::cycles::Cycle;
+use crate::system::energy::{EnergyInterval, EnergyWatermark};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS samples (
+        device_name TEXT NOT NULL,
+        resolution TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        time_usage_today INTEGER,
+        time_usage_past7 INTEGER,
+        time_usage_past30 INTEGER,
+        power_usage_today INTEGER,
+        power_usage_past7 INTEGER,
+        power_usage_past30 INTEGER,
+        PRIMARY KEY (device_name, resolution, timestamp)
+    );
+    CREATE INDEX IF NOT EXISTS samples_device_name_timestamp ON samples (device_name, timestamp);
+    CREATE TABLE IF NOT EXISTS energy_watermarks (
+        device_name TEXT NOT NULL,
+        interval TEXT NOT NULL,
+        start_date_time INTEGER NOT NULL,
+        PRIMARY KEY (device_name, interval)
+    );
+    CREATE TABLE IF NOT EXISTS cycles (
+        device_name TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER NOT NULL,
+        energy_wh INTEGER NOT NULL,
+        PRIMARY KEY (device_name, started_at)
+    );
+";
+
+const COLUMNS: &str = "time_usage_today, time_usage_past7, time_usage_past30, \
+    power_usage_today, power_usage_past7, power_usage_past30";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    Raw,
+    Hourly,
+    Daily,
+}
+
+impl Resolution {
+    fn as_str(self) -> &'static str {
+        match self {
+            Resolution::Raw => "raw",
+            Resolution::Hourly => "hourly",
+            Resolution::Daily => "daily",
+        }
+    }
+
+    fn bucket_s(self) -> i64 {
+        match self {
+            Resolution::Raw => 1,
+            Resolution::Hourly => 60 * 60,
+            Resolution::Daily => 24 * 60 * 60,
+        }
+    }
+}
+
+/// A device usage sample. The values are the counters reported by the device at `timestamp`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    pub timestamp: DateTime<Utc>,
+    pub time_usage_today: Option<u64>,
+    pub time_usage_past7: Option<u64>,
+    pub time_usage_past30: Option<u64>,
+    pub power_usage_today: Option<u64>,
+    pub power_usage_past7: Option<u64>,
+    pub power_usage_past30: Option<u64>,
+}
+
+impl Sample {
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        device: &Device,
+        device_usage: &DeviceUsageEnergyMonitoringResult,
+    ) -> Self {
+        let time_usage = |value: Option<u64>| value.filter(|_| device.record_time_usage);
+
+        Self {
+            timestamp,
+            time_usage_today: time_usage(device_usage.time_usage.today),
+            time_usage_past7: time_usage(device_usage.time_usage.past7),
+            time_usage_past30: time_usage(device_usage.time_usage.past30),
+            power_usage_today: device_usage.power_usage.today,
+            power_usage_past7: device_usage.power_usage.past7,
+            power_usage_past30: device_usage.power_usage.past30,
+        }
+    }
+}
+
+/// SQLite backed device usage history.
+///
+/// Samples start out as `raw` and are downsampled to `hourly` and then `daily` as they age,
+/// keeping the last sample of every hour/day since the values are counters.
+#[derive(Debug)]
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Opens the database at `path`, creating it if needed. Use `:memory:` for a throwaway one.
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self { connection })
+    }
+
+    pub fn insert(&self, device_name: &str, sample: &Sample) -> rusqlite::Result<()> {
+        self.connection.execute(
+            &format!(
+                "INSERT OR REPLACE INTO samples (device_name, resolution, timestamp, {COLUMNS})
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+            ),
+            params![
+                device_name,
+                Resolution::Raw.as_str(),
+                sample.timestamp.timestamp(),
+                sample.time_usage_today,
+                sample.time_usage_past7,
+                sample.time_usage_past30,
+                sample.power_usage_today,
+                sample.power_usage_past7,
+                sample.power_usage_past30,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the samples in `[from, to)`, one per `resolution` bucket.
+    /// Ranges that have already been downsampled are returned at the resolution they're stored at.
+    pub fn query(
+        &self,
+        device_name: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        resolution: Resolution,
+    ) -> rusqlite::Result<Vec<Sample>> {
+        // SQLite takes the bare columns from the row that holds the MAX(timestamp),
+        // i.e. the last sample of every bucket
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT MAX(timestamp), {COLUMNS} FROM samples
+            WHERE device_name = ?1 AND timestamp >= ?2 AND timestamp < ?3
+            GROUP BY timestamp / ?4
+            ORDER BY 1"
+        ))?;
+
+        let samples = statement
+            .query_map(
+                params![
+                    device_name,
+                    from.timestamp(),
+                    to.timestamp(),
+                    resolution.bucket_s()
+                ],
+                |row| {
+                    Ok(Sample {
+                        timestamp: DateTime::from_timestamp(row.get(0)?, 0).unwrap_or_default(),
+                        time_usage_today: row.get(1)?,
+                        time_usage_past7: row.get(2)?,
+                        time_usage_past30: row.get(3)?,
+                        power_usage_today: row.get(4)?,
+                        power_usage_past7: row.get(5)?,
+                        power_usage_past30: row.get(6)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(samples)
+    }
+
+    /// Records the watermark, unless a later one has been recorded already.
+    pub fn save_energy_watermark(&self, watermark: &EnergyWatermark) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT INTO energy_watermarks (device_name, interval, start_date_time)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (device_name, interval)
+            DO UPDATE SET start_date_time = MAX(start_date_time, excluded.start_date_time)",
+            params![
+                watermark.device_name,
+                watermark.interval.as_str(),
+                watermark.start_date_time.timestamp(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn energy_watermarks(&self) -> rusqlite::Result<Vec<EnergyWatermark>> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT device_name, interval, start_date_time FROM energy_watermarks",
+        )?;
+
+        let watermarks = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .filter_map(|row| match row {
+                Ok((device_name, interval, start_date_time)) => Some(Ok(EnergyWatermark {
+                    device_name,
+                    // rows written by a different version are skipped
+                    interval: EnergyInterval::parse(&interval)?,
+                    start_date_time: DateTime::from_timestamp(start_date_time, 0)?,
+                })),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(watermarks)
+    }
+
+    pub fn insert_cycle(&self, device_name: &str, cycle: &Cycle) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO cycles (device_name, started_at, finished_at, energy_wh)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![
+                device_name,
+                cycle.started_at.timestamp(),
+                cycle.finished_at.timestamp(),
+                cycle.energy_wh,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the cycles that started in `[from, to)`.
+    pub fn cycles(
+        &self,
+        device_name: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<Cycle>> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT started_at, finished_at, energy_wh FROM cycles
+            WHERE device_name = ?1 AND started_at >= ?2 AND started_at < ?3
+            ORDER BY started_at",
+        )?;
+
+        let cycles = statement
+            .query_map(
+                params![device_name, from.timestamp(), to.timestamp()],
+                |row| {
+                    let started_at: i64 = row.get(0)?;
+                    let finished_at: i64 = row.get(1)?;
+
+                    Ok(Cycle {
+                        started_at: DateTime::from_timestamp(started_at, 0).unwrap_or_default(),
+                        finished_at: DateTime::from_timestamp(finished_at, 0).unwrap_or_default(),
+                        duration_s: u64::try_from(finished_at - started_at).unwrap_or_default(),
+                        energy_wh: row.get(2)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(cycles)
+    }
+
+    /// Applies the retention policies of `config` as of `now`.
+    pub fn apply_retention(
+        &mut self,
+        config: &Storage,
+        now: DateTime<Utc>,
+    ) -> rusqlite::Result<()> {
+        let days = |days: u64| TimeDelta::days(i64::try_from(days).unwrap_or(i64::MAX));
+
+        let transaction = self.connection.transaction()?;
+
+        downsample(
+            &transaction,
+            Resolution::Raw,
+            Resolution::Hourly,
+            now - days(config.raw_retention_days),
+        )?;
+        downsample(
+            &transaction,
+            Resolution::Hourly,
+            Resolution::Daily,
+            now - days(config.hourly_retention_days),
+        )?;
+
+        if let Some(daily_retention_days) = config.daily_retention_days {
+            let cutoff = (now - days(daily_retention_days)).timestamp();
+
+            transaction.execute(
+                "DELETE FROM samples WHERE resolution = ?1 AND timestamp < ?2",
+                params![Resolution::Daily.as_str(), cutoff],
+            )?;
+            // cycles are kept as long as the coarsest samples
+            transaction.execute("DELETE FROM cycles WHERE started_at < ?1", params![cutoff])?;
+        }
+
+        transaction.commit()
+    }
+}
+
+/// Replaces the `from` samples older than `cutoff` with one `to` sample per bucket.
+fn downsample(
+    connection: &Connection,
+    from: Resolution,
+    to: Resolution,
+    cutoff: DateTime<Utc>,
+) -> rusqlite::Result<()> {
+    // only whole buckets are downsampled, the partial one is left for the next run
+    let bucket_s = to.bucket_s();
+    let cutoff = cutoff.timestamp().div_euclid(bucket_s) * bucket_s;
+
+    connection.execute(
+        &format!(
+            "INSERT OR REPLACE INTO samples (device_name, resolution, timestamp, {COLUMNS})
+            SELECT device_name, ?1, MAX(timestamp), {COLUMNS} FROM samples
+            WHERE resolution = ?2 AND timestamp < ?3
+            GROUP BY device_name, timestamp / ?4"
+        ),
+        params![to.as_str(), from.as_str(), cutoff, bucket_s],
+    )?;
+
+    connection.execute(
+        "DELETE FROM samples WHERE resolution = ?1 AND timestamp < ?2",
+        params![from.as_str(), cutoff],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,352 @@
+use std::time::Duration;
+
+use actix::clock::interval;
+use actix::{Actor, ActorContext, AsyncContext, Context, Handler, MessageResult, WrapFuture};
+use anyhow::Context as _;
+use chrono::Utc;
+use tracing::{error, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::settings::Storage;
+use crate::system::cycles::CycleEvent;
+use crate::system::energy::EnergyWatermark;
+use crate::system::messages::{
+    CycleEventMessage, DeviceUsageMessage, EnergyPublishedMessage, GetDeviceCyclesMessage,
+    GetDeviceHistoryMessage, GetEnergyWatermarksMessage, RunStorageMaintenanceMessage,
+    ShutdownMessage,
+};
+use crate::system::storage::database::{Database, Sample};
+use crate::telemetry::record_error;
+
+/// How often the retention policies are applied.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug)]
+pub struct StorageActor {
+    config: Storage,
+    database: Database,
+}
+
+impl StorageActor {
+    #[instrument(name = "StorageActor::new", skip_all, fields(
+        storage.path = %config.path,
+        otel.status_code = tracing::field::Empty,
+        exception.type = tracing::field::Empty,
+        exception.message = tracing::field::Empty,
+        exception.stacktrace = tracing::field::Empty,
+    ))]
+    pub fn new(config: Storage) -> anyhow::Result<Self> {
+        let span = tracing::Span::current();
+
+        let database = Database::open(&config.path)
+            .inspect_err(|e| {
+                record_error(&span, &e);
+            })
+            .with_context(|| format!("failed to open the database at '{}'", config.path))?;
+
+        Ok(Self { config, database })
+    }
+}
+
+impl Actor for StorageActor {
+    type Context = Context<Self>;
+
+    #[instrument(name = "StorageActor::started", skip_all)]
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let addr = ctx.address();
+
+        let fut = async move {
+            let mut interval = interval(MAINTENANCE_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let span = tracing::info_span!(
+                    "StorageActor::IntervalTick",
+                    otel.kind = "producer",
+                    messaging.message.id = "RunStorageMaintenanceMessage",
+                    messaging.operation.name = "send",
+                    messaging.operation.type = "send",
+                    messaging.destination.name = "StorageActor",
+                    otel.status_code = tracing::field::Empty,
+                    exception.type = tracing::field::Empty,
+                    exception.message = tracing::field::Empty,
+                    exception.stacktrace = tracing::field::Empty,
+                );
+                let _enter = span.enter();
+
+                if let Err(e) = addr.try_send(RunStorageMaintenanceMessage {
+                    span_context: span.context(),
+                }) {
+                    record_error(&span, &e);
+                }
+            }
+        }
+        .into_actor(self);
+
+        ctx.spawn(fut);
+    }
+
+    #[instrument(name = "StorageActor::stopped", level = "error", skip_all)]
+    fn stopped(&mut self, _: &mut Self::Context) {}
+}
+
+impl Handler<DeviceUsageMessage> for StorageActor {
+    type Result = ();
+
+    #[instrument(
+        name = "StorageActor::Handler<DeviceUsageMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "DeviceUsageMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            device.name = %message.device.name,
+            device.ip_address = %message.device.ip_address,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: DeviceUsageMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let sample = Sample::new(Utc::now(), &message.device, &message.device_usage);
+
+        if let Err(e) = self.database.insert(&message.device.name, &sample) {
+            error!("Failed to store the device usage: {e:?}");
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<RunStorageMaintenanceMessage> for StorageActor {
+    type Result = ();
+
+    #[instrument(
+        name = "StorageActor::Handler<RunStorageMaintenanceMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "RunStorageMaintenanceMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: RunStorageMaintenanceMessage,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if let Err(e) = self.database.apply_retention(&self.config, Utc::now()) {
+            error!("Failed to apply the retention policies: {e:?}");
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetDeviceHistoryMessage> for StorageActor {
+    type Result = MessageResult<GetDeviceHistoryMessage>;
+
+    #[instrument(
+        name = "StorageActor::Handler<GetDeviceHistoryMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDeviceHistoryMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            device.name = %message.device_name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: GetDeviceHistoryMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let result = self
+            .database
+            .query(
+                &message.device_name,
+                message.from,
+                message.to,
+                message.resolution,
+            )
+            .inspect_err(|e| record_error(&span, e))
+            .context("failed to query the device history");
+
+        MessageResult(Some(result))
+    }
+}
+
+impl Handler<ShutdownMessage> for StorageActor {
+    type Result = ();
+
+    #[instrument(
+        name = "StorageActor::Handler<ShutdownMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "ShutdownMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+        )
+    )]
+    fn handle(&mut self, message: ShutdownMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let _ = tracing::Span::current().set_parent(message.span_context);
+
+        // samples are written as they arrive, so everything queued before this has been stored
+        ctx.stop();
+    }
+}
+
+impl Handler<EnergyPublishedMessage> for StorageActor {
+    type Result = ();
+
+    #[instrument(
+        name = "StorageActor::Handler<EnergyPublishedMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "EnergyPublishedMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            device.name = %message.watermark.device_name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: EnergyPublishedMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        if let Err(e) = self.database.save_energy_watermark(&message.watermark) {
+            error!("Failed to store the energy watermark: {e:?}");
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetEnergyWatermarksMessage> for StorageActor {
+    type Result = MessageResult<GetEnergyWatermarksMessage>;
+
+    #[instrument(
+        name = "StorageActor::Handler<GetEnergyWatermarksMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetEnergyWatermarksMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(
+        &mut self,
+        message: GetEnergyWatermarksMessage,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let result: anyhow::Result<Vec<EnergyWatermark>> = self
+            .database
+            .energy_watermarks()
+            .inspect_err(|e| record_error(&span, e))
+            .context("failed to read the energy watermarks");
+
+        MessageResult(result)
+    }
+}
+
+impl Handler<CycleEventMessage> for StorageActor {
+    type Result = ();
+
+    #[instrument(
+        name = "StorageActor::Handler<CycleEventMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "CycleEventMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            device.name = %message.device.name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: CycleEventMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        // only finished cycles make it into the history
+        let CycleEvent::CycleFinished(cycle) = message.event else {
+            return;
+        };
+
+        if let Err(e) = self.database.insert_cycle(&message.device.name, &cycle) {
+            error!("Failed to store the cycle: {e:?}");
+            record_error(&span, &e);
+        }
+    }
+}
+
+impl Handler<GetDeviceCyclesMessage> for StorageActor {
+    type Result = MessageResult<GetDeviceCyclesMessage>;
+
+    #[instrument(
+        name = "StorageActor::Handler<GetDeviceCyclesMessage>",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.message.id = "GetDeviceCyclesMessage",
+            messaging.operation.name = "poll",
+            messaging.operation.type = "receive",
+            messaging.destination.name = "StorageActor",
+            device.name = %message.device_name,
+            otel.status_code = tracing::field::Empty,
+            exception.type = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.stacktrace = tracing::field::Empty,
+        )
+    )]
+    fn handle(&mut self, message: GetDeviceCyclesMessage, _: &mut Context<Self>) -> Self::Result {
+        let span = tracing::Span::current();
+        let _ = span.set_parent(message.span_context);
+
+        let result = self
+            .database
+            .cycles(&message.device_name, message.from, message.to)
+            .inspect_err(|e| record_error(&span, e))
+            .context("failed to query the device cycles");
+
+        MessageResult(Some(result))
+    }
+}
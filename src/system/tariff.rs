@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+
+use chrono::Weekday;
+use chrono::{DateTime, Datelike as _, Local, Months, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tapo::responses::DeviceUsageEnergyMonitoringResult;
+
+use crate::settings::{Tariff, TariffBand, TariffDays};
+use crate::system::energy::{EnergyData, EnergyInterval};
+
+impl TariffBand {
+    fn applies_at(&self, time: NaiveDateTime) -> bool {
+        let weekend = matches!(time.weekday(), Weekday::Sat | Weekday::Sun);
+        let day_matches = match self.days {
+            TariffDays::All => true,
+            TariffDays::Weekdays => !weekend,
+            TariffDays::Weekends => weekend,
+        };
+
+        let time = time.time();
+        let time_matches = match self.start.cmp(&self.end) {
+            Ordering::Less => self.start <= time && time < self.end,
+            Ordering::Greater => self.start <= time || time < self.end,
+            Ordering::Equal => true,
+        };
+
+        day_matches && time_matches
+    }
+}
+
+impl Tariff {
+    /// Price per kWh at the given local time.
+    pub fn price_at(&self, time: NaiveDateTime) -> f64 {
+        self.bands
+            .iter()
+            .find(|band| band.applies_at(time))
+            .map_or(self.price_per_kwh, |band| band.price_per_kwh)
+    }
+
+    /// Average price per kWh over `[from, to)`, i.e. assuming the energy was used evenly.
+    ///
+    /// The price only changes at midnight and where a band starts or ends, so the period is cut
+    /// there and every piece is weighted by how long it lasts in local time.
+    pub fn average_price(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+        let local = |time: DateTime<Utc>| time.with_timezone(&Local).naive_local();
+        let (from, to) = (local(from), local(to));
+
+        if self.bands.is_empty() || to <= from {
+            return self.price_at(from);
+        }
+
+        let mut total = 0.0;
+        let mut start = from;
+
+        while start < to {
+            let end = self.next_price_change(start).min(to);
+            total += self.price_at(start) * (end - start).as_seconds_f64();
+            start = end;
+        }
+
+        total / (to - from).as_seconds_f64()
+    }
+
+    /// First time after `time` at which the price may change: the next band start or end on the
+    /// same day, or the next midnight.
+    fn next_price_change(&self, time: NaiveDateTime) -> NaiveDateTime {
+        let midnight = time.date().and_time(NaiveTime::MIN);
+
+        self.bands
+            .iter()
+            .flat_map(|band| [band.start, band.end])
+            .map(|change| time.date().and_time(change))
+            .filter(|change| *change > time)
+            .fold(midnight + TimeDelta::days(1), NaiveDateTime::min)
+    }
+
+    /// Cost of `energy_wh` used evenly over `[from, to)`.
+    pub fn energy_cost(&self, energy_wh: u64, from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+        round(energy_wh as f64 / 1000.0 * self.average_price(from, to))
+    }
+
+    pub fn standing_charge(&self, days: u32) -> f64 {
+        round(self.standing_charge_per_day * f64::from(days))
+    }
+
+    /// Sets the cost of every entry and the currency they're in.
+    pub fn apply(&self, energy_data: &mut EnergyData) {
+        let interval = energy_data.interval;
+
+        for entry in &mut energy_data.entries {
+            let end = entry_end(interval, entry.start_date_time);
+            entry.cost = Some(self.energy_cost(entry.energy, entry.start_date_time, end));
+        }
+
+        energy_data.currency = Some(self.currency.clone());
+    }
+}
+
+fn entry_end(interval: EnergyInterval, start: DateTime<Utc>) -> DateTime<Utc> {
+    match interval {
+        EnergyInterval::Hourly => start + TimeDelta::hours(1),
+        EnergyInterval::Daily => start + TimeDelta::days(1),
+        EnergyInterval::Monthly => start
+            .checked_add_months(Months::new(1))
+            .unwrap_or(start + TimeDelta::days(30)),
+    }
+}
+
+/// Rounds to a hundredth of the smallest unit most currencies have.
+fn round(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodCost {
+    /// Energy in watt-hour (Wh)
+    pub energy_wh: u64,
+    pub cost: f64,
+    pub standing_charge: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageCost {
+    pub currency: String,
+    pub today: Option<PeriodCost>,
+    pub past7: Option<PeriodCost>,
+    pub past30: Option<PeriodCost>,
+}
+
+impl UsageCost {
+    /// Costs the power usage counters of a device as of `now`.
+    ///
+    /// The counters are totals, so the energy is assumed to have been used evenly over every period.
+    pub fn new(
+        tariff: &Tariff,
+        device_usage: &DeviceUsageEnergyMonitoringResult,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let midnight = now
+            .with_timezone(&Local)
+            .date_naive()
+            .and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .map_or(now, |midnight| midnight.to_utc());
+
+        let period = |energy_wh: Option<u64>, from: DateTime<Utc>, days: u32| {
+            energy_wh.map(|energy_wh| PeriodCost {
+                energy_wh,
+                cost: tariff.energy_cost(energy_wh, from, now),
+                standing_charge: tariff.standing_charge(days),
+            })
+        };
+
+        Self {
+            currency: tariff.currency.clone(),
+            today: period(device_usage.power_usage.today, midnight, 1),
+            past7: period(device_usage.power_usage.past7, now - TimeDelta::days(7), 7),
+            past30: period(
+                device_usage.power_usage.past30,
+                now - TimeDelta::days(30),
+                30,
+            ),
+        }
+    }
+}
@@ -12,7 +12,7 @@ use tracing_subscriber::{EnvFilter, layer::SubscriberExt as _};
 
 use crate::settings::Telemetry;
 
-pub fn record_error(span: &Span, e: &impl Error) {
+pub fn record_error(span: &Span, e: &(impl Error + ?Sized)) {
     span.record(semconv::attribute::OTEL_STATUS_CODE, "ERROR");
     span.record(
         semconv::attribute::EXCEPTION_TYPE,
@@ -0,0 +1,183 @@
+use home_automation_tapo::settings::{ApiAuth, ApiScope, ApiToken, ApiUser, Settings};
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, settings};
+
+fn settings_with_auth() -> Settings {
+    let mut settings = settings();
+    settings.api.auth = Some(ApiAuth {
+        tokens: vec![
+            ApiToken {
+                token: "read-token".to_string(),
+                scope: ApiScope::Read,
+            },
+            ApiToken {
+                token: "control-token".to_string(),
+                scope: ApiScope::Control,
+            },
+        ],
+        users: vec![ApiUser {
+            username: "dashboard".to_string(),
+            password: "secret".to_string(),
+            scope: ApiScope::Read,
+        }],
+    });
+    settings
+}
+
+#[actix_rt::test]
+async fn health_check_doesnt_require_credentials() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_auth()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/health-check", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+}
+
+#[actix_rt::test]
+async fn requests_without_valid_credentials_are_rejected() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_auth()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/devices", &app.address);
+
+    for (case, request) in [
+        ("no credentials", client.get(&url)),
+        (
+            "unknown token",
+            client.get(&url).bearer_auth("guessed-token"),
+        ),
+        (
+            "wrong password",
+            client.get(&url).basic_auth("dashboard", Some("guess")),
+        ),
+        (
+            "unknown user",
+            client.get(&url).basic_auth("admin", Some("secret")),
+        ),
+        (
+            "malformed header",
+            client.get(&url).header("Authorization", "Basic !!"),
+        ),
+    ] {
+        // Act
+        let response = request.send().await.expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{case}");
+        assert!(
+            response.headers().contains_key("www-authenticate"),
+            "{case}"
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn read_credentials_are_accepted_for_reads_only() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_auth()).await;
+    let client = reqwest::Client::new();
+
+    for (case, request) in [
+        (
+            "token",
+            client
+                .get(format!("{}/devices", &app.address))
+                .bearer_auth("read-token"),
+        ),
+        (
+            "basic auth",
+            client
+                .get(format!("{}/devices", &app.address))
+                .basic_auth("dashboard", Some("secret")),
+        ),
+    ] {
+        // Act
+        let response = request.send().await.expect("Failed to execute request.");
+
+        // Assert
+        assert!(response.status().is_success(), "{case}");
+    }
+
+    // Act
+    let response = client
+        .post(format!("{}/device", &app.address))
+        .bearer_auth("read-token")
+        .json(&json!({ "ip_address": "127.0.0.1", "device_on": true }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_rt::test]
+async fn control_credentials_are_accepted_for_every_route() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_auth()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let read = client
+        .get(format!("{}/devices", &app.address))
+        .bearer_auth("control-token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let control = client
+        .put(format!("{}/devices/unknown/light", &app.address))
+        .bearer_auth("control-token")
+        .json(&json!({ "brightness": 50 }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(read.status().is_success());
+    // past the authorization, the device isn't known
+    assert_eq!(control.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn websocket_accepts_a_token_in_the_subprotocols() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_auth()).await;
+    let client = reqwest::Client::new();
+    let handshake = |protocols: &str| {
+        client
+            .get(format!("{}/events/ws", &app.address))
+            .header("Connection", "Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .header("Sec-WebSocket-Protocol", protocols)
+    };
+
+    // Act
+    let accepted = handshake("bearer, read-token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let rejected = handshake("bearer, guessed-token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(accepted.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(
+        accepted.headers().get("sec-websocket-protocol"),
+        Some(&"bearer".parse().expect("Failed to parse the header"))
+    );
+    assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use home_automation_tapo::settings::{
+    Automation, AutomationAction, AutomationCondition, Automations, DeviceAction,
+};
+use home_automation_tapo::system::automations::rules::AutomationStatus;
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn automations_returns_not_found_when_there_are_none() {
+    // Arrange
+    let app = TestApp::with_settings(settings()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/automations", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn automations_report_their_state() {
+    // Arrange
+    let mut settings = settings();
+    settings.automations = Some(Automations {
+        rules: vec![Automation {
+            name: "monitor-off".to_string(),
+            conditions: vec![AutomationCondition {
+                device: "desk".to_string(),
+                power_above: None,
+                power_below: Some(5),
+                device_on: None,
+                event: None,
+                sensor: None,
+                for_s: 600,
+            }],
+            window: None,
+            actions: vec![AutomationAction::Device {
+                name: "monitor".to_string(),
+                action: DeviceAction::Off,
+            }],
+        }],
+    });
+    settings.devices = vec![device("desk"), device("monitor")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let mut response = None;
+
+    // the automation actor is started by the first health check, give it a moment
+    for _ in 0..20 {
+        let attempt = client
+            .get(format!("{}/automations/monitor-off", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if attempt.status() != StatusCode::INTERNAL_SERVER_ERROR {
+            response = Some(attempt);
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let missing = client
+        .get(format!("{}/automations/lights-on", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let response = response.expect("The automation actor was never started");
+    assert!(response.status().is_success());
+
+    let status: AutomationStatus = response.json().await.expect("Failed to parse the response");
+    assert_eq!(status.automation.name, "monitor-off");
+    assert!(!status.active);
+    assert_eq!(status.last_triggered, None);
+
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,92 @@
+use home_automation_tapo::settings::{Settings, Tariff};
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+fn settings_with_tariff() -> Settings {
+    let mut settings = settings();
+    settings.tariff = Some(Tariff {
+        currency: "EUR".to_string(),
+        price_per_kwh: 0.3,
+        bands: vec![],
+        standing_charge_per_day: 0.5,
+    });
+    settings.devices = vec![device("washing-machine")];
+
+    settings
+}
+
+#[actix_rt::test]
+async fn cost_returns_not_found_when_the_tariff_is_not_configured() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/washing-machine/cost", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response.text().await.expect("Failed to read the body");
+    assert!(body.contains("tariff is not configured"), "{body}");
+}
+
+#[actix_rt::test]
+async fn cost_returns_not_found_for_unknown_devices() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_tariff()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/dryer/cost", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response.text().await.expect("Failed to read the body");
+    assert!(body.contains("device 'dryer' not found"), "{body}");
+}
+
+#[actix_rt::test]
+async fn metrics_are_exposed_in_the_prometheus_format() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_tariff()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/metrics", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    assert!(content_type.starts_with("text/plain"), "{content_type}");
+
+    // no device has been read yet, only the standing charge is known
+    let body = response.text().await.expect("Failed to read the body");
+    assert!(body.contains("# TYPE tapo_standing_charge gauge"), "{body}");
+    assert!(
+        body.contains(r#"tapo_standing_charge{period="past7",currency="EUR"} 3.5"#),
+        "{body}"
+    );
+    assert!(!body.contains("tapo_power_usage_wh"), "{body}");
+}
@@ -0,0 +1,53 @@
+use home_automation_tapo::settings::Storage;
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn cycles_returns_not_found_when_storage_is_disabled() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/washing-machine/cycles", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn cycles_rejects_an_empty_range() {
+    // Arrange
+    let mut settings = settings();
+    settings.storage = Some(Storage {
+        path: ":memory:".to_string(),
+        raw_retention_days: 1,
+        hourly_retention_days: 7,
+        daily_retention_days: None,
+    });
+    settings.devices = vec![device("washing-machine")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!(
+            "{}/devices/washing-machine/cycles?from=2026-01-02T00:00:00Z&to=2026-01-01T00:00:00Z",
+            &app.address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
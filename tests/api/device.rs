@@ -0,0 +1,46 @@
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::TestApp;
+
+#[actix_rt::test]
+async fn get_device_rejects_unknown_credentials_profiles() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/device", &app.address))
+        .json(&json!({ "ip_address": "127.0.0.1", "credentials": "unknown" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: String = response.json().await.expect("Failed to parse the response");
+    assert_eq!(json, "unknown credentials profile 'unknown'");
+}
+
+#[actix_rt::test]
+async fn toggle_device_rejects_unknown_credentials_profiles() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .post(format!("{}/device/toggle", &app.address))
+        .json(&json!({ "ip_address": "127.0.0.1", "credentials": "unknown" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: String = response.json().await.expect("Failed to parse the response");
+    assert_eq!(json, "unknown credentials profile 'unknown'");
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use home_automation_tapo::settings::Discovery;
+use home_automation_tapo::system::discovery::protocol::{DiscoveredDevice, build_probe, discover};
+use reqwest::StatusCode;
+
+use crate::api::fake_discovery_responder::FakeDiscoveryResponder;
+use crate::api::test_app::{TestApp, settings};
+
+#[actix_rt::test]
+async fn discovery_returns_not_found_when_disabled() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/discovery", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn discovery_lists_responding_devices() {
+    // Arrange
+    let responder = FakeDiscoveryResponder::start("P110(EU)", "AA-BB-CC-DD-EE-FF").await;
+
+    let mut settings = settings();
+    settings.discovery = Some(Discovery {
+        targets: vec!["127.0.0.1".to_string()],
+        port: responder.port,
+        interval_s: 60,
+        timeout_s: 1,
+        auto_register: false,
+    });
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let mut devices: Vec<DiscoveredDevice> = vec![];
+
+    for _ in 0..60 {
+        let response = client
+            .get(format!("{}/discovery", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(response.status().is_success());
+
+        devices = response.json().await.expect("Failed to parse the response");
+        if !devices.is_empty() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    // Assert
+    assert_eq!(
+        devices,
+        vec![DiscoveredDevice {
+            device_id: "80221234567890ABCDEF".to_string(),
+            device_type: "SMART.TAPOPLUG".to_string(),
+            device_model: "P110(EU)".to_string(),
+            ip_address: "127.0.0.1".to_string(),
+            mac: "AA:BB:CC:DD:EE:FF".to_string(),
+        }]
+    );
+}
+
+#[actix_rt::test]
+async fn discovery_skips_the_targets_that_fail() {
+    // Arrange
+    let responder = FakeDiscoveryResponder::start("P110(EU)", "AA-BB-CC-DD-EE-FF").await;
+    let probe = build_probe().expect("Failed to build the probe");
+    // an invalid target, and one the IPv4 socket can't send to
+    let targets = ["not-a-target", "::1", "127.0.0.1"].map(str::to_string);
+
+    // Act
+    let devices = discover(&probe, &targets, responder.port, Duration::from_millis(500))
+        .await
+        .expect("Failed to discover the devices");
+
+    // Assert
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].mac, "AA:BB:CC:DD:EE:FF");
+}
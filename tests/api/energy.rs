@@ -0,0 +1,43 @@
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn energy_returns_not_found_for_unknown_devices() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/dryer/energy", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn energy_rejects_requests_the_devices_do_not_support() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!(
+            "{}/devices/washing-machine/energy?interval=daily&start=2026-05-01",
+            &app.address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use home_automation_tapo::system::events::{DeviceCommand, DeviceEventKind};
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, device, power_strip, settings};
+
+/// Reads the next event off a `text/event-stream` response.
+async fn next_event(response: &mut reqwest::Response) -> (String, serde_json::Value) {
+    let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+        .await
+        .expect("No event was streamed")
+        .expect("Failed to read the stream")
+        .expect("The stream ended");
+    let frame = String::from_utf8(chunk.to_vec()).expect("Expected a text frame");
+
+    let event_type = frame
+        .lines()
+        .find_map(|line| line.strip_prefix("event: "))
+        .expect("Expected an event type");
+    let data = frame
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .expect("Expected some data");
+
+    (
+        event_type.to_string(),
+        serde_json::from_str(data).expect("Failed to parse the event"),
+    )
+}
+
+#[actix_rt::test]
+async fn events_stream_the_command_results() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("kettle"), power_strip("desk")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    let mut events = client
+        .get(format!("{}/events?device=desk&type=command", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(events.status().is_success());
+    assert_eq!(
+        events
+            .headers()
+            .get("content-type")
+            .expect("Expected a content type"),
+        "text/event-stream"
+    );
+
+    // Act
+    // the device actors are started by the first health check, give it a moment
+    for _ in 0..20 {
+        let response = client
+            .put(format!("{}/devices/desk/outlets/1", &app.address))
+            .json(&json!({ "action": "on" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if response.status() != StatusCode::NOT_FOUND {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Assert
+    // nothing answers at the strip's address
+    let (event_type, event) = next_event(&mut events).await;
+    assert_eq!(event_type, "command");
+    assert_eq!(event["device"], "desk");
+
+    let kind: DeviceEventKind = serde_json::from_value(event).expect("Failed to parse the event");
+    let DeviceEventKind::Command {
+        command,
+        success,
+        error,
+        ..
+    } = kind
+    else {
+        panic!("Expected a command result, got {kind:?}");
+    };
+    assert!(matches!(command, DeviceCommand::Outlet { position: 1, .. }));
+    assert!(!success);
+    assert!(error.is_some());
+}
+
+#[actix_rt::test]
+async fn events_reject_unknown_event_types() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    for path in ["events", "events/ws"] {
+        // Act
+        let response = client
+            .get(format!("{}/{path}?type=sample,everything", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{path}");
+    }
+}
@@ -0,0 +1,52 @@
+use serde_json::json;
+use tokio::net::UdpSocket;
+
+/// Answers Tapo discovery probes on a local UDP port as if it were a single device.
+pub struct FakeDiscoveryResponder {
+    pub port: u16,
+}
+
+impl FakeDiscoveryResponder {
+    pub async fn start(device_model: &str, mac: &str) -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind the fake discovery responder");
+        let port = socket
+            .local_addr()
+            .expect("Failed to get the address")
+            .port();
+
+        let payload = json!({
+            "error_code": 0,
+            "result": {
+                "device_id": "80221234567890ABCDEF",
+                "device_type": "SMART.TAPOPLUG",
+                "device_model": device_model,
+                "ip": "127.0.0.1",
+                "mac": mac,
+                "mgt_encrypt_schm": {
+                    "is_support_https": false,
+                    "encrypt_type": "KLAP",
+                    "http_port": 80,
+                    "lv": 2,
+                },
+            },
+        });
+
+        let mut response = vec![0u8; 16];
+        response.extend_from_slice(payload.to_string().as_bytes());
+
+        tokio::spawn(async move {
+            let mut buf = [0; 4096];
+
+            while let Ok((size, source)) = socket.recv_from(&mut buf).await {
+                // only answer what looks like a probe: a version 2 header followed by a payload
+                if size > 16 && buf[0] == 2 {
+                    let _ = socket.send_to(&response, source).await;
+                }
+            }
+        });
+
+        Self { port }
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use home_automation_tapo::settings::{Settings, Storage};
+use home_automation_tapo::system::api::handlers::HistoryResponse;
+use home_automation_tapo::system::storage::database::Resolution;
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+fn settings_with_storage() -> Settings {
+    let path = std::env::temp_dir().join(format!(
+        "home-automation-tapo-{}-{}.db",
+        std::process::id(),
+        rand::random::<u32>()
+    ));
+
+    let mut settings = settings();
+    settings.storage = Some(Storage {
+        path: path.display().to_string(),
+        raw_retention_days: 1,
+        hourly_retention_days: 7,
+        daily_retention_days: None,
+    });
+    settings.devices = vec![device("washing-machine")];
+
+    settings
+}
+
+#[actix_rt::test]
+async fn history_returns_not_found_when_storage_is_disabled() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/washing-machine/history", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn history_returns_not_found_for_unknown_devices() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_storage()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/dryer/history", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn history_rejects_an_empty_range() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_storage()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!(
+            "{}/devices/washing-machine/history?from=2026-01-02T00:00:00Z&to=2026-01-01T00:00:00Z",
+            &app.address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn history_returns_the_requested_range() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_storage()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let mut response = None;
+
+    // the storage actor is started by the first health check, give it a moment
+    for _ in 0..20 {
+        let attempt = client
+            .get(format!(
+                "{}/devices/washing-machine/history?from=2026-01-01T00:00:00Z&to=2026-01-02T00:00:00Z&resolution=hourly",
+                &app.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if attempt.status() != StatusCode::NOT_FOUND {
+            response = Some(attempt);
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Assert
+    let response = response.expect("The storage actor was never started");
+    assert!(response.status().is_success());
+
+    let json: HistoryResponse = response.json().await.expect("Failed to parse the response");
+    assert_eq!(json.name, "washing-machine");
+    assert_eq!(json.resolution, Resolution::Hourly);
+    assert_eq!(
+        json.from,
+        "2026-01-01T00:00:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap()
+    );
+    assert!(json.samples.is_empty());
+}
@@ -0,0 +1,79 @@
+use home_automation_tapo::settings::{Device, DeviceType};
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn light_returns_not_found_for_unknown_devices() {
+    // Arrange
+    let app = TestApp::with_settings(settings()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .put(format!("{}/devices/lamp/light", &app.address))
+        .json(&json!({ "brightness": 50 }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn light_rejects_settings_the_device_type_does_not_support() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![
+        device("kettle"),
+        Device {
+            device_type: DeviceType::Bulb,
+            ..device("lamp")
+        },
+        Device {
+            device_type: DeviceType::ColorBulb,
+            ..device("desk")
+        },
+    ];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    let cases = [
+        (
+            "kettle",
+            json!({ "brightness": 50 }),
+            "isn't supported by a plug",
+        ),
+        (
+            "lamp",
+            json!({ "hue": 120, "saturation": 50 }),
+            "isn't supported by a bulb",
+        ),
+        (
+            "desk",
+            json!({ "lighting_effect": "Aurora" }),
+            "isn't supported by a color bulb",
+        ),
+        ("desk", json!({ "hue": 120 }), "saturation"),
+        ("desk", json!({ "brightness": 0 }), "brightness"),
+        ("desk", json!({}), "requires at least one of"),
+    ];
+
+    for (name, body, error) in cases {
+        // Act
+        let response = client
+            .put(format!("{}/devices/{name}/light", &app.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{body}");
+        let message = response.text().await.expect("Failed to read the response");
+        assert!(message.contains(error), "{body}: {message}");
+    }
+}
@@ -1,2 +1,19 @@
+mod auth;
+mod automations;
+mod cost;
+mod cycles;
+mod device;
+mod discovery;
+mod energy;
+mod events;
+mod fake_discovery_responder;
 mod health_check;
-mod test_app;
+mod history;
+mod light;
+mod outlets;
+mod refresh_rate;
+mod scenes;
+mod schedules;
+mod sensors;
+pub mod test_app;
+mod timer;
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use home_automation_tapo::settings::DeviceType;
+use home_automation_tapo::system::device_status::DeviceSummary;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, device, power_strip, settings};
+
+#[actix_rt::test]
+async fn devices_lists_the_configured_devices() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("kettle"), power_strip("desk")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let devices: Vec<DeviceSummary> = response.json().await.expect("Failed to parse the response");
+
+    assert_eq!(devices.len(), 2);
+    assert_eq!(devices[0].name, "kettle");
+    assert_eq!(devices[0].device_type, DeviceType::Plug);
+    assert_eq!(devices[1].name, "desk");
+    assert_eq!(devices[1].device_type, DeviceType::PowerStrip);
+    // nothing answers at the strip's address, so no outlets were reported
+    assert!(devices[1].outlets.is_empty());
+}
+
+#[actix_rt::test]
+async fn outlets_return_not_found_for_unknown_devices_and_plugs() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("kettle")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    for name in ["desk", "kettle"] {
+        // Act
+        let response = client
+            .put(format!("{}/devices/{name}/outlets/1", &app.address))
+            .json(&json!({ "action": "on" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{name}");
+    }
+}
+
+#[actix_rt::test]
+async fn outlets_report_unreachable_power_strips() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![power_strip("desk")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let mut response = None;
+
+    // the device actors are started by the first health check, give it a moment
+    for _ in 0..20 {
+        let attempt = client
+            .put(format!("{}/devices/desk/outlets/1", &app.address))
+            .json(&json!({ "action": "toggle" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if attempt.status() != StatusCode::NOT_FOUND {
+            response = Some(attempt);
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Assert
+    let response = response.expect("The device actor was never started");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use home_automation_tapo::system::api::handlers::RefreshRateResponse;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn set_refresh_rate_updates_the_device() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let mut response = None;
+
+    // the device actors are started by the first health check, give it a moment
+    for _ in 0..20 {
+        let attempt = client
+            .put(format!(
+                "{}/devices/washing-machine/refresh-rate",
+                &app.address
+            ))
+            .json(&json!({ "refresh_rate_s": 15 }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if attempt.status() != StatusCode::NOT_FOUND {
+            response = Some(attempt);
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Assert
+    let response = response.expect("The device actor was never started");
+    assert!(response.status().is_success());
+
+    let json: RefreshRateResponse = response.json().await.expect("Failed to parse the response");
+    assert_eq!(
+        json,
+        RefreshRateResponse {
+            name: "washing-machine".to_string(),
+            refresh_rate_s: 15,
+        }
+    );
+}
+
+#[actix_rt::test]
+async fn set_refresh_rate_returns_not_found_for_unknown_devices() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .put(format!("{}/devices/unknown/refresh-rate", &app.address))
+        .json(&json!({ "refresh_rate_s": 15 }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn set_refresh_rate_rejects_zero() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .put(format!("{}/devices/unknown/refresh-rate", &app.address))
+        .json(&json!({ "refresh_rate_s": 0 }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use home_automation_tapo::settings::{Device, DeviceAction, DeviceType, Scene, SceneState};
+use home_automation_tapo::system::scenes::SceneActivation;
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn scenes_returns_not_found_for_unknown_scenes() {
+    // Arrange
+    let app = TestApp::with_settings(settings()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .post(format!("{}/scenes/movie-night/activate", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn scenes_report_the_devices_that_failed() {
+    // Arrange
+    let state = SceneState {
+        action: DeviceAction::On,
+        brightness: Some(20),
+        hue: None,
+        saturation: None,
+        color_temperature: Some(2700),
+    };
+
+    let mut settings = settings();
+    settings.scenes = vec![Scene {
+        name: "movie-night".to_string(),
+        devices: BTreeMap::from([
+            ("lamp".to_string(), state.clone()),
+            ("tv".to_string(), state),
+        ]),
+    }];
+    // nothing answers for the lamp, and the tv isn't configured at all
+    settings.devices = vec![Device {
+        device_type: DeviceType::Bulb,
+        ..device("lamp")
+    }];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .post(format!("{}/scenes/movie-night/activate", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+    let activation: SceneActivation = response.json().await.expect("Failed to parse the response");
+    assert_eq!(activation.scene, "movie-night");
+
+    let devices = activation
+        .devices
+        .iter()
+        .map(|result| result.device.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(devices, ["lamp", "tv"]);
+    assert!(activation.devices.iter().all(|result| !result.success));
+    assert_eq!(
+        activation.devices[1].error.as_deref(),
+        Some("unknown device")
+    );
+}
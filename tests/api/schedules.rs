@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use home_automation_tapo::settings::{DeviceAction, Scheduler, Settings};
+use home_automation_tapo::system::scheduler::schedule::ScheduleStatus;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+fn settings_with_scheduler() -> Settings {
+    let mut settings = settings();
+    settings.scheduler = Some(Scheduler {
+        location: None,
+        override_s: 3600,
+        schedules: vec![],
+    });
+    settings.devices = vec![device("lamp")];
+
+    settings
+}
+
+#[actix_rt::test]
+async fn schedules_returns_not_found_when_the_scheduler_is_disabled() {
+    // Arrange
+    let app = TestApp::with_settings(settings()).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/schedules", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn schedules_reject_invalid_schedules() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_scheduler()).await;
+    let client = reqwest::Client::new();
+
+    for schedule in [
+        json!({ "name": "lights-on", "device": "lamp", "action": "on", "cron": "0 25 * * *" }),
+        json!({ "name": "lights-on", "device": "dryer", "action": "on", "cron": "0 7 * * *" }),
+        // there's no location to work out the sunset from
+        json!({ "name": "lights-on", "device": "lamp", "action": "on", "sun": "sunset" }),
+    ] {
+        // Act
+        let response = client
+            .post(format!("{}/schedules", &app.address))
+            .json(&schedule)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{schedule}");
+    }
+}
+
+#[actix_rt::test]
+async fn schedules_can_be_added_replaced_and_deleted() {
+    // Arrange
+    let app = TestApp::with_settings(settings_with_scheduler()).await;
+    let client = reqwest::Client::new();
+
+    let schedule = |action: &str| json!({ "name": "lights-on", "device": "lamp", "action": action, "cron": "0 7 * * *" });
+
+    // Act
+    let created = client
+        .post(format!("{}/schedules", &app.address))
+        .json(&schedule("on"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let replaced = client
+        .post(format!("{}/schedules", &app.address))
+        .json(&schedule("toggle"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let mut schedules = None;
+
+    // the scheduler actor is started by the first health check, give it a moment
+    for _ in 0..20 {
+        let attempt = client
+            .get(format!("{}/schedules/lights-on", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if attempt.status() != StatusCode::INTERNAL_SERVER_ERROR {
+            schedules = Some(attempt);
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let deleted = client
+        .delete(format!("{}/schedules/lights-on", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let missing = client
+        .get(format!("{}/schedules/lights-on", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(created.status(), StatusCode::CREATED);
+    assert_eq!(replaced.status(), StatusCode::OK);
+
+    let schedules = schedules.expect("The scheduler actor was never started");
+    assert!(schedules.status().is_success());
+
+    let status: ScheduleStatus = schedules
+        .json()
+        .await
+        .expect("Failed to parse the response");
+    assert_eq!(status.schedule.action, DeviceAction::Toggle);
+    assert!(status.next_run.is_some());
+    assert_eq!(status.overridden_until, None);
+
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
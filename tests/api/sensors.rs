@@ -0,0 +1,60 @@
+use home_automation_tapo::settings::{Device, DeviceType};
+use home_automation_tapo::system::sensors::Sensor;
+use reqwest::StatusCode;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn sensors_are_empty_until_the_hub_reports_them() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![Device {
+        device_type: DeviceType::Hub,
+        ..device("hall")
+    }];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/devices/hall/sensors", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let sensor = client
+        .get(format!("{}/devices/hall/sensors/T310-0001", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    let sensors: Vec<Sensor> = response.json().await.expect("Failed to parse the response");
+    assert!(sensors.is_empty());
+
+    assert_eq!(sensor.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn sensors_return_not_found_for_unknown_devices_and_plugs() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("kettle")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    for name in ["hall", "kettle"] {
+        // Act
+        let response = client
+            .get(format!("{}/devices/{name}/sensors", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{name}");
+    }
+}
@@ -1,4 +1,10 @@
-use home_automation_tapo::{settings::Tapo, system::api::web_server::WebServer};
+use std::collections::HashMap;
+
+use actix::Actor;
+use home_automation_tapo::{
+    settings::{Api, Device, DeviceType, Mqtt, Settings, Shutdown, Tapo, Telemetry},
+    system::{api::web_server::WebServer, coordinator_actor::CoordinatorActor},
+};
 
 pub struct TestApp {
     pub address: String,
@@ -6,13 +12,19 @@ pub struct TestApp {
 
 impl TestApp {
     pub async fn new() -> Self {
-        let tapo = Tapo {
-            username: "".to_string(),
-            password: "".to_string(),
-            refresh_rate_s: 60,
-        };
+        Self::with_settings(settings()).await
+    }
 
-        let web_server = WebServer::new("localhost", 0, tapo)
+    pub async fn with_settings(settings: Settings) -> Self {
+        let auth = settings.api.auth.clone();
+        let tapo = settings.tapo.clone();
+        let tariff = settings.tariff.clone();
+
+        let coordinator_actor_addr = CoordinatorActor::new(settings)
+            .expect("Failed to create the CoordinatorActor")
+            .start();
+
+        let web_server = WebServer::new("localhost", 0, auth, tapo, tariff, coordinator_actor_addr)
             .await
             .expect("Failed to build API");
 
@@ -24,3 +36,71 @@ impl TestApp {
         }
     }
 }
+
+pub fn settings() -> Settings {
+    Settings {
+        telemetry: Telemetry {
+            service_name: "home-automation-tapo".to_string(),
+            service_namespace: "test".to_string(),
+            deployment_environment: "test".to_string(),
+            otlp_endpoint: None,
+        },
+        api: Api {
+            host: "localhost".to_string(),
+            port: 0,
+            auth: None,
+        },
+        tapo: Tapo {
+            username: "".to_string(),
+            password: "".to_string(),
+            credentials: HashMap::new(),
+            refresh_rate_s: 60,
+            poll_jitter_s: 0,
+        },
+        mqtt: Mqtt {
+            address: "tcp://localhost:1883".to_string(),
+            topic_name: "test".to_string(),
+            status_topic_name: None,
+            energy_topic_name: None,
+            alert_topic_name: None,
+            cycle_topic_name: None,
+            scene_topic_name: None,
+            light_topic_name: None,
+            outlet_topic_name: None,
+            sensor_topic_name: None,
+        },
+        discovery: None,
+        energy: None,
+        storage: None,
+        tariff: None,
+        alerts: None,
+        scheduler: None,
+        automations: None,
+        scenes: vec![],
+        shutdown: Shutdown::default(),
+        devices: vec![],
+    }
+}
+
+/// A device that nothing answers for, so its polls fail quickly.
+pub fn device(name: &str) -> Device {
+    Device {
+        ip_address: "127.0.0.1".to_string(),
+        name: name.to_string(),
+        device_type: DeviceType::Plug,
+        record_time_usage: true,
+        refresh_rate_s: None,
+        credentials: None,
+        mac: None,
+        device_id: None,
+        cycle: None,
+    }
+}
+
+/// A power strip that nothing answers for.
+pub fn power_strip(name: &str) -> Device {
+    Device {
+        device_type: DeviceType::PowerStrip,
+        ..device(name)
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use home_automation_tapo::settings::DeviceAction;
+use home_automation_tapo::system::device_status::TimerStatus;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::api::test_app::{TestApp, device, settings};
+
+#[actix_rt::test]
+async fn timer_can_be_set_and_cancelled() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("kettle")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let mut response = None;
+
+    // the device actors are started by the first health check, give it a moment
+    for _ in 0..20 {
+        let attempt = client
+            .post(format!("{}/devices/kettle/timer", &app.address))
+            .json(&json!({ "duration_s": 1200, "action": "off" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if attempt.status() != StatusCode::NOT_FOUND {
+            response = Some(attempt);
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let response = response.expect("The device actor was never started");
+    assert!(response.status().is_success());
+    let timer: TimerStatus = response.json().await.expect("Failed to parse the response");
+
+    let cancelled = client
+        .delete(format!("{}/devices/kettle/timer", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let cancelled_again = client
+        .delete(format!("{}/devices/kettle/timer", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(timer.action, DeviceAction::Off);
+    assert!((1199..=1200).contains(&timer.remaining_s));
+
+    assert_eq!(cancelled.status(), StatusCode::NO_CONTENT);
+    assert_eq!(cancelled_again.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_rt::test]
+async fn timer_rejects_invalid_durations() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("kettle")];
+
+    let app = TestApp::with_settings(settings).await;
+    let client = reqwest::Client::new();
+
+    for duration_s in [0, 24 * 60 * 60 + 1] {
+        // Act
+        let response = client
+            .post(format!("{}/devices/kettle/timer", &app.address))
+            .json(&json!({ "duration_s": duration_s, "action": "off" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{duration_s}");
+    }
+}
+
+#[actix_rt::test]
+async fn timer_returns_not_found_for_unknown_devices() {
+    // Arrange
+    let app = TestApp::new().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let timer = client
+        .post(format!("{}/devices/unknown/timer", &app.address))
+        .json(&json!({ "duration_s": 60, "action": "off" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let status = client
+        .get(format!("{}/devices/unknown", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(timer.status(), StatusCode::NOT_FOUND);
+    assert_eq!(status.status(), StatusCode::NOT_FOUND);
+}
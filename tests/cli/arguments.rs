@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use clap::Parser as _;
+use home_automation_tapo::cli::{Cli, Command, DeviceAction, DevicesCommand};
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("home-automation-tapo").chain(args.iter().copied()))
+        .expect("Failed to parse the arguments")
+}
+
+#[test]
+fn cli_runs_by_default() {
+    // Act
+    let cli = parse(&[]);
+
+    // Assert
+    assert!(cli.command.is_none());
+    assert!(cli.config.is_none());
+}
+
+#[test]
+fn cli_parses_run() {
+    // Act
+    let cli = parse(&["run"]);
+
+    // Assert
+    assert!(matches!(cli.command, Some(Command::Run)));
+}
+
+#[test]
+fn cli_parses_check_config() {
+    // Act
+    let cli = parse(&["check-config", "--config", "settings.toml"]);
+
+    // Assert
+    assert!(matches!(cli.command, Some(Command::CheckConfig)));
+    assert_eq!(cli.config, Some(PathBuf::from("settings.toml")));
+}
+
+#[test]
+fn cli_parses_devices_list() {
+    // Act
+    let cli = parse(&["devices", "list"]);
+
+    // Assert
+    assert!(matches!(
+        cli.command,
+        Some(Command::Devices {
+            command: DevicesCommand::List
+        })
+    ));
+}
+
+#[test]
+fn cli_parses_device_actions() {
+    for (arg, expected) in [
+        ("on", DeviceAction::On),
+        ("off", DeviceAction::Off),
+        ("toggle", DeviceAction::Toggle),
+        ("info", DeviceAction::Info),
+    ] {
+        // Act
+        let cli = parse(&["device", "washing-machine", arg]);
+
+        // Assert
+        let Some(Command::Device { name, action }) = cli.command else {
+            panic!("Expected the device command for '{arg}'");
+        };
+        assert_eq!(name, "washing-machine");
+        assert_eq!(action, expected);
+    }
+}
+
+#[test]
+fn cli_parses_usage() {
+    // Act
+    let cli = parse(&["usage", "washing-machine"]);
+
+    // Assert
+    let Some(Command::Usage { name }) = cli.command else {
+        panic!("Expected the usage command");
+    };
+    assert_eq!(name, "washing-machine");
+}
+
+#[test]
+fn cli_parses_discover() {
+    // Act
+    let cli = parse(&[
+        "discover",
+        "--target",
+        "192.168.1.0/24",
+        "--target",
+        "192.168.2.10",
+        "--timeout-s",
+        "5",
+    ]);
+
+    // Assert
+    let Some(Command::Discover { target, timeout_s }) = cli.command else {
+        panic!("Expected the discover command");
+    };
+    assert_eq!(target, ["192.168.1.0/24", "192.168.2.10"]);
+    assert_eq!(timeout_s, Some(5));
+}
+
+#[test]
+fn cli_accepts_the_config_after_the_subcommand() {
+    // Act
+    let cli = parse(&["devices", "list", "--config", "settings.yaml"]);
+
+    // Assert
+    assert_eq!(cli.config, Some(PathBuf::from("settings.yaml")));
+}
+
+#[test]
+fn cli_rejects_a_device_without_an_action() {
+    // Act
+    let result = Cli::try_parse_from(["home-automation-tapo", "device", "washing-machine"]);
+
+    // Assert
+    assert!(result.is_err());
+}
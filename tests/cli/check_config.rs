@@ -0,0 +1,53 @@
+use home_automation_tapo::cli::commands;
+
+use crate::settings::loading::write_file;
+
+const SETTINGS: &str = r#"
+telemetry:
+  service_name: home-automation-tapo
+  service_namespace: test
+  deployment_environment: test
+api:
+  host: 0.0.0.0
+  port: 80
+tapo:
+  username: user@example.com
+  password: secret
+  refresh_rate_s: 60
+mqtt:
+  address: tcp://localhost:1883
+  topic_name: tapo
+devices:
+  - name: washing-machine
+    ip_address: 192.168.1.10
+    record_time_usage: true
+"#;
+
+#[test]
+fn check_config_accepts_valid_settings() {
+    // Arrange
+    let path = write_file("check-config-valid.yaml", SETTINGS);
+
+    // Act
+    let result = commands::check_config(Some(path));
+
+    // Assert
+    assert!(result.is_ok(), "{result:?}");
+}
+
+#[test]
+fn check_config_reports_invalid_settings() {
+    // Arrange
+    let settings = SETTINGS.replace("refresh_rate_s: 60", "refresh_rate_s: 0");
+    let path = write_file("check-config-invalid.yaml", &settings);
+
+    // Act
+    let error = commands::check_config(Some(path)).expect_err("The settings should be invalid");
+
+    // Assert
+    let message = format!("{error:#}");
+    assert!(
+        message.contains("tapo.refresh_rate_s: must be greater than 0"),
+        "{message}"
+    );
+}
@@ -0,0 +1,3 @@
+mod arguments;
+mod check_config;
+mod usage;
@@ -0,0 +1,42 @@
+use home_automation_tapo::cli::commands;
+
+use crate::settings::loading::write_file;
+
+const SETTINGS: &str = r#"
+telemetry:
+  service_name: home-automation-tapo
+  service_namespace: test
+  deployment_environment: test
+api:
+  host: 0.0.0.0
+  port: 80
+tapo:
+  username: user@example.com
+  password: secret
+  refresh_rate_s: 60
+mqtt:
+  address: tcp://localhost:1883
+  topic_name: tapo
+devices:
+  - name: lamp
+    device_type: color_bulb
+    ip_address: 192.168.1.11
+    record_time_usage: false
+"#;
+
+#[actix_rt::test]
+async fn usage_is_rejected_for_devices_other_than_plugs() {
+    // Arrange
+    let path = write_file("usage-bulb.yaml", SETTINGS);
+
+    // Act
+    let error = commands::usage(Some(path), "lamp")
+        .await
+        .expect_err("Usage should be rejected for a bulb");
+
+    // Assert
+    assert_eq!(
+        error.to_string(),
+        "'lamp' is a color bulb, only plugs report usage"
+    );
+}
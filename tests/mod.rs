@@ -1 +1,4 @@
 mod api;
+mod cli;
+mod settings;
+mod system;
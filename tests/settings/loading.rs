@@ -0,0 +1,260 @@
+use std::path::PathBuf;
+
+use home_automation_tapo::settings::{AutomationAction, DeviceAction, DeviceType, Settings};
+
+const TOML_SETTINGS: &str = r#"
+[telemetry]
+service_name = "home-automation-tapo"
+service_namespace = "test"
+deployment_environment = "test"
+
+[api]
+host = "0.0.0.0"
+port = 80
+
+[tapo]
+username = "user@example.com"
+password = "from-file"
+refresh_rate_s = 60
+
+[mqtt]
+address = "tcp://localhost:1883"
+topic_name = "tapo"
+
+[[devices]]
+name = "washing-machine"
+ip_address = "192.168.1.10"
+record_time_usage = true
+"#;
+
+/// Writes `contents` to a file unique to the calling test and returns its path.
+pub fn write_file(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("home-automation-tapo-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("Failed to create the temp dir");
+
+    let path = dir.join(name);
+    std::fs::write(&path, contents).expect("Failed to write the file");
+
+    path
+}
+
+fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn settings_load_from_toml() {
+    // Arrange
+    let path = write_file("toml.toml", TOML_SETTINGS);
+
+    // Act
+    let settings = Settings::load(Some(path), vars(&[])).expect("Failed to load the settings");
+
+    // Assert
+    assert_eq!(settings.tapo.password, "from-file");
+    assert_eq!(settings.devices.len(), 1);
+    assert_eq!(settings.devices[0].name, "washing-machine");
+}
+
+#[test]
+fn settings_load_from_the_config_env_var() {
+    // Arrange
+    let json = r#"{
+        "telemetry": { "service_name": "hat", "service_namespace": "test", "deployment_environment": "test" },
+        "api": { "host": "0.0.0.0", "port": 8080 },
+        "tapo": { "username": "user@example.com", "password": "secret", "refresh_rate_s": 30 },
+        "mqtt": { "address": "tcp://localhost:1883", "topic_name": "tapo" },
+        "devices": []
+    }"#;
+    let path = write_file("config-env-var.json", json);
+
+    // Act
+    let settings = Settings::load(
+        None,
+        vars(&[("HAT_CONFIG", path.to_str().expect("Invalid path"))]),
+    )
+    .expect("Failed to load the settings");
+
+    // Assert
+    assert_eq!(settings.api.port, 8080);
+    assert_eq!(settings.tapo.refresh_rate_s, 30);
+}
+
+#[test]
+fn settings_are_overridden_by_env_vars() {
+    // Arrange
+    let path = write_file("env-vars.toml", TOML_SETTINGS);
+
+    // Act
+    let settings = Settings::load(
+        Some(path),
+        vars(&[
+            ("HAT__TAPO__PASSWORD", "from-env"),
+            ("HAT__API__PORT", "8080"),
+        ]),
+    )
+    .expect("Failed to load the settings");
+
+    // Assert
+    assert_eq!(settings.tapo.password, "from-env");
+    assert_eq!(settings.api.port, 8080);
+}
+
+#[test]
+fn settings_read_file_env_vars() {
+    // Arrange
+    let path = write_file("file-env-vars.toml", TOML_SETTINGS);
+    let secret_path = write_file("tapo-password", "from-secret\n");
+
+    // Act
+    let settings = Settings::load(
+        Some(path),
+        vars(&[(
+            "HAT__TAPO__PASSWORD_FILE",
+            secret_path.to_str().expect("Invalid path"),
+        )]),
+    )
+    .expect("Failed to load the settings");
+
+    // Assert
+    assert_eq!(settings.tapo.password, "from-secret");
+}
+
+#[test]
+fn settings_reject_unsupported_formats() {
+    // Arrange
+    let path = write_file("settings.ini", "");
+
+    // Act
+    let result = Settings::load(Some(path), vars(&[]));
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[test]
+fn settings_load_automations_from_yaml() {
+    // Arrange
+    let settings = r#"
+telemetry:
+  service_name: home-automation-tapo
+  service_namespace: test
+  deployment_environment: test
+api:
+  host: 0.0.0.0
+  port: 80
+tapo:
+  username: user@example.com
+  password: secret
+  refresh_rate_s: 60
+mqtt:
+  address: tcp://localhost:1883
+  topic_name: tapo
+automations:
+  rules:
+    - name: monitor-off
+      conditions:
+        - device: desk
+          power_below: 5
+          for_s: 600
+      window:
+        start: "08:00"
+        end: "18:00"
+        days: weekdays
+      actions:
+        - device:
+            name: monitor
+            action: off
+        - mqtt:
+            topic: home/desk
+            payload: idle
+        - webhook:
+            url: https://example.com/hooks/desk
+devices:
+  - name: desk
+    ip_address: 192.168.1.10
+    record_time_usage: true
+"#;
+    let path = write_file("automations.yaml", settings);
+
+    // Act
+    let settings = Settings::load(Some(path), vars(&[])).expect("Failed to load the settings");
+
+    // Assert
+    let automations = settings.automations.expect("The automations are missing");
+    let automation = &automations.rules[0];
+
+    assert_eq!(automation.conditions[0].power_below, Some(5));
+    assert_eq!(
+        automation.actions[0],
+        AutomationAction::Device {
+            name: "monitor".to_string(),
+            action: DeviceAction::Off,
+        }
+    );
+    assert!(matches!(
+        &automation.actions[1],
+        AutomationAction::Mqtt { topic, payload } if topic == "home/desk" && payload == "idle"
+    ));
+    assert!(matches!(
+        &automation.actions[2],
+        AutomationAction::Webhook(webhook) if webhook.url == "https://example.com/hooks/desk"
+    ));
+}
+
+#[test]
+fn settings_load_scenes_from_yaml() {
+    // Arrange
+    let settings = r#"
+telemetry:
+  service_name: home-automation-tapo
+  service_namespace: test
+  deployment_environment: test
+api:
+  host: 0.0.0.0
+  port: 80
+tapo:
+  username: user@example.com
+  password: secret
+  refresh_rate_s: 60
+mqtt:
+  address: tcp://localhost:1883
+  topic_name: tapo
+scenes:
+  - name: movie-night
+    devices:
+      tv:
+        action: on
+      lamp:
+        action: on
+        brightness: 20
+        color_temperature: 2700
+devices:
+  - name: tv
+    ip_address: 192.168.1.10
+    record_time_usage: true
+  - name: lamp
+    device_type: color_bulb
+    ip_address: 192.168.1.11
+    record_time_usage: false
+"#;
+    let path = write_file("scenes.yaml", settings);
+
+    // Act
+    let settings = Settings::load(Some(path), vars(&[])).expect("Failed to load the settings");
+
+    // Assert
+    let scene = &settings.scenes[0];
+
+    assert_eq!(scene.name, "movie-night");
+    assert_eq!(scene.devices["tv"].action, DeviceAction::On);
+    assert!(!scene.devices["tv"].has_light_settings());
+    assert_eq!(scene.devices["lamp"].brightness, Some(20));
+    assert_eq!(scene.devices["lamp"].color_temperature, Some(2700));
+    assert_eq!(settings.mqtt.scene_topic(), "tapo/scenes");
+    assert_eq!(settings.devices[0].device_type, DeviceType::Plug);
+    assert_eq!(settings.devices[1].device_type, DeviceType::ColorBulb);
+    assert_eq!(settings.mqtt.light_topic(), "tapo/lights");
+}
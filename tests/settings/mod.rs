@@ -0,0 +1,2 @@
+pub mod loading;
+mod validation;
@@ -0,0 +1,156 @@
+use home_automation_tapo::settings::Settings;
+
+use crate::settings::loading::write_file;
+
+const INVALID_SETTINGS: &str = r#"
+telemetry:
+  service_name: home-automation-tapo
+  service_namespace: test
+  deployment_environment: test
+api:
+  host: 0.0.0.0
+  port: 80
+  auth:
+    tokens:
+      - token: ""
+        scope: read
+    users:
+      - username: admin:root
+        password: ""
+        scope: control
+tapo:
+  username: user@example.com
+  password: ""
+  refresh_rate_s: 0
+mqtt:
+  address: localhost:1883
+  topic_name: tapo
+tariff:
+  currency: ""
+  price_per_kwh: -0.1
+  bands:
+    - price_per_kwh: 0.1
+      start: "00:00"
+      end: "07:00"
+alerts:
+  rules:
+    - name: heater-on-too-long
+      device: heater
+      condition: runtime_today_above
+  webhooks:
+    - url: ftp://example.com/alerts
+scheduler:
+  schedules:
+    - name: lights-on
+      device: lamp
+      action: on
+      cron: "0 25 * * *"
+    - name: lights-off
+      device: lamp
+      action: off
+      sun: sunrise
+automations:
+  rules:
+    - name: monitor-off
+      conditions:
+        - device: desk
+        - device: hall
+          event: open
+          power_above: 3
+          for_s: 5
+      actions:
+        - webhook:
+            url: example.com/hooks/desk
+scenes:
+  - name: movie-night
+    devices:
+      lamp:
+        action: off
+        brightness: 20
+      tv-light:
+        action: on
+        hue: 400
+      dishwasher:
+        action: on
+        brightness: 50
+devices:
+  - name: washing-machine
+    ip_address: 192.168.1.10
+    record_time_usage: true
+  - name: washing-machine
+    ip_address: 192.168.1.300
+    record_time_usage: true
+    credentials: unknown
+  - name: dishwasher
+    ip_address: 192.168.1.10
+    record_time_usage: true
+    mac: not-a-mac
+  - name: porch
+    device_type: bulb
+    ip_address: 192.168.1.20
+    record_time_usage: false
+    cycle:
+      start_threshold_w: 10
+      idle_threshold_w: 2
+      min_idle_s: 60
+"#;
+
+#[test]
+fn settings_report_all_validation_errors() {
+    // Arrange
+    let path = write_file("invalid.yaml", INVALID_SETTINGS);
+
+    // Act
+    let error = Settings::load(Some(path), vec![]).expect_err("The settings should be invalid");
+
+    // Assert
+    let message = format!("{error:#}");
+
+    for expected in [
+        "invalid settings in",
+        "api.auth.tokens[0].token: must not be empty",
+        "api.auth.users[0].username: must not contain ':'",
+        "api.auth.users[0].password: must not be empty",
+        "tapo.password: must not be empty",
+        "tapo.refresh_rate_s: must be greater than 0",
+        "mqtt.address: 'localhost:1883' is missing a scheme",
+        "tariff.currency: must not be empty",
+        "tariff.price_per_kwh: must be a positive number or 0",
+        "alerts.rules[0].threshold: is required by this condition",
+        "alerts.webhooks[0].url: unsupported scheme 'ftp'",
+        "scheduler.schedules[0].cron: '0 25 * * *' is not a valid cron expression",
+        "scheduler.schedules[1].sun: requires scheduler.location",
+        "automations.rules[0].conditions[0].device: requires at least one of power_above, power_below, device_on or event",
+        "automations.rules[0].conditions[1].event: can't be combined with power_above, power_below or device_on",
+        "automations.rules[0].conditions[1].for_s: isn't supported by events",
+        "automations.rules[0].actions[0].webhook.url: 'example.com/hooks/desk' is missing a scheme",
+        "scenes[0].devices.lamp.action: must be on when brightness or color are set",
+        "scenes[0].devices.tv-light.saturation: is required by hue",
+        "scenes[0].devices.dishwasher.brightness: isn't supported by a plug",
+        "devices[1].name: 'washing-machine' is already used by devices[0]",
+        "devices[1].ip_address: '192.168.1.300' is not a valid IP address or hostname",
+        "devices[1].credentials: 'unknown' is not defined under tapo.credentials",
+        "devices[2].ip_address: '192.168.1.10' is already used by devices[0]",
+        "devices[2].mac: 'not-a-mac' is not a valid MAC address",
+        "devices[3].cycle: isn't supported by a bulb",
+    ] {
+        assert!(
+            message.contains(expected),
+            "'{expected}' not found in:\n{message}"
+        );
+    }
+}
+
+#[test]
+fn settings_reject_unknown_keys() {
+    // Arrange
+    let settings = INVALID_SETTINGS.replace("refresh_rate_s: 0", "refresh_rate: 60");
+    let path = write_file("unknown-keys.yaml", &settings);
+
+    // Act
+    let error = Settings::load(Some(path), vec![]).expect_err("The settings should be invalid");
+
+    // Assert
+    let message = format!("{error:#}");
+    assert!(message.contains("refresh_rate"), "{message}");
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::Actor;
+use chrono::{DateTime, TimeDelta, Utc};
+use home_automation_tapo::settings::{AlertCondition, AlertRule, Alerts, Webhook};
+use home_automation_tapo::system::alerts::rules::{AlertState, Observation, RuleState};
+use home_automation_tapo::system::coordinator_actor::CoordinatorActor;
+
+use crate::api::test_app::{device, settings};
+use crate::system::webhook_stand_in::WebhookStandIn;
+
+fn rule(condition: AlertCondition, threshold: Option<u64>) -> AlertRule {
+    AlertRule {
+        name: "washing-machine-finished".to_string(),
+        device: "washing-machine".to_string(),
+        condition,
+        threshold,
+        for_s: 300,
+        hysteresis: 2,
+    }
+}
+
+fn power(current_power: u64) -> Observation {
+    Observation::Usage {
+        current_power,
+        runtime_today: None,
+    }
+}
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().expect("Failed to parse the timestamp")
+}
+
+#[actix_rt::test]
+async fn rules_fire_once_the_condition_held_long_enough_and_resolve_past_the_hysteresis() {
+    // Arrange
+    let rule = rule(AlertCondition::PowerBelow, Some(5));
+    let mut state = RuleState::default();
+    let start = at("2026-01-10T10:00:00Z");
+    let minutes = |minutes: i64| start + TimeDelta::minutes(minutes);
+
+    let observations = [
+        (0, power(1800), None),
+        (1, power(3), None),
+        // the power went back up before `for_s` passed
+        (3, power(1200), None),
+        (4, power(2), None),
+        (8, power(2), None),
+        (9, power(1), Some(AlertState::Firing)),
+        (10, power(1), None),
+        // within the hysteresis
+        (11, power(6), None),
+        (12, power(7), Some(AlertState::Resolved)),
+        // unreachable devices don't affect power rules
+        (13, Observation::Unreachable, None),
+    ];
+
+    for (minute, observation, expected) in observations {
+        // Act
+        let alert = state.evaluate(&rule, observation, minutes(minute));
+
+        // Assert
+        assert_eq!(
+            alert.as_ref().map(|alert| alert.state),
+            expected,
+            "minute {minute}: {alert:?}"
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn unreachable_rules_resolve_on_the_next_successful_poll() {
+    // Arrange
+    let mut rule = rule(AlertCondition::Unreachable, None);
+    rule.for_s = 0;
+    let mut state = RuleState::default();
+    let now = at("2026-01-10T10:00:00Z");
+
+    // Act
+    let fired = state.evaluate(&rule, Observation::Unreachable, now);
+    let repeated = state.evaluate(&rule, Observation::Unreachable, now);
+    let resolved = state.evaluate(&rule, power(0), now);
+
+    // Assert
+    assert_eq!(fired.map(|alert| alert.state), Some(AlertState::Firing));
+    assert_eq!(repeated, None);
+    assert_eq!(
+        resolved.map(|alert| alert.state),
+        Some(AlertState::Resolved)
+    );
+}
+
+#[actix_rt::test]
+async fn alerts_are_delivered_to_webhooks() {
+    // Arrange
+    let mut webhook = WebhookStandIn::start();
+
+    let mut rule = rule(AlertCondition::Unreachable, None);
+    rule.name = "washing-machine-unreachable".to_string();
+    rule.for_s = 0;
+
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine")];
+    settings.alerts = Some(Alerts {
+        rules: vec![rule],
+        webhooks: vec![Webhook {
+            url: webhook.url.clone(),
+            headers: HashMap::new(),
+        }],
+    });
+
+    // Act
+    // nothing answers for the device, so its first poll fails
+    let _coordinator_actor_addr = CoordinatorActor::new(settings)
+        .expect("Failed to create the CoordinatorActor")
+        .start();
+
+    let alert = tokio::time::timeout(Duration::from_secs(10), webhook.alerts.recv())
+        .await
+        .expect("No alert has been delivered")
+        .expect("The webhook stand-in stopped");
+
+    // Assert
+    assert_eq!(alert.rule, "washing-machine-unreachable");
+    assert_eq!(alert.device_name, "washing-machine");
+    assert_eq!(alert.condition, AlertCondition::Unreachable);
+    assert_eq!(alert.state, AlertState::Firing);
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use home_automation_tapo::settings::{
+    Automation, AutomationAction, AutomationCondition, Days, DeviceAction, SensorEventKind,
+    TimeWindow,
+};
+use home_automation_tapo::system::automations::rules::{AutomationState, DeviceState};
+use home_automation_tapo::system::sensors::SensorEvent;
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().expect("Failed to parse the timestamp")
+}
+
+fn condition(device: &str) -> AutomationCondition {
+    AutomationCondition {
+        device: device.to_string(),
+        power_above: None,
+        power_below: None,
+        device_on: None,
+        event: None,
+        sensor: None,
+        for_s: 0,
+    }
+}
+
+fn automation(conditions: Vec<AutomationCondition>, window: Option<TimeWindow>) -> Automation {
+    Automation {
+        name: "monitor-off".to_string(),
+        conditions,
+        window,
+        actions: vec![AutomationAction::Device {
+            name: "monitor".to_string(),
+            action: DeviceAction::Off,
+        }],
+    }
+}
+
+fn devices(states: &[(&str, u64, bool)]) -> HashMap<String, DeviceState> {
+    states
+        .iter()
+        .map(|(name, current_power, device_on)| {
+            (
+                name.to_string(),
+                DeviceState {
+                    current_power: *current_power,
+                    device_on: *device_on,
+                },
+            )
+        })
+        .collect()
+}
+
+fn no_events() -> HashMap<String, Vec<SensorEvent>> {
+    HashMap::new()
+}
+
+#[actix_rt::test]
+async fn automations_trigger_once_the_conditions_held_long_enough() {
+    // Arrange
+    let automation = automation(
+        vec![AutomationCondition {
+            power_below: Some(5),
+            for_s: 600,
+            ..condition("desk")
+        }],
+        None,
+    );
+    let mut state = AutomationState::default();
+    let start = at("2026-01-12T10:00:00Z");
+
+    // Act
+    let triggers: Vec<_> = [80, 3, 2, 4, 1, 90, 2, 2]
+        .into_iter()
+        .enumerate()
+        .map(|(index, current_power)| {
+            let now = start + TimeDelta::minutes(5 * index as i64);
+            state.evaluate(
+                &automation,
+                &devices(&[("desk", current_power, true)]),
+                &no_events(),
+                &now,
+            )
+        })
+        .collect();
+
+    // Assert
+    // fires 10 minutes into the idle spell, and again only after the power went back up
+    assert_eq!(
+        triggers,
+        vec![false, false, false, true, false, false, false, false]
+    );
+    assert_eq!(
+        state.status(&automation).last_triggered,
+        Some(at("2026-01-12T10:15:00Z"))
+    );
+    // re-armed, but not held long enough to trigger again
+    assert!(!state.status(&automation).active);
+}
+
+#[actix_rt::test]
+async fn automations_look_at_other_devices() {
+    // Arrange
+    let automation = automation(
+        vec![
+            AutomationCondition {
+                power_below: Some(5),
+                ..condition("desk")
+            },
+            AutomationCondition {
+                device_on: Some(true),
+                ..condition("monitor")
+            },
+        ],
+        None,
+    );
+    let mut state = AutomationState::default();
+    let now = at("2026-01-12T10:00:00Z");
+
+    // Act
+    let monitor_off = state.evaluate(
+        &automation,
+        &devices(&[("desk", 2, true), ("monitor", 0, false)]),
+        &no_events(),
+        &now,
+    );
+    // devices that haven't been read don't satisfy any condition
+    let monitor_unknown = state.evaluate(
+        &automation,
+        &devices(&[("desk", 2, true)]),
+        &no_events(),
+        &now,
+    );
+    let monitor_on = state.evaluate(
+        &automation,
+        &devices(&[("desk", 2, true), ("monitor", 30, true)]),
+        &no_events(),
+        &now,
+    );
+
+    // Assert
+    assert!(!monitor_off);
+    assert!(!monitor_unknown);
+    assert!(monitor_on);
+}
+
+#[actix_rt::test]
+async fn automations_only_trigger_within_their_window() {
+    // Arrange
+    let window = TimeWindow {
+        start: "08:00:00".parse().unwrap(),
+        end: "18:00:00".parse().unwrap(),
+        days: Days::Weekdays,
+    };
+    let automation = automation(
+        vec![AutomationCondition {
+            power_below: Some(5),
+            ..condition("desk")
+        }],
+        Some(window),
+    );
+    let devices = devices(&[("desk", 2, true)]);
+
+    // Act
+    // 2026-01-10 is a Saturday
+    let weekend = AutomationState::default().evaluate(
+        &automation,
+        &devices,
+        &no_events(),
+        &at("2026-01-10T10:00:00Z"),
+    );
+    let evening = AutomationState::default().evaluate(
+        &automation,
+        &devices,
+        &no_events(),
+        &at("2026-01-12T19:00:00Z"),
+    );
+    let working_hours = AutomationState::default().evaluate(
+        &automation,
+        &devices,
+        &no_events(),
+        &at("2026-01-12T10:00:00Z"),
+    );
+
+    // Assert
+    assert!(!weekend);
+    assert!(!evening);
+    assert!(working_hours);
+}
+
+#[actix_rt::test]
+async fn automations_trigger_on_sensor_events() {
+    // Arrange
+    let automation = automation(
+        vec![AutomationCondition {
+            event: Some(SensorEventKind::Open),
+            sensor: Some("Front door".to_string()),
+            ..condition("hall")
+        }],
+        None,
+    );
+    let event = |nickname: &str, event: SensorEventKind| {
+        HashMap::from([(
+            "hall".to_string(),
+            vec![SensorEvent {
+                device_id: format!("{nickname}-id"),
+                nickname: nickname.to_string(),
+                id: 1,
+                event,
+                timestamp: at("2026-01-12T10:00:00Z"),
+            }],
+        )])
+    };
+    let mut state = AutomationState::default();
+    let now = at("2026-01-12T10:00:00Z");
+
+    // Act
+    let closed = state.evaluate(
+        &automation,
+        &HashMap::new(),
+        &event("Front door", SensorEventKind::Close),
+        &now,
+    );
+    let other_door = state.evaluate(
+        &automation,
+        &HashMap::new(),
+        &event("Back door", SensorEventKind::Open),
+        &now,
+    );
+    let opened = state.evaluate(
+        &automation,
+        &HashMap::new(),
+        &event("Front door", SensorEventKind::Open),
+        &now,
+    );
+    // the events are gone on the next evaluation, which re-arms the automation
+    state.evaluate(&automation, &HashMap::new(), &no_events(), &now);
+    let opened_again = state.evaluate(
+        &automation,
+        &HashMap::new(),
+        &event("Front door", SensorEventKind::Open),
+        &now,
+    );
+
+    // Assert
+    assert!(!closed);
+    assert!(!other_door);
+    assert!(opened);
+    assert!(opened_again);
+}
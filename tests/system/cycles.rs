@@ -0,0 +1,66 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use home_automation_tapo::settings::CycleDetection;
+use home_automation_tapo::system::cycles::{Cycle, CycleDetector, CycleEvent};
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().expect("Failed to parse the timestamp")
+}
+
+fn detector() -> CycleDetector {
+    CycleDetector::new(CycleDetection {
+        start_threshold_w: 100,
+        idle_threshold_w: 5,
+        min_idle_s: 300,
+    })
+}
+
+#[actix_rt::test]
+async fn cycle_detector_ignores_short_pauses() {
+    // Arrange
+    let mut detector = detector();
+    let start = at("2026-01-10T10:00:00Z");
+
+    // Act
+    let events: Vec<_> = [0, 1000, 1000, 2, 1000, 1000, 0, 0, 0, 0, 0, 0]
+        .into_iter()
+        .enumerate()
+        .filter_map(|(minute, current_power)| {
+            detector.observe(current_power, start + TimeDelta::minutes(minute as i64))
+        })
+        .collect();
+
+    // Assert
+    assert_eq!(
+        events,
+        vec![
+            CycleEvent::CycleStarted {
+                started_at: at("2026-01-10T10:01:00Z"),
+            },
+            CycleEvent::CycleFinished(Cycle {
+                started_at: at("2026-01-10T10:01:00Z"),
+                finished_at: at("2026-01-10T10:06:00Z"),
+                duration_s: 300,
+                energy_wh: 58,
+            }),
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn cycle_detector_stays_idle_below_the_start_threshold() {
+    // Arrange
+    let mut detector = detector();
+    let start = at("2026-01-10T10:00:00Z");
+
+    // Act
+    let events: Vec<_> = [0, 50, 99, 20, 0]
+        .into_iter()
+        .enumerate()
+        .filter_map(|(minute, current_power)| {
+            detector.observe(current_power, start + TimeDelta::minutes(minute as i64))
+        })
+        .collect();
+
+    // Assert
+    assert!(events.is_empty());
+}
@@ -0,0 +1,101 @@
+use home_automation_tapo::system::device_status::{DeviceInfo, LightState};
+use serde_json::json;
+
+fn device_info() -> serde_json::Value {
+    json!({
+        "device_id": "8022A1B2C3D4E5F6",
+        "type": "SMART.TAPOPLUG",
+        "model": "P110",
+        "hw_id": "hw",
+        "hw_ver": "1.0",
+        "fw_id": "fw",
+        "fw_ver": "1.3.1 Build 240621 Rel.162048",
+        "oem_id": "oem",
+        "mac": "AA-BB-CC-DD-EE-FF",
+        "ip": "192.168.1.10",
+        // "Home WiFi"
+        "ssid": "SG9tZSBXaUZp",
+        "signal_level": 2,
+        "rssi": -58,
+        "specs": "",
+        "lang": "en_US",
+        "device_on": true,
+        "on_time": 3600,
+        // "Kettle"
+        "nickname": "S2V0dGxl",
+        "avatar": "kettle",
+        "has_set_location_info": false,
+        "overheat_status": "normal",
+    })
+}
+
+#[test]
+fn device_info_decodes_what_the_device_reports() {
+    // Act
+    let device_info = DeviceInfo::try_from(device_info()).expect("Failed to read the device info");
+
+    // Assert
+    assert_eq!(device_info.model, "P110");
+    assert_eq!(device_info.device_type, "SMART.TAPOPLUG");
+    assert_eq!(
+        device_info.firmware_version,
+        "1.3.1 Build 240621 Rel.162048"
+    );
+    assert_eq!(device_info.nickname, "Kettle");
+    assert_eq!(device_info.ssid, "Home WiFi");
+    assert_eq!(device_info.ip_address, "192.168.1.10");
+    assert_eq!(device_info.rssi, -58);
+    assert_eq!(device_info.signal_level, 2);
+    assert_eq!(device_info.device_on, Some(true));
+    assert_eq!(device_info.on_time_s, Some(3600));
+    assert!(!device_info.overheated);
+}
+
+#[test]
+fn device_info_reports_overheated_plugs_and_bulbs() {
+    // Arrange
+    let mut plug = device_info();
+    plug["overheat_status"] = json!("overheated");
+
+    let mut bulb = device_info();
+    bulb["model"] = json!("L530");
+    bulb.as_object_mut()
+        .expect("The device info isn't an object")
+        .remove("overheat_status");
+    bulb["overheated"] = json!(true);
+
+    for device_info in [plug, bulb] {
+        // Act
+        let device_info =
+            DeviceInfo::try_from(device_info).expect("Failed to read the device info");
+
+        // Assert
+        assert!(device_info.overheated, "{}", device_info.model);
+    }
+}
+
+#[test]
+fn light_state_reports_the_running_lighting_effect() {
+    // Arrange
+    let mut light_strip = device_info();
+    light_strip["brightness"] = json!(40);
+    light_strip["hue"] = json!(0);
+    light_strip["saturation"] = json!(100);
+    light_strip["color_temp"] = json!(0);
+    light_strip["lighting_effect"] =
+        json!({ "enable": 1, "name": "Aurora", "id": "TapoStrip_1MClvV18i15Jq3bvJVf0eP" });
+
+    let mut bulb = light_strip.clone();
+    bulb["lighting_effect"]["enable"] = json!(0);
+
+    // Act
+    let light_strip = LightState::try_from(light_strip).expect("Failed to read the light state");
+    let bulb = LightState::try_from(bulb).expect("Failed to read the light state");
+
+    // Assert
+    assert!(light_strip.device_on);
+    assert_eq!(light_strip.brightness, Some(40));
+    assert_eq!(light_strip.saturation, Some(100));
+    assert_eq!(light_strip.lighting_effect.as_deref(), Some("Aurora"));
+    assert_eq!(bulb.lighting_effect, None);
+}
@@ -0,0 +1,128 @@
+use chrono::NaiveDate;
+use home_automation_tapo::system::energy::{EnergyInterval, EnergyRequest};
+
+fn date(date: &str) -> NaiveDate {
+    date.parse().expect("Failed to parse the date")
+}
+
+#[actix_rt::test]
+async fn energy_request_defaults_to_the_current_period() {
+    // Arrange
+    let today = date("2026-08-17");
+
+    // Act
+    let hourly = EnergyRequest::new(EnergyInterval::Hourly, None, None, today);
+    let daily = EnergyRequest::new(EnergyInterval::Daily, None, None, today);
+    let monthly = EnergyRequest::new(EnergyInterval::Monthly, None, None, today);
+
+    // Assert
+    assert_eq!(hourly.map(|request| request.start), Ok(today));
+    assert_eq!(daily.map(|request| request.start), Ok(date("2026-07-01")));
+    assert_eq!(monthly.map(|request| request.start), Ok(date("2026-01-01")));
+}
+
+#[actix_rt::test]
+async fn energy_request_enforces_the_device_limits() {
+    // Arrange
+    let today = date("2026-08-17");
+
+    let cases = [
+        // hourly spans are limited to 8 days
+        (
+            EnergyInterval::Hourly,
+            "2026-08-01",
+            Some("2026-08-09"),
+            true,
+        ),
+        (
+            EnergyInterval::Hourly,
+            "2026-08-01",
+            Some("2026-08-10"),
+            false,
+        ),
+        (
+            EnergyInterval::Hourly,
+            "2026-08-02",
+            Some("2026-08-01"),
+            false,
+        ),
+        // daily breakdowns start on the first day of a quarter
+        (EnergyInterval::Daily, "2026-04-01", None, true),
+        (EnergyInterval::Daily, "2026-05-01", None, false),
+        (
+            EnergyInterval::Daily,
+            "2026-04-01",
+            Some("2026-04-02"),
+            false,
+        ),
+        // monthly breakdowns start on the first day of a year
+        (EnergyInterval::Monthly, "2025-01-01", None, true),
+        (EnergyInterval::Monthly, "2025-02-01", None, false),
+    ];
+
+    for (interval, start, end, valid) in cases {
+        // Act
+        let result = EnergyRequest::new(interval, Some(date(start)), end.map(date), today);
+
+        // Assert
+        assert_eq!(
+            result.is_ok(),
+            valid,
+            "{interval:?} from {start} to {end:?}: {result:?}"
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn backfill_covers_the_completed_periods_since_the_watermark() {
+    // Arrange
+    let today = date("2026-08-17");
+    let oldest = date("2026-01-01");
+
+    // Act
+    let hourly = EnergyRequest::backfill(EnergyInterval::Hourly, date("2026-08-03"), oldest, today);
+    let daily = EnergyRequest::backfill(EnergyInterval::Daily, date("2026-02-10"), oldest, today);
+    let monthly =
+        EnergyRequest::backfill(EnergyInterval::Monthly, date("2025-06-01"), oldest, today);
+    let up_to_date = EnergyRequest::backfill(EnergyInterval::Hourly, today, oldest, today);
+
+    // Assert
+    let spans: Vec<_> = hourly
+        .iter()
+        .map(|request| (request.start, request.end))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (date("2026-08-03"), date("2026-08-11")),
+            (date("2026-08-12"), date("2026-08-16")),
+        ]
+    );
+
+    let starts: Vec<_> = daily.iter().map(|request| request.start).collect();
+    assert_eq!(starts, vec![date("2026-01-01"), date("2026-04-01")]);
+
+    assert!(monthly.is_empty());
+    assert!(up_to_date.is_empty());
+}
+
+#[actix_rt::test]
+async fn backfill_does_not_go_further_back_than_the_oldest_date() {
+    // Arrange
+    let today = date("2026-08-17");
+
+    // Act
+    let hourly = EnergyRequest::backfill(
+        EnergyInterval::Hourly,
+        date("2026-01-01"),
+        date("2026-08-14"),
+        today,
+    );
+
+    // Assert
+    let spans: Vec<_> = hourly
+        .iter()
+        .map(|request| (request.start, request.end))
+        .collect();
+    assert_eq!(spans, vec![(date("2026-08-14"), date("2026-08-16"))]);
+}
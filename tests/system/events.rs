@@ -0,0 +1,84 @@
+use home_automation_tapo::settings::DeviceAction;
+use home_automation_tapo::system::events::{
+    DeviceCommand, DeviceEvent, DeviceEventKind, DeviceEventType, EventFilter,
+};
+use serde_json::json;
+
+fn availability(device: &str) -> DeviceEvent {
+    DeviceEvent::new(device, DeviceEventKind::Availability { available: false })
+}
+
+fn state(device: &str) -> DeviceEvent {
+    DeviceEvent::new(device, DeviceEventKind::State { device_on: true })
+}
+
+#[test]
+fn event_filters_match_the_requested_devices_and_types() {
+    // Arrange
+    let everything = EventFilter::parse(None, None).expect("Failed to parse the filter");
+    let filter = EventFilter::parse(Some("heater, fan"), Some("state,availability"))
+        .expect("Failed to parse the filter");
+
+    // Act & Assert
+    assert!(everything.matches(&availability("kettle")));
+    assert!(everything.matches(&state("heater")));
+
+    assert!(filter.matches(&availability("heater")));
+    assert!(filter.matches(&state("fan")));
+    assert!(!filter.matches(&state("kettle")));
+    assert!(!filter.matches(&DeviceEvent::command(
+        "heater",
+        DeviceCommand::Light,
+        Ok(true)
+    )));
+}
+
+#[test]
+fn event_filters_reject_unknown_types() {
+    // Act
+    let result = EventFilter::parse(None, Some("sample,everything"));
+
+    // Assert
+    assert_eq!(result, Err("unknown event type 'everything'".to_string()));
+}
+
+#[test]
+fn command_events_report_the_outcome() {
+    // Arrange
+    let succeeded = DeviceEvent::command(
+        "heater",
+        DeviceCommand::Outlet {
+            position: 2,
+            action: DeviceAction::Toggle,
+        },
+        Ok(false),
+    );
+    let failed = DeviceEvent::command(
+        "heater",
+        DeviceCommand::Timer {
+            action: DeviceAction::Off,
+        },
+        Err("unreachable".to_string()),
+    );
+
+    // Act
+    let succeeded = json!(succeeded);
+    let failed = json!(failed);
+
+    // Assert
+    assert_eq!(succeeded["type"], "command");
+    assert_eq!(succeeded["command"], "outlet");
+    assert_eq!(succeeded["position"], 2);
+    assert_eq!(succeeded["action"], "toggle");
+    assert_eq!(succeeded["success"], true);
+    assert_eq!(succeeded["device_on"], false);
+    assert_eq!(failed["command"], "timer");
+    assert_eq!(failed["success"], false);
+    assert_eq!(failed["error"], "unreachable");
+    assert_eq!(
+        serde_json::from_value::<DeviceEvent>(failed)
+            .expect("Failed to parse the event")
+            .event_type(),
+        DeviceEventType::Command
+    );
+}
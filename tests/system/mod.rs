@@ -0,0 +1,12 @@
+mod alerts;
+mod automations;
+mod cycles;
+mod device_status;
+mod energy;
+mod events;
+mod scheduler;
+mod sensors;
+mod shutdown;
+mod storage;
+mod tariff;
+mod webhook_stand_in;
@@ -0,0 +1,88 @@
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use home_automation_tapo::settings::{DeviceAction, Location, Schedule, SunEvent};
+use home_automation_tapo::system::scheduler::schedule::{next_run, parse_cron};
+use home_automation_tapo::system::scheduler::sun::sun_event_time;
+
+const LONDON: Location = Location {
+    latitude: 51.5074,
+    longitude: -0.1278,
+};
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().expect("Failed to parse the timestamp")
+}
+
+fn schedule(cron: Option<&str>, sun: Option<SunEvent>, offset_min: i64) -> Schedule {
+    Schedule {
+        name: "lights".to_string(),
+        device: "lamp".to_string(),
+        action: DeviceAction::On,
+        cron: cron.map(ToString::to_string),
+        sun,
+        offset_min,
+        enabled: true,
+    }
+}
+
+fn assert_close(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+    assert!(
+        (actual - expected).abs() < TimeDelta::minutes(3),
+        "{actual} is not close to {expected}"
+    );
+}
+
+#[actix_rt::test]
+async fn cron_expressions_take_five_or_six_fields() {
+    // Assert
+    assert!(parse_cron("30 7 * * MON-FRI").is_ok());
+    assert!(parse_cron("0 30 7 * * MON-FRI").is_ok());
+    assert!(parse_cron("0 25 * * *").is_err());
+    assert!(parse_cron("every morning").is_err());
+}
+
+#[actix_rt::test]
+async fn cron_schedules_run_at_the_next_match() {
+    // Arrange
+    let schedule = schedule(Some("30 7 * * MON-FRI"), None, 0);
+
+    // Act
+    // 2026-01-09 is a Friday
+    let next = next_run(&schedule, None, &at("2026-01-09T08:00:00Z"));
+
+    // Assert
+    assert_eq!(next, Some(at("2026-01-12T07:30:00Z")));
+}
+
+#[actix_rt::test]
+async fn sun_events_follow_the_location() {
+    // Act
+    let date = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+    let sunrise = sun_event_time(SunEvent::Sunrise, LONDON, date).expect("No sunrise");
+    let sunset = sun_event_time(SunEvent::Sunset, LONDON, date).expect("No sunset");
+
+    let tromso = Location {
+        latitude: 69.6492,
+        longitude: 18.9553,
+    };
+
+    // Assert
+    assert_close(sunrise, at("2026-06-21T03:43:00Z"));
+    assert_close(sunset, at("2026-06-21T20:21:00Z"));
+    // midnight sun
+    assert_eq!(sun_event_time(SunEvent::Sunset, tromso, date), None);
+}
+
+#[actix_rt::test]
+async fn sun_schedules_apply_their_offset() {
+    // Arrange
+    let schedule = schedule(None, Some(SunEvent::Sunset), -30);
+
+    // Act
+    let today = next_run(&schedule, Some(LONDON), &at("2026-06-21T12:00:00Z"));
+    let tomorrow = next_run(&schedule, Some(LONDON), &at("2026-06-21T20:00:00Z"));
+
+    // Assert
+    assert_close(today.expect("No run"), at("2026-06-21T19:51:00Z"));
+    assert_close(tomorrow.expect("No run"), at("2026-06-22T19:51:00Z"));
+    assert_eq!(next_run(&schedule, None, &at("2026-06-21T12:00:00Z")), None);
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use home_automation_tapo::settings::SensorEventKind;
+use home_automation_tapo::system::sensors::{Sensor, SensorEvent, SensorReading, unseen_events};
+use serde_json::json;
+use tapo::responses::{ChildDeviceHubResult, TemperatureUnit};
+
+fn child(model: &str, specific: serde_json::Value) -> ChildDeviceHubResult {
+    let mut child = json!({
+        "model": model,
+        "at_low_battery": false,
+        "avatar": "sensor",
+        "bind_count": 1,
+        "category": "subg.trigger",
+        "device_id": format!("{model}-0001"),
+        "fw_ver": "1.9.0",
+        "hw_id": "hw",
+        "hw_ver": "1.0",
+        "jamming_rssi": -110,
+        "jamming_signal_level": 1,
+        "mac": "AABBCCDDEEFF",
+        "nickname": "Hallway",
+        "oem_id": "oem",
+        "parent_device_id": "hub-0001",
+        "region": "Europe/London",
+        "rssi": -60,
+        "signal_level": 3,
+        "specs": "EU",
+        "status": "online",
+        "type": "SMART.TAPOSENSOR",
+        "lastOnboardingTimestamp": 1700000000,
+        "report_interval": 16,
+        "status_follow_edge": false,
+    });
+
+    for (key, value) in specific.as_object().expect("Expected an object") {
+        child[key] = value.clone();
+    }
+
+    serde_json::from_value(child).expect("Failed to read the child device")
+}
+
+#[test]
+fn sensors_are_read_from_the_hub_child_devices() {
+    // Arrange
+    let climate = child(
+        "T310",
+        json!({
+            "current_temp": 21.5,
+            "current_temp_exception": 0.0,
+            "current_humidity": 48,
+            "current_humidity_exception": 0,
+            "temp_unit": "celsius",
+        }),
+    );
+    let contact = child("T110", json!({ "open": true, "at_low_battery": true }));
+    let motion = child("T100", json!({ "detected": false, "status": "offline" }));
+
+    // Act
+    let climate = Sensor::from_child(climate).expect("Expected a sensor");
+    let contact = Sensor::from_child(contact).expect("Expected a sensor");
+    let motion = Sensor::from_child(motion).expect("Expected a sensor");
+
+    // Assert
+    assert_eq!(climate.device_id, "T310-0001");
+    assert_eq!(climate.nickname, "Hallway");
+    assert_eq!(
+        climate.reading,
+        SensorReading::Climate {
+            temperature: 21.5,
+            temperature_unit: TemperatureUnit::Celsius,
+            humidity: 48,
+        }
+    );
+
+    assert_eq!(contact.model, "T110");
+    assert!(contact.low_battery);
+    assert_eq!(contact.reading, SensorReading::Contact { open: true });
+
+    assert!(!motion.online);
+    assert_eq!(motion.reading, SensorReading::Motion { detected: false });
+}
+
+#[test]
+fn sensors_skip_the_child_devices_that_are_not_sensors() {
+    // Arrange
+    let other: ChildDeviceHubResult = serde_json::from_value(json!({ "model": "X999" }))
+        .expect("Failed to read the child device");
+
+    // Act
+    let sensor = Sensor::from_child(other);
+
+    // Assert
+    assert!(sensor.is_none());
+}
+
+fn event(id: u64) -> SensorEvent {
+    SensorEvent {
+        device_id: "T110-0001".to_string(),
+        nickname: "Front door".to_string(),
+        id,
+        event: SensorEventKind::Open,
+        timestamp: DateTime::<Utc>::from_timestamp(1_768_212_000 + id as i64, 0)
+            .expect("Invalid timestamp"),
+    }
+}
+
+#[test]
+fn sensor_events_are_read_incrementally() {
+    // Arrange
+    // the hubs return the newest events first
+    let first_read = vec![event(12), event(11), event(10)];
+    let second_read = vec![event(14), event(13), event(12), event(11)];
+
+    // Act
+    let (on_start, last_event_id) = unseen_events(first_read, None);
+    let (since_start, newest_id) = unseen_events(second_read, last_event_id);
+    let (nothing_new, unchanged_id) = unseen_events(vec![], newest_id);
+
+    // Assert
+    // nothing is replayed when the service starts
+    assert!(on_start.is_empty());
+    assert_eq!(last_event_id, Some(12));
+
+    let ids: Vec<_> = since_start.iter().map(|event| event.id).collect();
+    assert_eq!(ids, vec![13, 14]);
+    assert_eq!(since_start[0].timestamp, event(13).timestamp);
+    assert_eq!(newest_id, Some(14));
+
+    assert!(nothing_new.is_empty());
+    assert_eq!(unchanged_id, Some(14));
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use actix::Actor;
+use home_automation_tapo::system::coordinator_actor::CoordinatorActor;
+use home_automation_tapo::system::messages::ShutdownMessage;
+
+use crate::api::test_app::{device, settings};
+
+#[actix_rt::test]
+async fn shutdown_drains_and_stops_the_coordinator() {
+    // Arrange
+    let mut settings = settings();
+    settings.devices = vec![device("washing-machine"), device("dryer")];
+    settings.devices[1].ip_address = "127.0.0.2".to_string();
+
+    let coordinator_actor_addr = CoordinatorActor::new(settings)
+        .expect("Failed to create the CoordinatorActor")
+        .start();
+
+    // let the first health check start the API and the device actors
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Act
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        coordinator_actor_addr.send(ShutdownMessage {
+            span_context: opentelemetry::Context::new(),
+        }),
+    )
+    .await;
+
+    // Assert
+    assert!(matches!(result, Ok(Ok(()))));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!coordinator_actor_addr.connected());
+}
@@ -0,0 +1,218 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use home_automation_tapo::settings::Storage;
+use home_automation_tapo::system::cycles::Cycle;
+use home_automation_tapo::system::energy::{EnergyInterval, EnergyWatermark};
+use home_automation_tapo::system::storage::database::{Database, Resolution, Sample};
+
+fn sample(timestamp: DateTime<Utc>, power_usage_today: u64) -> Sample {
+    Sample {
+        timestamp,
+        time_usage_today: None,
+        time_usage_past7: None,
+        time_usage_past30: None,
+        power_usage_today: Some(power_usage_today),
+        power_usage_past7: None,
+        power_usage_past30: None,
+    }
+}
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().expect("Failed to parse the timestamp")
+}
+
+fn storage() -> Storage {
+    Storage {
+        path: ":memory:".to_string(),
+        raw_retention_days: 1,
+        hourly_retention_days: 7,
+        daily_retention_days: Some(30),
+    }
+}
+
+#[actix_rt::test]
+async fn query_returns_the_last_sample_of_every_bucket() {
+    // Arrange
+    let database = Database::open(":memory:").expect("Failed to open the database");
+
+    for (timestamp, power_usage_today) in [
+        ("2026-01-10T10:05:00Z", 1),
+        ("2026-01-10T10:35:00Z", 2),
+        ("2026-01-10T11:10:00Z", 3),
+    ] {
+        database
+            .insert("washing-machine", &sample(at(timestamp), power_usage_today))
+            .expect("Failed to insert the sample");
+    }
+    database
+        .insert("dryer", &sample(at("2026-01-10T10:20:00Z"), 10))
+        .expect("Failed to insert the sample");
+
+    // Act
+    let raw = database
+        .query(
+            "washing-machine",
+            at("2026-01-10T00:00:00Z"),
+            at("2026-01-11T00:00:00Z"),
+            Resolution::Raw,
+        )
+        .expect("Failed to query the samples");
+    let hourly = database
+        .query(
+            "washing-machine",
+            at("2026-01-10T00:00:00Z"),
+            at("2026-01-11T00:00:00Z"),
+            Resolution::Hourly,
+        )
+        .expect("Failed to query the samples");
+
+    // Assert
+    assert_eq!(raw.len(), 3);
+    assert_eq!(
+        hourly,
+        vec![
+            sample(at("2026-01-10T10:35:00Z"), 2),
+            sample(at("2026-01-10T11:10:00Z"), 3),
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn retention_downsamples_and_expires_old_samples() {
+    // Arrange
+    let mut database = Database::open(":memory:").expect("Failed to open the database");
+    let now = at("2026-02-01T12:00:00Z");
+
+    for (age, power_usage_today) in [
+        // past the daily retention
+        (TimeDelta::days(40), 1),
+        // past the hourly retention, downsampled to one sample per day
+        (TimeDelta::days(10), 2),
+        (TimeDelta::days(10) - TimeDelta::hours(2), 3),
+        // past the raw retention, downsampled to one sample per hour
+        (TimeDelta::days(3), 4),
+        (TimeDelta::days(3) - TimeDelta::minutes(20), 5),
+        // kept as is
+        (TimeDelta::hours(2), 6),
+        (TimeDelta::hours(1), 7),
+    ] {
+        database
+            .insert("washing-machine", &sample(now - age, power_usage_today))
+            .expect("Failed to insert the sample");
+    }
+
+    // Act
+    database
+        .apply_retention(&storage(), now)
+        .expect("Failed to apply the retention policies");
+
+    // Assert
+    let samples = database
+        .query(
+            "washing-machine",
+            now - TimeDelta::days(365),
+            now,
+            Resolution::Raw,
+        )
+        .expect("Failed to query the samples");
+
+    let power_usage: Vec<_> = samples
+        .iter()
+        .filter_map(|sample| sample.power_usage_today)
+        .collect();
+    assert_eq!(power_usage, vec![3, 5, 6, 7]);
+}
+
+#[actix_rt::test]
+async fn energy_watermarks_only_move_forward() {
+    // Arrange
+    let database = Database::open(":memory:").expect("Failed to open the database");
+
+    let watermark = |interval, start_date_time| EnergyWatermark {
+        device_name: "washing-machine".to_string(),
+        interval,
+        start_date_time,
+    };
+
+    // Act
+    for (interval, start_date_time) in [
+        (EnergyInterval::Hourly, at("2026-01-10T10:00:00Z")),
+        (EnergyInterval::Hourly, at("2026-01-10T12:00:00Z")),
+        // a late backfill must not move the watermark back
+        (EnergyInterval::Hourly, at("2026-01-09T08:00:00Z")),
+        (EnergyInterval::Daily, at("2026-01-10T00:00:00Z")),
+    ] {
+        database
+            .save_energy_watermark(&watermark(interval, start_date_time))
+            .expect("Failed to save the watermark");
+    }
+
+    let mut watermarks = database
+        .energy_watermarks()
+        .expect("Failed to read the watermarks");
+    watermarks.sort_by_key(|watermark| watermark.interval.as_str());
+
+    // Assert
+    assert_eq!(
+        watermarks,
+        vec![
+            watermark(EnergyInterval::Daily, at("2026-01-10T00:00:00Z")),
+            watermark(EnergyInterval::Hourly, at("2026-01-10T12:00:00Z")),
+        ]
+    );
+}
+
+#[actix_rt::test]
+async fn cycles_are_returned_by_start_and_expire_with_the_daily_samples() {
+    // Arrange
+    let mut database = Database::open(":memory:").expect("Failed to open the database");
+
+    let cycle = |started_at: &str, finished_at: &str| {
+        let (started_at, finished_at) = (at(started_at), at(finished_at));
+
+        Cycle {
+            started_at,
+            finished_at,
+            duration_s: (finished_at - started_at).num_seconds() as u64,
+            energy_wh: 800,
+        }
+    };
+
+    for cycle in [
+        cycle("2025-11-01T10:00:00Z", "2025-11-01T11:30:00Z"),
+        cycle("2026-01-10T10:00:00Z", "2026-01-10T11:30:00Z"),
+        cycle("2026-01-10T18:00:00Z", "2026-01-10T19:00:00Z"),
+    ] {
+        database
+            .insert_cycle("washing-machine", &cycle)
+            .expect("Failed to insert the cycle");
+    }
+
+    // Act
+    let cycles = database
+        .cycles(
+            "washing-machine",
+            at("2026-01-10T00:00:00Z"),
+            at("2026-01-10T18:00:00Z"),
+        )
+        .expect("Failed to query the cycles");
+
+    database
+        .apply_retention(&storage(), at("2026-01-11T00:00:00Z"))
+        .expect("Failed to apply the retention");
+
+    let remaining = database
+        .cycles(
+            "washing-machine",
+            at("2025-01-01T00:00:00Z"),
+            at("2027-01-01T00:00:00Z"),
+        )
+        .expect("Failed to query the cycles");
+
+    // Assert
+    assert_eq!(
+        cycles,
+        vec![cycle("2026-01-10T10:00:00Z", "2026-01-10T11:30:00Z")]
+    );
+    assert_eq!(cycles[0].duration_s, 90 * 60);
+    assert_eq!(remaining.len(), 2);
+}
@@ -0,0 +1,126 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use home_automation_tapo::settings::{Tariff, TariffBand, TariffDays};
+use home_automation_tapo::system::energy::{EnergyData, EnergyEntry, EnergyInterval};
+
+fn local(time: &str) -> NaiveDateTime {
+    time.parse().expect("Failed to parse the time")
+}
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().expect("Failed to parse the timestamp")
+}
+
+fn band(price_per_kwh: f64, start: &str, end: &str, days: TariffDays) -> TariffBand {
+    TariffBand {
+        price_per_kwh,
+        start: start.parse().expect("Failed to parse the start"),
+        end: end.parse().expect("Failed to parse the end"),
+        days,
+    }
+}
+
+fn tariff(bands: Vec<TariffBand>) -> Tariff {
+    Tariff {
+        currency: "EUR".to_string(),
+        price_per_kwh: 0.3,
+        bands,
+        standing_charge_per_day: 0.5,
+    }
+}
+
+#[actix_rt::test]
+async fn tariff_prices_follow_the_first_matching_band() {
+    // Arrange
+    let tariff = tariff(vec![
+        band(0.1, "00:00:00", "00:00:00", TariffDays::Weekends),
+        band(0.15, "23:00:00", "07:00:00", TariffDays::All),
+        band(0.4, "17:00:00", "20:00:00", TariffDays::Weekdays),
+    ]);
+
+    let cases = [
+        // Friday
+        ("2026-01-16T12:00:00", 0.3),
+        ("2026-01-16T17:00:00", 0.4),
+        ("2026-01-16T20:00:00", 0.3),
+        ("2026-01-16T23:30:00", 0.15),
+        ("2026-01-16T06:59:00", 0.15),
+        // Saturday
+        ("2026-01-17T18:00:00", 0.1),
+        ("2026-01-17T23:30:00", 0.1),
+    ];
+
+    for (time, expected) in cases {
+        // Act
+        let price = tariff.price_at(local(time));
+
+        // Assert
+        assert_eq!(price, expected, "{time}");
+    }
+}
+
+#[actix_rt::test]
+async fn tariff_average_price_is_weighted_by_time() {
+    // Arrange
+    let tariff = tariff(vec![band(0.1, "00:00:00", "06:00:00", TariffDays::All)]);
+    let from = at("2026-01-15T00:00:00Z");
+
+    // Act
+    let average_price = tariff.average_price(from, from + TimeDelta::days(1));
+
+    // Assert
+    // a whole day covers every band once, whatever the local time zone
+    assert!((average_price - 0.25).abs() < 1e-9, "{average_price}");
+}
+
+#[actix_rt::test]
+async fn tariff_average_price_follows_the_first_matching_band() {
+    // Arrange
+    let tariff = tariff(vec![
+        band(0.1, "00:00:00", "00:00:00", TariffDays::Weekends),
+        band(0.4, "17:00:00", "20:00:00", TariffDays::All),
+    ]);
+    let from = at("2026-01-12T00:00:00Z");
+
+    // Act
+    let average_price = tariff.average_price(from, from + TimeDelta::days(7));
+
+    // Assert
+    // a whole week covers every day once: 2 weekend days at 0.1, 5 weekdays with 3 hours at 0.4
+    let expected = (2.0 * 24.0 * 0.1 + 5.0 * (3.0 * 0.4 + 21.0 * 0.3)) / (7.0 * 24.0);
+    assert!((average_price - expected).abs() < 1e-9, "{average_price}");
+}
+
+#[actix_rt::test]
+async fn tariff_costs_every_energy_entry() {
+    // Arrange
+    let tariff = tariff(vec![]);
+    let mut energy_data = EnergyData {
+        interval: EnergyInterval::Hourly,
+        start_date_time: at("2026-01-15T00:00:00Z"),
+        interval_length: 60,
+        currency: None,
+        entries: vec![
+            EnergyEntry {
+                start_date_time: at("2026-01-15T00:00:00Z"),
+                energy: 500,
+                cost: None,
+            },
+            EnergyEntry {
+                start_date_time: at("2026-01-15T01:00:00Z"),
+                energy: 1250,
+                cost: None,
+            },
+        ],
+    };
+
+    // Act
+    tariff.apply(&mut energy_data);
+
+    // Assert
+    assert_eq!(energy_data.currency.as_deref(), Some("EUR"));
+
+    let costs: Vec<_> = energy_data.entries.iter().map(|entry| entry.cost).collect();
+    assert_eq!(costs, vec![Some(0.15), Some(0.375)]);
+
+    assert_eq!(tariff.standing_charge(7), 3.5);
+}
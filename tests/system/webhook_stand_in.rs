@@ -0,0 +1,50 @@
+use std::net::TcpListener;
+
+use actix_web::{App, HttpResponse, HttpServer, web};
+use home_automation_tapo::system::alerts::rules::Alert;
+use tokio::sync::mpsc;
+
+/// Stands in for a webhook receiver on a local port, handing over every alert posted to it.
+pub struct WebhookStandIn {
+    pub url: String,
+    pub alerts: mpsc::UnboundedReceiver<Alert>,
+}
+
+impl WebhookStandIn {
+    pub fn start() -> Self {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").expect("Failed to bind the webhook stand-in");
+        let port = listener
+            .local_addr()
+            .expect("Failed to get the address")
+            .port();
+
+        let (sender, alerts) = mpsc::unbounded_channel();
+        let sender = web::Data::new(sender);
+
+        let server = HttpServer::new(move || {
+            App::new().app_data(sender.clone()).route(
+                "/alerts",
+                web::post().to(
+                    |sender: web::Data<mpsc::UnboundedSender<Alert>>,
+                     alert: web::Json<Alert>| async move {
+                        let _ = sender.send(alert.into_inner());
+                        HttpResponse::NoContent().finish()
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .expect("Failed to listen to the webhook stand-in socket")
+        .run();
+
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://127.0.0.1:{port}/alerts"),
+            alerts,
+        }
+    }
+}